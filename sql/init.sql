@@ -67,6 +67,56 @@ INSERT INTO api_keys (key_value, user_id, name, permissions) VALUES
     ('sk-0987654321fedcba', 2, 'Developer API Key', ARRAY['read', 'write'])
 ON CONFLICT (key_value) DO NOTHING;
 
+-- Create a table of array columns for checking how arrays are displayed
+CREATE TABLE IF NOT EXISTS array_samples (
+    id SERIAL PRIMARY KEY,
+    scores INTEGER[],
+    tags TEXT[],
+    grid INTEGER[][]
+);
+
+INSERT INTO array_samples (id, scores, tags, grid) VALUES
+    (1, ARRAY[90, 85, 77], ARRAY['plain', 'with,comma', 'with "quotes"'], ARRAY[[1, 2], [3, 4]]),
+    (2, ARRAY[]::INTEGER[], ARRAY['single'], NULL),
+    (3, ARRAY[1, NULL, 3], NULL, ARRAY[[5, 6, 7]])
+ON CONFLICT (id) DO NOTHING;
+
+-- Create a table of wide (Japanese), combining and emoji text for checking column alignment
+CREATE TABLE IF NOT EXISTS wide_text_samples (
+    id SERIAL PRIMARY KEY,
+    city TEXT,
+    note TEXT
+);
+
+INSERT INTO wide_text_samples (id, city, note) VALUES
+    (1, '東京', '日本の首都で、世界有数の大都市です'),
+    (2, 'Zürich', 'Café with a combining accent'),
+    (3, '大阪', 'たこ焼き 🐙 and okonomiyaki')
+ON CONFLICT (id) DO NOTHING;
+
+-- Create a table with enum and composite columns, shown specially in the table view
+DO $$ BEGIN
+    CREATE TYPE mood AS ENUM ('sad', 'ok', 'happy');
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+DO $$ BEGIN
+    CREATE TYPE address AS (street TEXT, city TEXT, zip INTEGER);
+EXCEPTION WHEN duplicate_object THEN NULL;
+END $$;
+
+CREATE TABLE IF NOT EXISTS type_samples (
+    id SERIAL PRIMARY KEY,
+    feeling mood,
+    home address
+);
+
+INSERT INTO type_samples (id, feeling, home) VALUES
+    (1, 'happy', ROW('1 Main St, Apt 2', 'Springfield', 12345)),
+    (2, 'sad', ROW('Elm "Old" Road', NULL, NULL)),
+    (3, NULL, NULL)
+ON CONFLICT (id) DO NOTHING;
+
 -- Create indexes for better performance
 CREATE INDEX IF NOT EXISTS idx_users_username ON users(username);
 CREATE INDEX IF NOT EXISTS idx_projects_owner ON projects(owner_id);
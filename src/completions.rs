@@ -0,0 +1,287 @@
+//! Shell completion scripts that complete saved connection names.
+//!
+//! clap_complete's scripts are static, so positionals taking a connection name (those
+//! with the value name `CONNECTION`) only get file completion from them. For bash, zsh
+//! and fish a little shell code is added that lists the names with the hidden
+//! `__complete-connections` command when one of those positionals is being completed,
+//! passing on a `--config-dir` typed earlier on the line so the names come from there.
+//! The other shells get the static script as it is.
+
+use clap::Command;
+use clap_complete::{Shell, generate};
+
+const CONNECTION: &str = "CONNECTION";
+
+/// The hidden command printing saved connection names, one per line.
+pub const COMPLETE_CONNECTIONS: &str = "__complete-connections";
+
+/// A positional taking a connection name: the subcommand with its aliases, the
+/// positional's place among the subcommand's positionals, and its id and help for zsh.
+#[derive(Debug, PartialEq)]
+struct ConnectionArg {
+    names: Vec<String>,
+    position: usize,
+    id: String,
+    help: String,
+}
+
+/// The completion script for `shell`.
+pub fn script(shell: Shell, mut cmd: Command) -> Vec<u8> {
+    let bin = cmd.get_name().to_string();
+    let mut script = Vec::new();
+    generate(shell, &mut cmd, &bin, &mut script);
+    let script = String::from_utf8_lossy(&script).into_owned();
+
+    let args = connection_args(&cmd);
+    let script = match shell {
+        Shell::Bash => bash(script, &bin, &args, &value_options(&cmd)),
+        Shell::Zsh => zsh(script, &bin, &args),
+        Shell::Fish => fish(script, &bin, &args, &value_options(&cmd)),
+        _ => script,
+    };
+    script.into_bytes()
+}
+
+fn connection_args(cmd: &Command) -> Vec<ConnectionArg> {
+    let mut args = Vec::new();
+    for sub in cmd.get_subcommands() {
+        let mut names = vec![sub.get_name().to_string()];
+        names.extend(sub.get_all_aliases().map(String::from));
+        for (position, arg) in sub.get_positionals().enumerate() {
+            let is_connection = arg
+                .get_value_names()
+                .is_some_and(|value_names| value_names.iter().any(|n| n == CONNECTION));
+            if is_connection {
+                args.push(ConnectionArg {
+                    names: names.clone(),
+                    position,
+                    id: arg.get_id().to_string(),
+                    help: arg.get_help().map(|h| h.to_string()).unwrap_or_default(),
+                });
+            }
+        }
+    }
+    args
+}
+
+// Every option that takes a value, top-level or in a subcommand, as typed on the command
+// line, so the value isn't counted as a positional
+fn value_options(cmd: &Command) -> Vec<String> {
+    let mut options = Vec::new();
+    let commands = std::iter::once(cmd).chain(cmd.get_subcommands());
+    for arg in commands.flat_map(|c| c.get_arguments()) {
+        if arg.is_positional() || !arg.get_action().takes_values() {
+            continue;
+        }
+        options.extend(arg.get_long().map(|long| format!("--{}", long)));
+        options.extend(arg.get_short().map(|short| format!("-{}", short)));
+    }
+    options.sort();
+    options.dedup();
+    options
+}
+
+// The `subcommand,position` pairs that take a connection name
+fn positions(args: &[ConnectionArg]) -> Vec<String> {
+    args.iter()
+        .flat_map(|arg| {
+            arg.names
+                .iter()
+                .map(move |name| format!("{},{}", name, arg.position))
+        })
+        .collect()
+}
+
+fn bash(script: String, bin: &str, args: &[ConnectionArg], options: &[String]) -> String {
+    let function = format!("_{}", bin);
+    let wrapper = format!("_{}_connections", bin);
+    let mut script = script.replace(
+        &format!("complete -F {} ", function),
+        &format!("complete -F {} ", wrapper),
+    );
+    script.push_str(&format!(
+        r#"
+# Complete saved connection names where a connection is expected
+{wrapper}() {{
+    local cur="${{COMP_WORDS[COMP_CWORD]}}" sub="" pos=0 skip=0 option="" word
+    local -a dir_args=()
+    for word in "${{COMP_WORDS[@]:1:COMP_CWORD-1}}"; do
+        if (( skip )); then
+            skip=0
+            [[ ${{option}} == --config-dir ]] && dir_args=(--config-dir "${{word}}")
+            continue
+        fi
+        case "${{word}}" in
+            --config-dir=*) dir_args=(--config-dir "${{word#--config-dir=}}") ;;
+            {options}) skip=1; option="${{word}}" ;;
+            -*) ;;
+            *)
+                if [[ -z "${{sub}}" ]]; then
+                    sub="${{word}}"
+                else
+                    pos=$((pos + 1))
+                fi
+                ;;
+        esac
+    done
+    # Not when completing an option or an option's value
+    if (( ! skip )) && [[ ${{cur}} != -* ]]; then
+        case "${{sub}},${{pos}}" in
+            {positions})
+                COMPREPLY=( $(compgen -W "$({bin} {COMPLETE_CONNECTIONS} "${{dir_args[@]}}" 2>/dev/null)" -- "${{cur}}") )
+                return 0
+                ;;
+        esac
+    fi
+    {function} "$@"
+}}
+"#,
+        options = options.join("|"),
+        positions = positions(args).join("|"),
+    ));
+    script
+}
+
+// clap's zsh script describes each positional as `:id -- help:_default`; the action of
+// the connection ones is swapped for a function listing the names
+fn zsh(script: String, bin: &str, args: &[ConnectionArg]) -> String {
+    let function = format!("_{}_connections", bin);
+    let mut script = script;
+    for arg in args {
+        let spec = format!(":{} -- {}:", arg.id, escape_zsh(&arg.help));
+        script = script.replace(
+            &format!("{}_default'", spec),
+            &format!("{}{}'", spec, function),
+        );
+    }
+    let definition = format!(
+        r#"(( $+functions[{function}] )) ||
+{function}() {{
+    local -a connections dir_args
+    local i
+    for (( i = 2; i < CURRENT; i++ )); do
+        case $words[i] in
+            --config-dir) dir_args=(--config-dir $words[i+1]) ;;
+            --config-dir=*) dir_args=(--config-dir ${{words[i]#--config-dir=}}) ;;
+        esac
+    done
+    connections=(${{(f)"$({bin} {COMPLETE_CONNECTIONS} $dir_args 2>/dev/null)"}})
+    _describe -t connections 'saved connections' connections
+}}
+
+"#
+    );
+    match script.rfind("if [ \"$funcstack[1]\"") {
+        Some(at) => script.insert_str(at, &definition),
+        None => script.push_str(&definition),
+    }
+    script
+}
+
+// The escaping clap_complete gives help text in zsh specs
+fn escape_zsh(help: &str) -> String {
+    help.replace('\\', "\\\\")
+        .replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+        .replace('$', "\\$")
+        .replace('`', "\\`")
+        .replace('\n', " ")
+}
+
+fn fish(script: String, bin: &str, args: &[ConnectionArg], options: &[String]) -> String {
+    let function = format!("__fish_{}_expects_connection", bin.replace('-', "_"));
+    let list = format!("__fish_{}_connections", bin.replace('-', "_"));
+    let mut script = script;
+    script.push_str(&format!(
+        r#"
+# Complete saved connection names where a connection is expected
+function {function}
+	set -l words (commandline -opc)
+	set -e words[1]
+	set -l sub
+	set -l pos 0
+	set -l skip 0
+	for word in $words
+		if test $skip -eq 1
+			set skip 0
+		else if contains -- $word {options}
+			set skip 1
+		else if string match -q -- '-*' $word
+			continue
+		else if test -z "$sub"
+			set sub $word
+		else
+			set pos (math $pos + 1)
+		end
+	end
+	test $skip -eq 0; and contains -- "$sub,$pos" {positions}
+end
+function {list}
+	set -l words (commandline -opc)
+	set -l dir_args
+	for i in (seq (count $words))
+		if test "$words[$i]" = --config-dir; and test $i -lt (count $words)
+			set dir_args --config-dir $words[(math $i + 1)]
+		else if string match -q -- '--config-dir=*' $words[$i]
+			set dir_args --config-dir (string replace -- '--config-dir=' '' $words[$i])
+		end
+	end
+	{bin} {COMPLETE_CONNECTIONS} $dir_args 2>/dev/null
+end
+complete -c {bin} -n {function} -f -a "({list})"
+"#,
+        options = options.join(" "),
+        positions = positions(args).join(" "),
+    ));
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Arg;
+
+    fn command() -> Command {
+        Command::new("tool")
+            .arg(Arg::new("config-dir").long("config-dir"))
+            .subcommand(
+                Command::new("connect")
+                    .arg(Arg::new("name").value_name(CONNECTION).help("Saved one")),
+            )
+            .subcommand(
+                Command::new("remove-query")
+                    .alias("rmq")
+                    .arg(Arg::new("conn").value_name(CONNECTION).help("Its [conn]"))
+                    .arg(Arg::new("name").help("Query name")),
+            )
+            .subcommand(Command::new("ls"))
+    }
+
+    #[test]
+    fn test_connection_args() {
+        let cmd = command();
+        let args = connection_args(&cmd);
+        assert_eq!(positions(&args), ["connect,0", "remove-query,0", "rmq,0"]);
+        assert_eq!(value_options(&cmd), ["--config-dir"]);
+    }
+
+    #[test]
+    fn test_scripts_complete_connections() {
+        let zsh = String::from_utf8(script(Shell::Zsh, command())).unwrap();
+        assert_eq!(zsh.matches(":_tool_connections'").count(), 2);
+        assert!(zsh.contains(":name -- Query name:_default'"));
+        assert!(zsh.find("_tool_connections() {") < zsh.rfind("if [ \"$funcstack[1]\""));
+
+        let bash = String::from_utf8(script(Shell::Bash, command())).unwrap();
+        assert!(bash.contains("complete -F _tool_connections "));
+        assert!(!bash.contains("complete -F _tool "));
+
+        // Each passes on a --config-dir from the line being completed
+        assert!(bash.contains("tool __complete-connections \"${dir_args[@]}\""));
+        assert!(zsh.contains("tool __complete-connections $dir_args"));
+        let fish = String::from_utf8(script(Shell::Fish, command())).unwrap();
+        assert!(fish.contains("tool __complete-connections $dir_args"));
+    }
+}
@@ -0,0 +1,188 @@
+//! The optional master password, which replaces `key.bin` as the source of the key that
+//! saved passwords are encrypted with.
+//!
+//! The key is derived from the passphrase with Argon2id. Only the salt and the Argon2
+//! parameters are stored, along with a known text encrypted under the key so that a
+//! wrong passphrase is caught before anything is decrypted or encrypted with it. The
+//! passphrase is asked for once per process and then kept only as the derived key.
+
+use anyhow::{Result, anyhow};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::io::{self, IsTerminal, Write};
+use std::sync::Mutex;
+
+/// The environment variable a master password can be given in, for scripts.
+pub const PASSWORD_ENV: &str = "DAEDALUS_MASTER_PASSWORD";
+
+// Encrypted under the derived key to tell whether a passphrase is the right one
+const CHECK_TEXT: &str = "daedalus-cli master password";
+
+// The derived key of the master password unlocked in this process, by salt
+static UNLOCKED: Mutex<Option<(String, [u8; 32])>> = Mutex::new(None);
+
+/// How to derive the key from the master password. Stored in `config.json`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MasterPassword {
+    pub salt: String, // Base64
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    pub check_cipher: String,
+    pub check_nonce: String,
+}
+
+impl MasterPassword {
+    /// Settings for `passphrase` with a fresh salt and Argon2's recommended parameters,
+    /// and the key they derive.
+    pub fn create(passphrase: &str) -> Result<(MasterPassword, [u8; 32])> {
+        Self::create_with_params(
+            passphrase,
+            Params::DEFAULT_M_COST,
+            Params::DEFAULT_T_COST,
+            Params::DEFAULT_P_COST,
+        )
+    }
+
+    pub(super) fn create_with_params(
+        passphrase: &str,
+        memory_kib: u32,
+        iterations: u32,
+        parallelism: u32,
+    ) -> Result<(MasterPassword, [u8; 32])> {
+        let mut salt = [0u8; 16];
+        rand::rng().fill(&mut salt);
+        let mut master = MasterPassword {
+            salt: STANDARD.encode(salt),
+            memory_kib,
+            iterations,
+            parallelism,
+            check_cipher: String::new(),
+            check_nonce: String::new(),
+        };
+        let key = master.derive_key(passphrase)?;
+        (master.check_cipher, master.check_nonce) =
+            super::Config::encrypt_with_key(&key, CHECK_TEXT)?;
+        Ok((master, key))
+    }
+
+    /// The key `passphrase` derives, or an error if it isn't the master password.
+    pub fn unlock_with(&self, passphrase: &str) -> Result<[u8; 32]> {
+        let key = self.derive_key(passphrase)?;
+        match super::Config::decrypt_with_key(&key, &self.check_cipher, &self.check_nonce) {
+            Ok(text) if text == CHECK_TEXT => Ok(key),
+            _ => Err(anyhow!("Wrong master password")),
+        }
+    }
+
+    /// The key, from the passphrase in `DAEDALUS_MASTER_PASSWORD` or else asked for on the
+    /// terminal. Only asks once per process; later calls return the same key.
+    pub fn unlock(&self) -> Result<[u8; 32]> {
+        let mut unlocked = UNLOCKED.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((salt, key)) = unlocked.as_ref()
+            && *salt == self.salt
+        {
+            return Ok(*key);
+        }
+
+        let key = match std::env::var(PASSWORD_ENV) {
+            Ok(passphrase) => self.unlock_with(&passphrase)?,
+            Err(_) if io::stdin().is_terminal() => self.unlock_interactively()?,
+            Err(_) => {
+                return Err(anyhow!(
+                    "Saved passwords are locked with a master password; set {} to unlock them",
+                    PASSWORD_ENV
+                ));
+            }
+        };
+        *unlocked = Some((self.salt.clone(), key));
+        Ok(key)
+    }
+
+    fn unlock_interactively(&self) -> Result<[u8; 32]> {
+        let mut attempts = 0;
+        loop {
+            let passphrase = prompt_password("Master password: ")?;
+            match self.unlock_with(&passphrase) {
+                Ok(key) => return Ok(key),
+                Err(e) if attempts < 2 => {
+                    eprintln!("{}", e);
+                    attempts += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn derive_key(&self, passphrase: &str) -> Result<[u8; 32]> {
+        let salt = STANDARD.decode(&self.salt)?;
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, Some(32))
+            .map_err(|e| anyhow!("Invalid master password settings: {}", e))?;
+        let mut key = [0u8; 32];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| anyhow!("Cannot derive the key from the master password: {}", e))?;
+        Ok(key)
+    }
+}
+
+/// Read a line from the terminal without echoing it.
+pub fn prompt_password(prompt: &str) -> Result<String> {
+    use crossterm::event::{Event, KeyCode, KeyEventKind, KeyModifiers, read};
+    use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+
+    enable_raw_mode()?;
+    let mut password = String::new();
+    let result = loop {
+        match read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => match key.code {
+                KeyCode::Enter => break Ok(()),
+                KeyCode::Backspace => {
+                    password.pop();
+                }
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    break Err(anyhow!("Interrupted"));
+                }
+                KeyCode::Char(c) => password.push(c),
+                _ => {}
+            },
+            Ok(_) => {}
+            Err(e) => break Err(e.into()),
+        }
+    };
+    disable_raw_mode()?;
+    eprintln!();
+
+    result.map(|_| password)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Far cheaper than the defaults, to keep the tests fast
+    fn test_master_password(passphrase: &str) -> (MasterPassword, [u8; 32]) {
+        MasterPassword::create_with_params(passphrase, 64, 1, 1).unwrap()
+    }
+
+    #[test]
+    fn test_unlock_with() {
+        let (master, key) = test_master_password("correct horse");
+        assert_eq!(master.unlock_with("correct horse").unwrap(), key);
+        assert_eq!(
+            master.unlock_with("wrong horse").unwrap_err().to_string(),
+            "Wrong master password"
+        );
+
+        // The same passphrase with a new salt derives a different key
+        let (other, other_key) = test_master_password("correct horse");
+        assert_ne!(other.salt, master.salt);
+        assert_ne!(other_key, key);
+    }
+}
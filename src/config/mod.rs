@@ -1,13 +1,24 @@
 use aead::{Aead, KeyInit};
 use aes_gcm::{Aes256Gcm, Nonce};
-use anyhow::Result;
+use anyhow::{Result, anyhow};
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
 use dirs::home_dir;
 use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::ffi::OsString;
 use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+pub mod master;
+pub mod password_command;
+pub mod pg_service;
+pub mod session;
+
+use master::MasterPassword;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ConnectionInfo {
@@ -17,6 +28,144 @@ pub struct ConnectionInfo {
     pub username: String,
     pub password: String,
     pub name: String,
+    #[serde(default)]
+    pub options: ConnectionOptions,
+    /// SQL run in order right after connecting; see `init_statement`
+    #[serde(default)]
+    pub on_connect: Vec<String>,
+    /// Overrides `Settings::max_count_rows` for this connection
+    #[serde(default)]
+    pub max_count_rows: Option<u64>,
+    /// Overrides `Settings::max_export_rows` for this connection
+    #[serde(default)]
+    pub max_export_rows: Option<u64>,
+    /// Shell command printing the password, run instead of using `password` each time
+    /// the connection is opened; see `password_command`
+    #[serde(default)]
+    pub password_command: Option<String>,
+}
+
+/// libpq options given as `?key=value` parameters of a connection URL.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct ConnectionOptions {
+    /// Shown for the session in `pg_stat_activity`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub application_name: Option<String>,
+    /// Seconds to wait for the connection to be established
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout: Option<u64>,
+    /// One of libpq's modes: disable, allow, prefer, require, verify-ca or verify-full
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sslmode: Option<String>,
+    /// PEM file of the certificate authorities to verify the server's certificate with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sslrootcert: Option<PathBuf>,
+    /// PEM file of the client certificate to present, with `sslkey`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sslcert: Option<PathBuf>,
+    /// PEM file of the client certificate's private key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sslkey: Option<PathBuf>,
+}
+
+impl ConnectionOptions {
+    pub fn is_empty(&self) -> bool {
+        *self == ConnectionOptions::default()
+    }
+
+    // The options as `key=value` URL query parameters, in libpq's names
+    fn query_parameters(&self) -> Vec<(&'static str, String)> {
+        let path = |path: &Option<PathBuf>| path.as_ref().map(|p| p.display().to_string());
+        [
+            ("application_name", self.application_name.clone()),
+            (
+                "connect_timeout",
+                self.connect_timeout.map(|s| s.to_string()),
+            ),
+            ("sslmode", self.sslmode.clone()),
+            ("sslrootcert", path(&self.sslrootcert)),
+            ("sslcert", path(&self.sslcert)),
+            ("sslkey", path(&self.sslkey)),
+        ]
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value?)))
+        .collect()
+    }
+}
+
+impl ConnectionInfo {
+    /// The password to connect with: what the password command prints if there is one,
+    /// otherwise the stored password.
+    pub async fn connect_password(&self) -> Result<String> {
+        match &self.password_command {
+            Some(command) => {
+                password_command::run(command, password_command::PASSWORD_COMMAND_TIMEOUT).await
+            }
+            // As with libpq, PGPASSWORD stands in for a missing password. It's only read
+            // here, so it is never saved with the connection
+            None if self.password.is_empty() => Ok(std::env::var("PGPASSWORD").unwrap_or_default()),
+            None => Ok(self.password.clone()),
+        }
+    }
+
+    /// The `postgresql://` URI for this connection, the inverse of parsing one. The
+    /// password is only included when asked for.
+    pub fn to_connection_string(&self, with_password: bool) -> String {
+        let mut uri = format!("postgresql://{}", percent_encode(&self.username));
+        if with_password && !self.password.is_empty() {
+            uri.push(':');
+            uri.push_str(&percent_encode(&self.password));
+        }
+        // A socket directory's slashes are encoded too, as libpq expects
+        uri.push_str(&format!(
+            "@{}:{}/{}",
+            percent_encode(&self.host),
+            self.port,
+            percent_encode(&self.database)
+        ));
+        let query: Vec<String> = self
+            .options
+            .query_parameters()
+            .into_iter()
+            .map(|(key, value)| format!("{}={}", key, percent_encode(&value)))
+            .collect();
+        if !query.is_empty() {
+            uri.push('?');
+            uri.push_str(&query.join("&"));
+        }
+        uri
+    }
+}
+
+/// The order connections are listed in: by name ignoring case, with names that differ
+/// only in case in a fixed order too.
+pub fn compare_names(a: &str, b: &str) -> std::cmp::Ordering {
+    a.to_lowercase()
+        .cmp(&b.to_lowercase())
+        .then_with(|| a.cmp(b))
+}
+
+/// Escape everything but URL-unreserved characters as %XX.
+pub fn percent_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Split an `on_connect` statement into its SQL and whether it's optional. A leading `?`
+/// marks a statement whose failure shouldn't stop the connection, e.g. loading an
+/// extension that not every server has.
+pub fn init_statement(statement: &str) -> (&str, bool) {
+    match statement.trim_start().strip_prefix('?') {
+        Some(sql) => (sql.trim(), true),
+        None => (statement.trim(), false),
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -29,37 +178,294 @@ pub struct StoredConnectionInfo {
     pub password_cipher: Option<String>,
     pub password_nonce: Option<String>,
     pub name: String,
+    #[serde(default, skip_serializing_if = "ConnectionOptions::is_empty")]
+    pub options: ConnectionOptions,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_connect: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_count_rows: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_export_rows: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_command: Option<String>,
+}
+
+/// A saved connection in the shareable export format. Secrets are only present when
+/// explicitly requested, and then only as ciphertext.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct ExportedConnection {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub database: String,
+    pub username: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_cipher: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_nonce: Option<String>,
+    #[serde(default, skip_serializing_if = "ConnectionOptions::is_empty")]
+    pub options: ConnectionOptions,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub on_connect: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_count_rows: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_export_rows: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_command: Option<String>,
+}
+
+/// What `list-conns --format json` shows of a saved connection. Deliberately has no
+/// password fields of any kind.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ConnectionSummary {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub database: String,
+    pub username: String,
+}
+
+/// A query saved under a name, to be loaded into the query input again.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedQuery {
+    pub name: String,
+    pub sql: String,
+}
+
+/// How table views work out the total number of rows in a table.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum RowCountMode {
+    /// Use the planner's estimate for large tables and an exact count for small ones
+    #[default]
+    Auto,
+    /// Always run `COUNT(*)`
+    Exact,
+    /// Use the planner's estimate whenever one is available
+    Estimate,
+}
+
+/// The built-in color themes for the TUI.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    #[default]
+    Default,
+    /// Black on white selections and bright, bold colors
+    HighContrast,
+    /// Avoids telling things apart by red and green alone
+    ColorblindSafe,
+}
+
+/// The order of the connection list in the TUI.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionOrder {
+    /// Most recently opened first, then the rest by name
+    #[default]
+    Recent,
+    /// By name
+    Name,
 }
 
-#[derive(Serialize, Deserialize)]
+/// How the table view shows boolean columns.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BoolDisplay {
+    /// `true` and `false`, as the server sends them
+    #[default]
+    Words,
+    /// `✓` and `✗`
+    Checks,
+    /// `t` and `f`, like psql
+    Letters,
+}
+
+impl BoolDisplay {
+    pub fn next(self) -> BoolDisplay {
+        match self {
+            BoolDisplay::Words => BoolDisplay::Checks,
+            BoolDisplay::Checks => BoolDisplay::Letters,
+            BoolDisplay::Letters => BoolDisplay::Words,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BoolDisplay::Words => "true/false",
+            BoolDisplay::Checks => "✓/✗",
+            BoolDisplay::Letters => "t/f",
+        }
+    }
+}
+
+/// Preferences that aren't tied to a single connection.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Settings {
+    #[serde(default)]
+    pub row_count: RowCountMode,
+    #[serde(default)]
+    pub theme: ThemeName,
+    /// strftime-style format for timestamps when they are shown formatted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_format: Option<String>,
+    /// Ask for confirmation before running a custom query that drops, truncates or alters
+    /// something, or deletes or updates without a `WHERE`
+    #[serde(default = "default_true")]
+    pub confirm_destructive: bool,
+    #[serde(default)]
+    pub bool_display: BoolDisplay,
+    /// Whether the table view shows a row of column types under the column names
+    #[serde(default = "default_true")]
+    pub show_column_types: bool,
+    #[serde(default)]
+    pub connection_order: ConnectionOrder,
+    /// Query results with more rows than this aren't counted exactly, since counting
+    /// means running the whole query
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_count_rows: Option<u64>,
+    /// Results with more rows than this are only exported after confirming, since the
+    /// export holds them all at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_export_rows: Option<u64>,
+    /// Seconds to reuse the results of a read-only query or the pages of a table rather
+    /// than fetching them again; off when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_cache_ttl: Option<u64>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            row_count: RowCountMode::default(),
+            theme: ThemeName::default(),
+            timestamp_format: None,
+            confirm_destructive: true,
+            bool_display: BoolDisplay::default(),
+            show_column_types: true,
+            connection_order: ConnectionOrder::default(),
+            max_count_rows: None,
+            max_export_rows: None,
+            query_cache_ttl: None,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The version of the config format this build writes; older ones are upgraded by
+/// `Config::migrate` when loaded.
+const CONFIG_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
+    /// The format version, missing in configs written before it was introduced.
+    #[serde(default)]
+    version: u32,
     connections: HashMap<String, StoredConnectionInfo>,
+    #[serde(default)]
+    pub settings: Settings,
+    /// Columns hidden in the table view, by connection and then by table.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    hidden_columns: HashMap<String, HashMap<String, Vec<String>>>,
+    /// Widths set with `<` and `>` in the table view, by connection, table and column.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    column_widths: HashMap<String, HashMap<String, HashMap<String, u16>>>,
+    /// Queries saved by name, by connection, each connection's sorted by name.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    saved_queries: HashMap<String, Vec<SavedQuery>>,
+    /// Set when passwords are encrypted with a key derived from a master password
+    /// rather than with `key.bin`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    master_password: Option<MasterPassword>,
+    // The key derived from the master password, once something has needed it
+    #[serde(skip)]
+    master_key: OnceLock<[u8; 32]>,
+    // The directory the config was loaded from, which holds key.bin too
+    #[serde(skip)]
+    dir: PathBuf,
 }
 
+// Set from `--config-dir`, taking precedence over the environment
+static CONFIG_DIR_OVERRIDE: OnceLock<PathBuf> = OnceLock::new();
+
 impl Config {
-    pub fn new() -> Result<Self> {
+    /// An empty config kept in `dir`.
+    pub fn new_in(dir: &Path) -> Result<Self> {
         Ok(Config {
+            version: CONFIG_VERSION,
             connections: HashMap::new(),
+            settings: Settings::default(),
+            hidden_columns: HashMap::new(),
+            column_widths: HashMap::new(),
+            saved_queries: HashMap::new(),
+            master_password: None,
+            master_key: OnceLock::new(),
+            dir: dir.to_path_buf(),
         })
     }
 
     pub fn load() -> Result<Self> {
-        let config_path = Config::get_config_file_path();
+        Self::load_from(&Self::get_config_dir())
+    }
+
+    /// Load the config kept in `dir`, creating an empty one if there is none.
+    pub fn load_from(dir: &Path) -> Result<Self> {
+        Config::recover_interrupted_rotation(dir)?;
+        Config::warn_on_open_key_permissions(dir);
+        let config_path = Config::get_config_file_path(dir);
 
         if !config_path.exists() {
-            let config = Config::new()?;
+            let config = Config::new_in(dir)?;
             config.save()?;
             return Ok(config);
         }
 
-        let config_str = fs::read_to_string(config_path)?;
-        let config: Config = serde_json::from_str(&config_str)?;
+        let mut config = Config::read(dir)?;
+        if config.version < CONFIG_VERSION {
+            config.migrate()?;
+            config.save()?;
+        }
 
         Ok(config)
     }
 
+    /// Load the config only to look at it, e.g. for shell completion: nothing is asked
+    /// for, migrated or written, and a missing config reads as an empty one. Passwords
+    /// can't be decrypted without the master password, if there is one.
+    pub fn load_without_unlocking() -> Result<Self> {
+        let dir = Self::get_config_dir();
+        if !Config::get_config_file_path(&dir).exists() {
+            return Config::new_in(&dir);
+        }
+        Config::read(&dir)
+    }
+
+    // Parse the config file in `dir` as it is
+    fn read(dir: &Path) -> Result<Self> {
+        let config_path = Config::get_config_file_path(dir);
+        let config_str = fs::read_to_string(&config_path)?;
+        // Check the version before the rest, whose shape a newer version may have changed
+        let value: serde_json::Value = serde_json::from_str(&config_str)?;
+        let version = value.get("version").and_then(|v| v.as_u64()).unwrap_or(0);
+        if version > u64::from(CONFIG_VERSION) {
+            return Err(anyhow!(
+                "{} was written by a newer version of daedalus-cli (config version {}, this one reads up to {}); upgrade daedalus-cli to use it",
+                config_path.display(),
+                version,
+                CONFIG_VERSION
+            ));
+        }
+        let mut config: Config = serde_json::from_value(value)?;
+        config.dir = dir.to_path_buf();
+        Ok(config)
+    }
+
     pub fn save(&self) -> Result<()> {
-        let config_path = Config::get_config_file_path();
+        let config_path = Config::get_config_file_path(&self.dir);
 
         // Ensure the config directory exists
         if let Some(parent) = config_path.parent() {
@@ -67,13 +473,119 @@ impl Config {
         }
 
         let config_str = serde_json::to_string_pretty(self)?;
-        fs::write(config_path, config_str)?;
+        Self::write_atomic(&config_path, config_str.as_bytes())?;
+        Ok(())
+    }
+
+    /// Re-encrypt every stored password under a freshly generated key.
+    ///
+    /// Both `config.json` and `key.bin` are replaced; nothing is written unless every
+    /// password decrypts under the current key. Returns the number of re-encrypted passwords.
+    pub fn rotate_key(&mut self) -> Result<usize> {
+        if self.master_password.is_some() {
+            return Err(anyhow!(
+                "Passwords are encrypted with the master password rather than key.bin; change it with set-master-password instead"
+            ));
+        }
+        let mut new_key = [0u8; 32];
+        rand::rng().fill(&mut new_key);
+        let (rotated, count) = self.reencrypted(&new_key, "key not rotated")?;
+
+        // Stage both files before committing. Renaming the config into place is the commit
+        // point; recover_interrupted_rotation rolls forward or back if we crash in between.
+        let config_path = Self::get_config_file_path(&self.dir);
+        let staged_config = Self::temp_path(&config_path);
+        let staged_key = Self::get_staged_key_file_path(&self.dir);
+        Self::write_private(
+            &staged_config,
+            serde_json::to_string_pretty(&rotated)?.as_bytes(),
+        )?;
+        Self::write_private(&staged_key, &new_key)?;
+        fs::rename(&staged_config, &config_path)?;
+        fs::rename(&staged_key, Self::get_key_file_path(&self.dir))?;
+
+        *self = rotated;
+        Ok(count)
+    }
+
+    /// Encrypt every stored password with a key derived from `passphrase`, or with
+    /// `key.bin` again when it's None, and save the config. Returns the number of
+    /// re-encrypted passwords.
+    pub fn set_master_password(&mut self, passphrase: Option<&str>) -> Result<usize> {
+        let (master, key) = match passphrase {
+            Some(passphrase) => {
+                let (master, key) = MasterPassword::create(passphrase)?;
+                (Some(master), key)
+            }
+            None => (None, Self::get_or_create_key(&self.dir)?),
+        };
+        let (mut changed, count) = self.reencrypted(&key, "master password not changed")?;
+        changed.master_key = OnceLock::new();
+        if master.is_some() {
+            let _ = changed.master_key.set(key);
+        }
+        changed.master_password = master;
+        changed.save()?;
+        *self = changed;
+        Ok(count)
+    }
+
+    pub fn has_master_password(&self) -> bool {
+        self.master_password.is_some()
+    }
+
+    // A copy of the config with every password encrypted under `new_key`, failing with
+    // `not_done` in the message if any can't be decrypted with the current key
+    fn reencrypted(&self, new_key: &[u8; 32], not_done: &str) -> Result<(Config, usize)> {
+        let old_key = self.key()?;
+
+        let mut passwords = Vec::new();
+        for (name, stored) in &self.connections {
+            let password = Self::stored_password(stored, &old_key).map_err(|e| {
+                anyhow!(
+                    "Cannot decrypt password for connection '{}' ({}); {}",
+                    name,
+                    e,
+                    not_done
+                )
+            })?;
+            passwords.push((name.clone(), password));
+        }
+
+        let mut changed = self.clone();
+        for (name, password) in &passwords {
+            if let Some(stored) = changed.connections.get_mut(name) {
+                let (cipher, nonce) = Self::encrypt_with_key(new_key, password)?;
+                stored.password = None;
+                stored.password_cipher = Some(cipher);
+                stored.password_nonce = Some(nonce);
+            }
+        }
+        Ok((changed, passwords.len()))
+    }
+
+    // Finish or undo a key rotation that was interrupted between staging and committing
+    fn recover_interrupted_rotation(dir: &Path) -> Result<()> {
+        let staged_key = Self::get_staged_key_file_path(dir);
+        if !staged_key.exists() {
+            return Ok(());
+        }
+
+        let staged_config = Self::temp_path(&Self::get_config_file_path(dir));
+        if staged_config.exists() {
+            // The new config was never committed, so the old key is still the valid one
+            fs::remove_file(&staged_config)?;
+            fs::remove_file(&staged_key)?;
+        } else {
+            // The new config is in place; the staged key must follow it
+            fs::rename(&staged_key, Self::get_key_file_path(dir))?;
+        }
         Ok(())
     }
 
     #[allow(dead_code)]
     pub fn add_connection(&mut self, info: ConnectionInfo) -> Result<()> {
-        let (cipher, nonce) = Self::encrypt_password(&info.password)?;
+        let (cipher, nonce) = self.encrypt_password(&info.password)?;
         let stored_info = StoredConnectionInfo {
             host: info.host,
             port: info.port,
@@ -83,68 +595,391 @@ impl Config {
             password_cipher: Some(cipher),
             password_nonce: Some(nonce),
             name: info.name,
+            options: info.options,
+            on_connect: info.on_connect,
+            max_count_rows: info.max_count_rows,
+            max_export_rows: info.max_export_rows,
+            password_command: info.password_command,
         };
         self.connections
             .insert(stored_info.name.clone(), stored_info);
         Ok(())
     }
 
+    /// Save a copy of the `source` connection as `new_name`, after `apply` has made its
+    /// changes to the copy (a different host, say). The password is decrypted and
+    /// encrypted again rather than copied, so the copy has its own nonce.
+    pub fn clone_connection(
+        &mut self,
+        source: &str,
+        new_name: &str,
+        apply: impl FnOnce(&mut ConnectionInfo),
+    ) -> Result<()> {
+        if self.has_connection(new_name) {
+            return Err(anyhow!("Connection '{}' already exists", new_name));
+        }
+        if !self.has_connection(source) {
+            return Err(anyhow!("Connection '{}' not found", source));
+        }
+        let mut info = self
+            .get_connection(source)
+            .ok_or_else(|| anyhow!("Cannot decrypt the password of '{}'", source))?;
+        info.name = new_name.to_string();
+        apply(&mut info);
+        self.add_connection(info)
+    }
+
+    // Upgrade a config written in an older format to CONFIG_VERSION, one version at a time
+    fn migrate(&mut self) -> Result<()> {
+        if self.version < 1 {
+            // Configs written before encryption was introduced may still hold plaintext passwords
+            let migrated = self.migrate_plaintext_passwords()?;
+            if migrated > 0 {
+                eprintln!(
+                    "Migrated {} plaintext password(s) to encrypted storage",
+                    migrated
+                );
+            }
+        }
+        self.version = CONFIG_VERSION;
+        Ok(())
+    }
+
+    // Encrypt any plaintext passwords in place, returning how many connections were migrated
+    fn migrate_plaintext_passwords(&mut self) -> Result<usize> {
+        if self
+            .connections
+            .values()
+            .all(|stored| stored.password.is_none())
+        {
+            return Ok(0);
+        }
+        let key = self.key()?;
+        let mut migrated = 0;
+        for stored in self.connections.values_mut() {
+            if let Some(plain) = stored.password.take() {
+                if stored.password_cipher.is_none() || stored.password_nonce.is_none() {
+                    let (cipher, nonce) = Self::encrypt_with_key(&key, &plain)?;
+                    stored.password_cipher = Some(cipher);
+                    stored.password_nonce = Some(nonce);
+                }
+                migrated += 1;
+            }
+        }
+        Ok(migrated)
+    }
+
+    // Recover the plaintext password of a stored connection using the given key
+    fn stored_password(stored: &StoredConnectionInfo, key: &[u8; 32]) -> Result<String> {
+        if let (Some(c), Some(n)) = (&stored.password_cipher, &stored.password_nonce) {
+            Self::decrypt_with_key(key, c, n)
+        } else {
+            stored
+                .password
+                .clone()
+                .ok_or_else(|| anyhow!("no password stored"))
+        }
+    }
+
     pub fn get_connection(&self, name: &str) -> Option<ConnectionInfo> {
+        self.try_get_connection(name).ok().flatten()
+    }
+
+    /// Like `get_connection`, but a saved connection whose password can't be decrypted
+    /// is an error rather than None.
+    pub fn try_get_connection(&self, name: &str) -> Result<Option<ConnectionInfo>> {
         if let Some(stored) = self.connections.get(name).cloned() {
-            let password = if let (Some(c), Some(n)) = (
-                stored.password_cipher.clone(),
-                stored.password_nonce.clone(),
-            ) {
-                match Self::decrypt_password(&c, &n) {
-                    Ok(p) => p,
-                    Err(_) => return None,
-                }
-            } else if let Some(p) = stored.password.clone() {
-                p
-            } else {
-                return None;
-            };
-            return Some(ConnectionInfo {
+            let password = self
+                .key()
+                .and_then(|key| Self::stored_password(&stored, &key))
+                .map_err(|e| {
+                    anyhow!("Cannot decrypt password for connection '{}' ({})", name, e)
+                })?;
+            return Ok(Some(ConnectionInfo {
                 host: stored.host,
                 port: stored.port,
                 database: stored.database,
                 username: stored.username,
                 password,
                 name: stored.name,
-            });
+                options: stored.options,
+                on_connect: stored.on_connect,
+                max_count_rows: stored.max_count_rows,
+                max_export_rows: stored.max_export_rows,
+                password_command: stored.password_command,
+            }));
         }
-        None
+        Ok(None)
     }
 
+    /// Export all connections sorted by name, including encrypted passwords only if asked.
+    pub fn export_connections(&self, include_secrets: bool) -> Vec<ExportedConnection> {
+        let mut exported: Vec<ExportedConnection> = self
+            .connections
+            .values()
+            .map(|stored| ExportedConnection {
+                name: stored.name.clone(),
+                host: stored.host.clone(),
+                port: stored.port,
+                database: stored.database.clone(),
+                username: stored.username.clone(),
+                password_cipher: stored.password_cipher.clone().filter(|_| include_secrets),
+                password_nonce: stored.password_nonce.clone().filter(|_| include_secrets),
+                options: stored.options.clone(),
+                on_connect: stored.on_connect.clone(),
+                max_count_rows: stored.max_count_rows,
+                max_export_rows: stored.max_export_rows,
+                password_command: stored.password_command.clone(),
+            })
+            .collect();
+        exported.sort_by(|a, b| compare_names(&a.name, &b.name));
+        exported
+    }
+
+    /// Decrypt the password of an exported connection, if it carries one readable with the
+    /// current key.
+    pub fn exported_password(&self, exported: &ExportedConnection) -> Option<String> {
+        let (cipher, nonce) = (
+            exported.password_cipher.as_ref()?,
+            exported.password_nonce.as_ref()?,
+        );
+        let key = self.key().ok()?;
+        Self::decrypt_with_key(&key, cipher, nonce).ok()
+    }
+
+    pub fn has_connection(&self, name: &str) -> bool {
+        self.connections.contains_key(name)
+    }
+
+    /// The names of the saved connections, sorted with `compare_names`.
     pub fn list_connections(&self) -> Vec<String> {
-        self.connections.keys().cloned().collect()
+        let mut names: Vec<String> = self.connections.keys().cloned().collect();
+        names.sort_by(|a, b| compare_names(a, b));
+        names
+    }
+
+    /// The saved connections without their passwords, sorted by name.
+    pub fn connection_summaries(&self) -> Vec<ConnectionSummary> {
+        let mut summaries: Vec<ConnectionSummary> = self
+            .connections
+            .values()
+            .map(|stored| ConnectionSummary {
+                name: stored.name.clone(),
+                host: stored.host.clone(),
+                port: stored.port,
+                database: stored.database.clone(),
+                username: stored.username.clone(),
+            })
+            .collect();
+        summaries.sort_by(|a, b| compare_names(&a.name, &b.name));
+        summaries
     }
 
     #[allow(dead_code)]
     pub fn remove_connection(&mut self, name: &str) -> bool {
+        self.hidden_columns.remove(name);
+        self.column_widths.remove(name);
+        self.saved_queries.remove(name);
         self.connections.remove(name).is_some()
     }
 
+    pub fn hidden_columns(&self, connection: &str, table: &str) -> &[String] {
+        self.hidden_columns
+            .get(connection)
+            .and_then(|tables| tables.get(table))
+            .map_or(&[], |columns| columns.as_slice())
+    }
+
+    /// Remember which columns of a table to hide; an empty list shows them all again.
+    pub fn set_hidden_columns(&mut self, connection: &str, table: &str, columns: Vec<String>) {
+        let tables = self
+            .hidden_columns
+            .entry(connection.to_string())
+            .or_default();
+        if columns.is_empty() {
+            tables.remove(table);
+        } else {
+            tables.insert(table.to_string(), columns);
+        }
+        if tables.is_empty() {
+            self.hidden_columns.remove(connection);
+        }
+    }
+
+    /// The width a column of a table was given, if it was sized by hand.
+    pub fn column_width(&self, connection: &str, table: &str, column: &str) -> Option<u16> {
+        self.column_widths
+            .get(connection)
+            .and_then(|tables| tables.get(table))
+            .and_then(|columns| columns.get(column))
+            .copied()
+    }
+
+    /// Remember the width of a column of a table; None sizes it automatically again.
+    pub fn set_column_width(
+        &mut self,
+        connection: &str,
+        table: &str,
+        column: &str,
+        width: Option<u16>,
+    ) {
+        let tables = self
+            .column_widths
+            .entry(connection.to_string())
+            .or_default();
+        let columns = tables.entry(table.to_string()).or_default();
+        match width {
+            Some(width) => columns.insert(column.to_string(), width),
+            None => columns.remove(column),
+        };
+        if columns.is_empty() {
+            tables.remove(table);
+        }
+        if tables.is_empty() {
+            self.column_widths.remove(connection);
+        }
+    }
+
+    /// The queries saved for a connection, sorted by name.
+    pub fn saved_queries(&self, connection: &str) -> &[SavedQuery] {
+        self.saved_queries
+            .get(connection)
+            .map_or(&[], |queries| queries.as_slice())
+    }
+
+    /// Save a query for a connection under `name`, replacing any saved under that name
+    /// before. Returns whether one was replaced.
+    pub fn save_query(&mut self, connection: &str, name: &str, sql: &str) -> bool {
+        let queries = self
+            .saved_queries
+            .entry(connection.to_string())
+            .or_default();
+        let query = SavedQuery {
+            name: name.to_string(),
+            sql: sql.to_string(),
+        };
+        match queries.binary_search_by(|q| q.name.as_str().cmp(name)) {
+            Ok(i) => {
+                queries[i] = query;
+                true
+            }
+            Err(i) => {
+                queries.insert(i, query);
+                false
+            }
+        }
+    }
+
+    /// Forget a saved query, returning whether there was one by that name.
+    pub fn remove_saved_query(&mut self, connection: &str, name: &str) -> bool {
+        let Some(queries) = self.saved_queries.get_mut(connection) else {
+            return false;
+        };
+        let len = queries.len();
+        queries.retain(|q| q.name != name);
+        let removed = queries.len() < len;
+        if queries.is_empty() {
+            self.saved_queries.remove(connection);
+        }
+        removed
+    }
+
     pub fn decrypt_connection_password(&self, info: &ConnectionInfo) -> Result<String> {
         Ok(info.password.clone())
     }
 
-    fn get_config_file_path() -> std::path::PathBuf {
-        let home_dir = Self::get_home_dir();
-        let mut config_dir = std::path::PathBuf::from(home_dir);
-        config_dir.push(".daedalus-cli");
-        config_dir.push("config.json");
-        config_dir
+    /// Use `dir` as the config directory for the rest of the process, as with
+    /// `--config-dir`. Only the first call has an effect.
+    pub fn set_config_dir(dir: PathBuf) {
+        let _ = CONFIG_DIR_OVERRIDE.set(dir);
+    }
+
+    /// The directory holding the config, key and logs: the one given to `set_config_dir`,
+    /// else `$DAEDALUS_CONFIG_DIR`, else `~/.daedalus-cli` if it exists, else
+    /// `$XDG_CONFIG_HOME/daedalus-cli` when that variable is set, else `~/.daedalus-cli`.
+    pub fn get_config_dir() -> PathBuf {
+        Self::config_dir_from(
+            CONFIG_DIR_OVERRIDE.get().map(PathBuf::as_path),
+            |name| std::env::var_os(name),
+            Path::new(&Self::get_home_dir()),
+        )
     }
 
-    fn get_key_file_path() -> std::path::PathBuf {
-        let home_dir = Self::get_home_dir();
-        let mut p = std::path::PathBuf::from(home_dir);
-        p.push(".daedalus-cli");
-        p.push("key.bin");
+    // `get_config_dir` with the override, the environment and the home directory given
+    fn config_dir_from(
+        override_dir: Option<&Path>,
+        env: impl Fn(&str) -> Option<OsString>,
+        home: &Path,
+    ) -> PathBuf {
+        if let Some(dir) = override_dir {
+            return dir.to_path_buf();
+        }
+        let env = |name| env(name).filter(|value| !value.is_empty());
+        if let Some(dir) = env("DAEDALUS_CONFIG_DIR") {
+            return PathBuf::from(dir);
+        }
+        let legacy_dir = home.join(".daedalus-cli");
+        if !legacy_dir.exists()
+            && let Some(xdg) = env("XDG_CONFIG_HOME")
+        {
+            return PathBuf::from(xdg).join("daedalus-cli");
+        }
+        legacy_dir
+    }
+
+    /// Where the TUI writes its log when `--verbose` is given without `--log-file`.
+    pub fn default_log_file_path() -> PathBuf {
+        let mut p = Self::get_config_dir();
+        p.push("daedalus.log");
         p
     }
 
+    /// Where the TUI keeps the errors it showed, see `tui::error_log`.
+    pub fn error_log_path() -> PathBuf {
+        let mut p = Self::get_config_dir();
+        p.push("errors.log");
+        p
+    }
+
+    fn get_config_file_path(dir: &Path) -> PathBuf {
+        dir.join("config.json")
+    }
+
+    fn get_key_file_path(dir: &Path) -> PathBuf {
+        dir.join("key.bin")
+    }
+
+    // Location of the replacement key while a rotation is in progress
+    fn get_staged_key_file_path(dir: &Path) -> PathBuf {
+        let mut p = Self::get_key_file_path(dir);
+        p.set_extension("bin.new");
+        p
+    }
+
+    fn temp_path(path: &Path) -> PathBuf {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".tmp");
+        PathBuf::from(name)
+    }
+
+    // Write a file readable only by the current user and flush it to disk
+    fn write_private(path: &Path, contents: &[u8]) -> Result<()> {
+        let mut file = fs::File::create(path)?;
+        Self::restrict_permissions(path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    // Write to a sibling temp file and rename it over the target so readers never see a
+    // partial file. The result is readable only by the current user.
+    fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+        let tmp = Self::temp_path(path);
+        Self::write_private(&tmp, contents)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
     fn get_home_dir() -> String {
         // Use the dirs crate for reliable cross-platform home directory detection
         home_dir()
@@ -152,44 +987,115 @@ impl Config {
             .unwrap_or_else(|| ".".to_string()) // Fallback to current directory
     }
 
-    fn get_or_create_key() -> Result<[u8; 32]> {
-        let path = Self::get_key_file_path();
+    fn get_or_create_key(dir: &Path) -> Result<[u8; 32]> {
+        let path = Self::get_key_file_path(dir);
         if !path.exists() {
             if let Some(parent) = path.parent() {
                 fs::create_dir_all(parent)?;
             }
             let mut key = [0u8; 32];
             rand::rng().fill(&mut key);
-            fs::write(&path, key)?;
+            Self::write_atomic(&path, &key)?;
             return Ok(key);
         }
-        let data = fs::read(path)?;
+        let data = fs::read(&path)?;
+        if data.len() < 32 {
+            return Err(anyhow!(
+                "Key file {} is {} bytes but must be 32; it may be truncated or corrupted",
+                path.display(),
+                data.len()
+            ));
+        }
         let mut key = [0u8; 32];
         key.copy_from_slice(&data[..32]);
         Ok(key)
     }
 
-    fn encrypt_password(plain: &str) -> Result<(String, String)> {
-        let key = Self::get_or_create_key()?;
-        let cipher = Aes256Gcm::new(&key.into());
+    #[cfg(unix)]
+    fn restrict_permissions(path: &Path) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        Ok(())
+    }
+
+    #[cfg(not(unix))]
+    fn restrict_permissions(_path: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    // Whether group or other users have any access to the file
+    #[cfg(unix)]
+    fn permissions_too_open(path: &Path) -> Result<bool> {
+        use std::os::unix::fs::PermissionsExt;
+        Ok(fs::metadata(path)?.permissions().mode() & 0o077 != 0)
+    }
+
+    #[cfg(not(unix))]
+    fn permissions_too_open(_path: &Path) -> Result<bool> {
+        Ok(false)
+    }
+
+    fn warn_on_open_key_permissions(dir: &Path) {
+        let path = Self::get_key_file_path(dir);
+        if path.exists() && Self::permissions_too_open(&path).unwrap_or(false) {
+            eprintln!(
+                "Warning: key file {} is accessible by other users; run `chmod 600 {}`",
+                path.display(),
+                path.display()
+            );
+        }
+    }
+
+    /// Ask for the master password now, if there is one and it hasn't been entered yet,
+    /// rather than whenever a password is first needed. The TUI does this before taking
+    /// over the terminal, where it couldn't ask.
+    pub fn unlock(&self) -> Result<()> {
+        if self.master_password.is_some() {
+            self.key()?;
+        }
+        Ok(())
+    }
+
+    // The key passwords are encrypted with: the master password's when there is one,
+    // otherwise key.bin's. The master password is only asked for the first time a
+    // password is encrypted or decrypted, so commands that don't need one never ask
+    fn key(&self) -> Result<[u8; 32]> {
+        let Some(master) = &self.master_password else {
+            return Self::get_or_create_key(&self.dir);
+        };
+        if let Some(key) = self.master_key.get() {
+            return Ok(*key);
+        }
+        let key = master.unlock()?;
+        Ok(*self.master_key.get_or_init(|| key))
+    }
+
+    fn encrypt_password(&self, plain: &str) -> Result<(String, String)> {
+        Self::encrypt_with_key(&self.key()?, plain)
+    }
+
+    fn encrypt_with_key(key: &[u8; 32], plain: &str) -> Result<(String, String)> {
+        let cipher = Aes256Gcm::new(key.into());
         let mut nonce_bytes = [0u8; 12];
         rand::rng().fill(&mut nonce_bytes);
         let nonce = Nonce::from_slice(&nonce_bytes);
         let ct = cipher
             .encrypt(nonce, plain.as_bytes())
-            .map_err(|_| anyhow::anyhow!("encryption failed"))?;
+            .map_err(|_| anyhow!("encryption failed"))?;
         Ok((STANDARD.encode(ct), STANDARD.encode(nonce_bytes)))
     }
 
-    fn decrypt_password(cipher_b64: &str, nonce_b64: &str) -> Result<String> {
-        let key = Self::get_or_create_key()?;
-        let cipher = Aes256Gcm::new(&key.into());
+    fn decrypt_with_key(key: &[u8; 32], cipher_b64: &str, nonce_b64: &str) -> Result<String> {
+        let cipher = Aes256Gcm::new(key.into());
         let nonce_bytes = STANDARD.decode(nonce_b64)?;
+        if nonce_bytes.len() != 12 {
+            return Err(anyhow!("invalid nonce length"));
+        }
         let nonce = Nonce::from_slice(&nonce_bytes);
         let ct = STANDARD.decode(cipher_b64)?;
         let pt = cipher
             .decrypt(nonce, ct.as_ref())
-            .map_err(|_| anyhow::anyhow!("decryption failed"))?;
+            .map_err(|_| anyhow!("decryption failed"))?;
         Ok(String::from_utf8(pt)?)
     }
 }
@@ -199,25 +1105,64 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    // A config directory of the test's own, passed to `new_in` and `load_from`
     fn setup_test_env() -> TempDir {
-        let temp_dir = TempDir::new().unwrap();
-        unsafe {
-            std::env::set_var("HOME", temp_dir.path().to_str().unwrap());
-        }
-        temp_dir
+        TempDir::new().unwrap()
+    }
+
+    #[test]
+    fn test_config_dir() {
+        let home = setup_test_env();
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| OsString::from(value))
+            }
+        };
+        let vars = &[
+            ("DAEDALUS_CONFIG_DIR", "/env/dir"),
+            ("XDG_CONFIG_HOME", "/xdg"),
+        ];
+
+        // --config-dir beats the environment, which beats the rest
+        assert_eq!(
+            Config::config_dir_from(Some(Path::new("/flag")), env(vars), home.path()),
+            PathBuf::from("/flag")
+        );
+        assert_eq!(
+            Config::config_dir_from(None, env(vars), home.path()),
+            PathBuf::from("/env/dir")
+        );
+
+        // Without them, XDG_CONFIG_HOME is used unless ~/.daedalus-cli already exists
+        let xdg = &[("DAEDALUS_CONFIG_DIR", ""), ("XDG_CONFIG_HOME", "/xdg")];
+        assert_eq!(
+            Config::config_dir_from(None, env(xdg), home.path()),
+            PathBuf::from("/xdg/daedalus-cli")
+        );
+        assert_eq!(
+            Config::config_dir_from(None, env(&[]), home.path()),
+            home.path().join(".daedalus-cli")
+        );
+        fs::create_dir(home.path().join(".daedalus-cli")).unwrap();
+        assert_eq!(
+            Config::config_dir_from(None, env(xdg), home.path()),
+            home.path().join(".daedalus-cli")
+        );
     }
 
     #[test]
     fn test_new_config() {
-        let _temp_dir = setup_test_env(); // Ensure isolated test environment
-        let config = Config::new().unwrap();
+        let temp_dir = setup_test_env();
+        let config = Config::new_in(temp_dir.path()).unwrap();
         assert!(config.connections.is_empty());
     }
 
     #[test]
     fn test_config_save_and_load() {
-        let _temp_dir = setup_test_env();
-        let mut config = Config::new().unwrap();
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
 
         // Create and save a connection
         let conn_info = ConnectionInfo {
@@ -227,13 +1172,18 @@ mod tests {
             username: "test_user".to_string(),
             password: "test_pass".to_string(),
             name: "test_conn".to_string(),
+            options: Default::default(),
+            on_connect: Vec::new(),
+            max_count_rows: None,
+            max_export_rows: None,
+            password_command: None,
         };
 
         config.add_connection(conn_info.clone()).unwrap();
         config.save().unwrap();
 
         // Load the config and verify it has the connection
-        let loaded_config = Config::load().unwrap();
+        let loaded_config = Config::load_from(temp_dir.path()).unwrap();
         assert_eq!(
             loaded_config.list_connections(),
             vec!["test_conn".to_string()]
@@ -248,10 +1198,38 @@ mod tests {
         assert_eq!(loaded_conn.name, "test_conn");
     }
 
+    #[test]
+    fn test_settings_default_when_missing() {
+        let config: Config = serde_json::from_str(r#"{"connections": {}}"#).unwrap();
+        assert_eq!(config.settings.row_count, RowCountMode::Auto);
+        assert!(config.settings.confirm_destructive);
+        assert!(config.settings.show_column_types);
+        assert_eq!(config.settings.connection_order, ConnectionOrder::Recent);
+
+        let config: Config =
+            serde_json::from_str(r#"{"connections": {}, "settings": {"row_count": "exact"}}"#)
+                .unwrap();
+        assert_eq!(config.settings.row_count, RowCountMode::Exact);
+        assert_eq!(config.settings.theme, ThemeName::Default);
+        assert!(config.settings.confirm_destructive);
+
+        let config: Config = serde_json::from_str(
+            r#"{"connections": {}, "settings": {"theme": "colorblind-safe"}}"#,
+        )
+        .unwrap();
+        assert_eq!(config.settings.theme, ThemeName::ColorblindSafe);
+        assert_eq!(config.settings.bool_display, BoolDisplay::Words);
+
+        let config: Config =
+            serde_json::from_str(r#"{"connections": {}, "settings": {"bool_display": "checks"}}"#)
+                .unwrap();
+        assert_eq!(config.settings.bool_display, BoolDisplay::Checks);
+    }
+
     #[test]
     fn test_add_connection() {
-        let _temp_dir = setup_test_env(); // Ensure isolated test environment
-        let mut config = Config::new().unwrap();
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
 
         let conn_info = ConnectionInfo {
             host: "localhost".to_string(),
@@ -260,6 +1238,11 @@ mod tests {
             username: "test_user".to_string(),
             password: "test_pass".to_string(),
             name: "test_conn".to_string(),
+            options: Default::default(),
+            on_connect: Vec::new(),
+            max_count_rows: None,
+            max_export_rows: None,
+            password_command: None,
         };
 
         config.add_connection(conn_info).unwrap();
@@ -268,9 +1251,9 @@ mod tests {
 
     #[test]
     fn test_get_connection() {
-        let _temp_dir = setup_test_env();
+        let temp_dir = setup_test_env();
 
-        let mut config = Config::new().unwrap();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
 
         let conn_info = ConnectionInfo {
             host: "localhost".to_string(),
@@ -279,6 +1262,11 @@ mod tests {
             username: "test_user".to_string(),
             password: "test_pass".to_string(),
             name: "test_conn".to_string(),
+            options: Default::default(),
+            on_connect: Vec::new(),
+            max_count_rows: None,
+            max_export_rows: None,
+            password_command: None,
         };
 
         config.add_connection(conn_info.clone()).unwrap();
@@ -292,15 +1280,61 @@ mod tests {
         assert_eq!(retrieved_conn.name, conn_info.name);
     }
 
+    #[test]
+    fn test_clone_connection() {
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
+        config
+            .add_connection(ConnectionInfo {
+                host: "prod.example.com".to_string(),
+                port: 5432,
+                database: "app".to_string(),
+                username: "app_user".to_string(),
+                password: "s3cret".to_string(),
+                name: "prod".to_string(),
+                options: Default::default(),
+                on_connect: vec!["SET search_path = app".to_string()],
+                max_count_rows: None,
+                max_export_rows: None,
+                password_command: None,
+            })
+            .unwrap();
+
+        config
+            .clone_connection("prod", "staging", |info| {
+                info.host = "staging.example.com".to_string()
+            })
+            .unwrap();
+        let staging = config.get_connection("staging").unwrap();
+        assert_eq!(staging.name, "staging");
+        assert_eq!(staging.host, "staging.example.com");
+        assert_eq!(staging.password, "s3cret");
+        assert_eq!(staging.on_connect, ["SET search_path = app"]);
+        assert_eq!(
+            config.get_connection("prod").unwrap().host,
+            "prod.example.com"
+        );
+        // Encrypted afresh rather than copied
+        assert_ne!(
+            config.connections["staging"].password_nonce,
+            config.connections["prod"].password_nonce
+        );
+
+        assert!(config.clone_connection("prod", "staging", |_| {}).is_err());
+        assert!(config.clone_connection("missing", "other", |_| {}).is_err());
+    }
+
     #[test]
     fn test_get_nonexistent_connection() {
-        let config = Config::new().unwrap();
+        let temp_dir = setup_test_env();
+        let config = Config::new_in(temp_dir.path()).unwrap();
         assert!(config.get_connection("nonexistent").is_none());
     }
 
     #[test]
     fn test_list_connections() {
-        let mut config = Config::new().unwrap();
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
 
         let conn1 = ConnectionInfo {
             host: "localhost".to_string(),
@@ -309,6 +1343,11 @@ mod tests {
             username: "user1".to_string(),
             password: "pass1".to_string(),
             name: "conn1".to_string(),
+            options: Default::default(),
+            on_connect: Vec::new(),
+            max_count_rows: None,
+            max_export_rows: None,
+            password_command: None,
         };
 
         let conn2 = ConnectionInfo {
@@ -317,21 +1356,26 @@ mod tests {
             database: "test_db2".to_string(),
             username: "user2".to_string(),
             password: "pass2".to_string(),
-            name: "conn2".to_string(),
+            name: "Conn2".to_string(),
+            options: Default::default(),
+            on_connect: Vec::new(),
+            max_count_rows: None,
+            max_export_rows: None,
+            password_command: None,
         };
 
-        config.add_connection(conn1).unwrap();
         config.add_connection(conn2).unwrap();
+        config.add_connection(conn1).unwrap();
 
-        let connections = config.list_connections();
-        assert_eq!(connections.len(), 2);
-        assert!(connections.contains(&"conn1".to_string()));
-        assert!(connections.contains(&"conn2".to_string()));
+        // Sorted ignoring case, whatever order they were added in
+        assert_eq!(config.list_connections(), ["conn1", "Conn2"]);
+        assert_eq!(compare_names("prod", "Prod"), std::cmp::Ordering::Greater);
     }
 
     #[test]
     fn test_remove_connection() {
-        let mut config = Config::new().unwrap();
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
 
         let conn_info = ConnectionInfo {
             host: "localhost".to_string(),
@@ -340,6 +1384,11 @@ mod tests {
             username: "test_user".to_string(),
             password: "test_pass".to_string(),
             name: "test_conn".to_string(),
+            options: Default::default(),
+            on_connect: Vec::new(),
+            max_count_rows: None,
+            max_export_rows: None,
+            password_command: None,
         };
 
         config.add_connection(conn_info).unwrap();
@@ -356,22 +1405,467 @@ mod tests {
 
     #[test]
     fn test_password_encryption_decryption() {
-        let _temp_dir = setup_test_env();
+        let temp_dir = setup_test_env();
         let plaintext = "my_secret_password";
-        let (cipher, nonce) = Config::encrypt_password(plaintext).unwrap();
+        let (cipher, nonce) = Config::new_in(temp_dir.path())
+            .unwrap()
+            .encrypt_password(plaintext)
+            .unwrap();
 
-        let decrypted = Config::decrypt_password(&cipher, &nonce).unwrap();
+        let key = Config::get_or_create_key(temp_dir.path()).unwrap();
+        let decrypted = Config::decrypt_with_key(&key, &cipher, &nonce).unwrap();
         assert_eq!(decrypted, plaintext);
     }
 
     #[test]
     fn test_config_default_creation() {
-        let _temp_dir = setup_test_env();
-        let path = Config::get_config_file_path();
+        let temp_dir = setup_test_env();
+        let path = Config::get_config_file_path(temp_dir.path());
 
         // Config::load should create a default config file if one doesn't exist
-        let config = Config::load().unwrap();
+        let config = Config::load_from(temp_dir.path()).unwrap();
         assert!(path.exists());
         assert!(config.connections.is_empty());
     }
+
+    #[test]
+    fn test_plaintext_password_migration() {
+        let temp_dir = setup_test_env();
+        let path = Config::get_config_file_path(temp_dir.path());
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(
+            &path,
+            r#"{
+  "connections": {
+    "legacy": {
+      "host": "localhost",
+      "port": 5432,
+      "database": "test_db",
+      "username": "test_user",
+      "password": "legacy_plaintext_secret",
+      "password_cipher": null,
+      "password_nonce": null,
+      "name": "legacy"
+    }
+  }
+}"#,
+        )
+        .unwrap();
+
+        let config = Config::load_from(temp_dir.path()).unwrap();
+        let on_disk = fs::read_to_string(&path).unwrap();
+        assert!(!on_disk.contains("legacy_plaintext_secret"));
+        assert!(on_disk.contains(&format!("\"version\": {}", CONFIG_VERSION)));
+
+        // The password must still be recoverable after migration
+        let conn = config.get_connection("legacy").unwrap();
+        assert_eq!(conn.password, "legacy_plaintext_secret");
+    }
+
+    #[test]
+    fn test_newer_config_version_is_an_error() {
+        let temp_dir = setup_test_env();
+        let path = Config::get_config_file_path(temp_dir.path());
+        let newer = format!(
+            r#"{{"version": {}, "connections": {{}}, "layout": "unknown"}}"#,
+            CONFIG_VERSION + 1
+        );
+        fs::write(&path, &newer).unwrap();
+
+        let err = Config::load_from(temp_dir.path()).err().unwrap();
+        assert!(err.to_string().contains("newer version"));
+        // The file is left alone
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+    }
+
+    #[test]
+    fn test_rotate_key() {
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
+
+        let conn_info = ConnectionInfo {
+            host: "localhost".to_string(),
+            port: 5432,
+            database: "test_db".to_string(),
+            username: "test_user".to_string(),
+            password: "test_pass".to_string(),
+            name: "test_conn".to_string(),
+            options: Default::default(),
+            on_connect: Vec::new(),
+            max_count_rows: None,
+            max_export_rows: None,
+            password_command: None,
+        };
+        config.add_connection(conn_info).unwrap();
+        config.save().unwrap();
+
+        let old_key = fs::read(Config::get_key_file_path(temp_dir.path())).unwrap();
+        let rotated = config.rotate_key().unwrap();
+        assert_eq!(rotated, 1);
+
+        let new_key = fs::read(Config::get_key_file_path(temp_dir.path())).unwrap();
+        assert_ne!(old_key, new_key);
+        assert!(!Config::get_staged_key_file_path(temp_dir.path()).exists());
+
+        // A fresh load must decrypt with the new key
+        let loaded = Config::load_from(temp_dir.path()).unwrap();
+        assert_eq!(
+            loaded.get_connection("test_conn").unwrap().password,
+            "test_pass"
+        );
+
+        // Both replaced files stay private
+        #[cfg(unix)]
+        for path in [
+            Config::get_config_file_path(temp_dir.path()),
+            Config::get_key_file_path(temp_dir.path()),
+        ] {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600, "{}", path.display());
+        }
+    }
+
+    #[test]
+    fn test_rotate_key_refuses_undecryptable_password() {
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
+
+        let conn_info = ConnectionInfo {
+            host: "localhost".to_string(),
+            port: 5432,
+            database: "test_db".to_string(),
+            username: "test_user".to_string(),
+            password: "test_pass".to_string(),
+            name: "test_conn".to_string(),
+            options: Default::default(),
+            on_connect: Vec::new(),
+            max_count_rows: None,
+            max_export_rows: None,
+            password_command: None,
+        };
+        config.add_connection(conn_info).unwrap();
+        config
+            .connections
+            .get_mut("test_conn")
+            .unwrap()
+            .password_cipher = Some(STANDARD.encode(b"garbage"));
+        config.save().unwrap();
+
+        let old_key = fs::read(Config::get_key_file_path(temp_dir.path())).unwrap();
+        let old_config = fs::read_to_string(Config::get_config_file_path(temp_dir.path())).unwrap();
+
+        // Looking the connection up reports why it can't be used
+        assert!(config.get_connection("test_conn").is_none());
+        let err = config.try_get_connection("test_conn").unwrap_err();
+        assert!(err.to_string().contains("Cannot decrypt"), "{}", err);
+        assert!(config.try_get_connection("missing").unwrap().is_none());
+
+        let err = config.rotate_key().unwrap_err();
+        assert!(err.to_string().contains("test_conn"));
+        assert_eq!(
+            fs::read(Config::get_key_file_path(temp_dir.path())).unwrap(),
+            old_key
+        );
+        assert_eq!(
+            fs::read_to_string(Config::get_config_file_path(temp_dir.path())).unwrap(),
+            old_config
+        );
+    }
+
+    #[test]
+    fn test_truncated_key_file_is_an_error() {
+        let temp_dir = setup_test_env();
+        let key_path = Config::get_key_file_path(temp_dir.path());
+        fs::create_dir_all(key_path.parent().unwrap()).unwrap();
+        fs::write(&key_path, [7u8; 10]).unwrap();
+
+        let err = Config::get_or_create_key(temp_dir.path()).unwrap_err();
+        assert!(err.to_string().contains("truncated"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_new_key_file_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = setup_test_env();
+        Config::get_or_create_key(temp_dir.path()).unwrap();
+
+        let mode = fs::metadata(Config::get_key_file_path(temp_dir.path()))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_saved_config_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = setup_test_env();
+        let path = Config::get_config_file_path(temp_dir.path());
+        Config::new_in(temp_dir.path()).unwrap().save().unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(!Config::permissions_too_open(&path).unwrap());
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(Config::permissions_too_open(&path).unwrap());
+    }
+
+    #[test]
+    fn test_export_connections() {
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
+
+        let conn_info = ConnectionInfo {
+            host: "localhost".to_string(),
+            port: 5432,
+            database: "test_db".to_string(),
+            username: "test_user".to_string(),
+            password: "test_pass".to_string(),
+            name: "test_conn".to_string(),
+            options: Default::default(),
+            on_connect: Vec::new(),
+            max_count_rows: None,
+            max_export_rows: None,
+            password_command: None,
+        };
+        config.add_connection(conn_info).unwrap();
+
+        let without_secrets = config.export_connections(false);
+        assert_eq!(without_secrets.len(), 1);
+        assert_eq!(without_secrets[0].host, "localhost");
+        assert!(without_secrets[0].password_cipher.is_none());
+        assert!(config.exported_password(&without_secrets[0]).is_none());
+        let json = serde_json::to_string(&without_secrets).unwrap();
+        assert!(!json.contains("password"));
+
+        let with_secrets = config.export_connections(true);
+        assert_eq!(
+            config.exported_password(&with_secrets[0]).as_deref(),
+            Some("test_pass")
+        );
+    }
+
+    #[test]
+    fn test_master_password_replaces_key_file() {
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
+        config
+            .add_connection(ConnectionInfo {
+                host: "localhost".to_string(),
+                port: 5432,
+                database: "test_db".to_string(),
+                username: "test_user".to_string(),
+                password: "test_pass".to_string(),
+                name: "test_conn".to_string(),
+                options: Default::default(),
+                on_connect: Vec::new(),
+                max_count_rows: None,
+                max_export_rows: None,
+                password_command: None,
+            })
+            .unwrap();
+        let file_key = Config::get_or_create_key(temp_dir.path()).unwrap();
+        let stored_with = |config: &Config, key: &[u8; 32]| {
+            Config::stored_password(&config.connections["test_conn"], key).ok()
+        };
+
+        assert_eq!(
+            config.set_master_password(Some("correct horse")).unwrap(),
+            1
+        );
+        assert!(config.has_master_password());
+        assert_eq!(stored_with(&config, &file_key), None);
+        assert_eq!(
+            config.get_connection("test_conn").unwrap().password,
+            "test_pass"
+        );
+        // Only the salt and parameters are saved, never the key
+        let saved = fs::read_to_string(Config::get_config_file_path(temp_dir.path())).unwrap();
+        let master = config.master_password.as_ref().unwrap();
+        assert!(saved.contains(&master.salt));
+        assert!(!saved.contains(&STANDARD.encode(config.master_key.get().unwrap())));
+        assert!(config.rotate_key().is_err());
+
+        config.set_master_password(None).unwrap();
+        assert!(!config.has_master_password());
+        assert_eq!(
+            stored_with(&config, &file_key).as_deref(),
+            Some("test_pass")
+        );
+    }
+
+    #[test]
+    fn test_connection_summaries_omit_passwords() {
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
+
+        for name in ["b_conn", "a_conn"] {
+            config
+                .add_connection(ConnectionInfo {
+                    host: "localhost".to_string(),
+                    port: 5432,
+                    database: "test_db".to_string(),
+                    username: "test_user".to_string(),
+                    password: "test_pass".to_string(),
+                    name: name.to_string(),
+                    options: Default::default(),
+                    on_connect: Vec::new(),
+                    max_count_rows: None,
+                    max_export_rows: None,
+                    password_command: None,
+                })
+                .unwrap();
+        }
+
+        let summaries = config.connection_summaries();
+        assert_eq!(summaries[0].name, "a_conn");
+        assert_eq!(summaries[1].name, "b_conn");
+        let json = serde_json::to_string(&summaries).unwrap();
+        assert!(!json.contains("password"));
+        assert!(!json.contains("test_pass"));
+        assert!(json.contains(r#""username":"test_user""#));
+    }
+
+    #[test]
+    fn test_hidden_columns() {
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
+        assert!(config.hidden_columns("db", "users").is_empty());
+
+        config.set_hidden_columns("db", "users", vec!["notes".to_string()]);
+        config.save().unwrap();
+        let mut loaded = Config::load_from(temp_dir.path()).unwrap();
+        assert_eq!(loaded.hidden_columns("db", "users"), ["notes"]);
+        assert!(loaded.hidden_columns("other", "users").is_empty());
+
+        loaded.set_hidden_columns("db", "users", Vec::new());
+        assert!(loaded.hidden_columns.is_empty());
+    }
+
+    #[test]
+    fn test_saved_queries() {
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
+        assert!(!config.save_query("db", "slow", "SELECT * FROM pg_stat_statements"));
+        assert!(!config.save_query("db", "locks", "SELECT * FROM pg_locks"));
+        assert!(config.save_query("db", "slow", "SELECT query FROM pg_stat_statements"));
+        config.save().unwrap();
+
+        let mut loaded = Config::load_from(temp_dir.path()).unwrap();
+        let names: Vec<&str> = loaded
+            .saved_queries("db")
+            .iter()
+            .map(|q| q.name.as_str())
+            .collect();
+        assert_eq!(names, ["locks", "slow"]);
+        assert_eq!(
+            loaded.saved_queries("db")[1].sql,
+            "SELECT query FROM pg_stat_statements"
+        );
+        assert!(loaded.saved_queries("other").is_empty());
+
+        assert!(loaded.remove_saved_query("db", "locks"));
+        assert!(!loaded.remove_saved_query("db", "locks"));
+        assert!(loaded.remove_saved_query("db", "slow"));
+        assert!(loaded.saved_queries.is_empty());
+    }
+
+    #[test]
+    fn test_column_widths() {
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
+        assert_eq!(config.column_width("db", "users", "email"), None);
+
+        config.set_column_width("db", "users", "email", Some(30));
+        config.save().unwrap();
+        let mut loaded = Config::load_from(temp_dir.path()).unwrap();
+        assert_eq!(loaded.column_width("db", "users", "email"), Some(30));
+        assert_eq!(loaded.column_width("db", "users", "name"), None);
+
+        loaded.set_column_width("db", "users", "email", None);
+        assert!(loaded.column_widths.is_empty());
+    }
+
+    #[test]
+    fn test_connection_options_are_saved() {
+        let temp_dir = setup_test_env();
+        let mut config = Config::new_in(temp_dir.path()).unwrap();
+        let options = ConnectionOptions {
+            application_name: Some("reports".to_string()),
+            connect_timeout: Some(5),
+            sslrootcert: Some(PathBuf::from("/etc/ssl/db/root.crt")),
+            ..Default::default()
+        };
+        config
+            .add_connection(ConnectionInfo {
+                host: "localhost".to_string(),
+                port: 5432,
+                database: "app".to_string(),
+                username: "user".to_string(),
+                password: "secret".to_string(),
+                name: "app".to_string(),
+                options: options.clone(),
+                on_connect: vec!["SET search_path = app".to_string()],
+                max_count_rows: None,
+                max_export_rows: None,
+                password_command: None,
+            })
+            .unwrap();
+        config.save().unwrap();
+
+        let loaded = Config::load_from(temp_dir.path()).unwrap();
+        assert_eq!(loaded.get_connection("app").unwrap().options, options);
+        assert_eq!(loaded.export_connections(false)[0].options, options);
+        assert_eq!(
+            loaded.get_connection("app").unwrap().on_connect,
+            ["SET search_path = app"]
+        );
+    }
+
+    #[test]
+    fn test_to_connection_string() {
+        let info = ConnectionInfo {
+            host: "/var/run/postgresql".to_string(),
+            port: 5432,
+            database: "app db".to_string(),
+            username: "me@corp".to_string(),
+            password: "p@ss:w/rd".to_string(),
+            name: "app".to_string(),
+            options: ConnectionOptions {
+                application_name: Some("daedalus cli".to_string()),
+                sslmode: Some("require".to_string()),
+                ..Default::default()
+            },
+            on_connect: Vec::new(),
+            max_count_rows: None,
+            max_export_rows: None,
+            password_command: None,
+        };
+        assert_eq!(
+            info.to_connection_string(false),
+            "postgresql://me%40corp@%2Fvar%2Frun%2Fpostgresql:5432/app%20db?application_name=daedalus%20cli&sslmode=require"
+        );
+        assert!(
+            info.to_connection_string(true)
+                .starts_with("postgresql://me%40corp:p%40ss%3Aw%2Frd@")
+        );
+    }
+
+    #[test]
+    fn test_init_statement() {
+        assert_eq!(
+            init_statement(" SET timezone = 'UTC' "),
+            ("SET timezone = 'UTC'", false)
+        );
+        assert_eq!(
+            init_statement("? CREATE EXTENSION IF NOT EXISTS pg_trgm"),
+            ("CREATE EXTENSION IF NOT EXISTS pg_trgm", true)
+        );
+    }
 }
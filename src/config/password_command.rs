@@ -0,0 +1,85 @@
+//! Passwords fetched by running a command, for servers that take short-lived tokens
+//! as passwords, such as `aws rds generate-db-auth-token` or `gcloud sql generate-login-token`.
+//!
+//! The command runs through the shell every time a connection is opened, so each one
+//! gets a fresh token, and what it prints is the password. It can't read from the
+//! terminal, which the TUI has in raw mode, and what it writes to stderr is kept for the
+//! error if it fails.
+
+use anyhow::{Result, anyhow};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// How long a password command may run before connecting gives up on it.
+pub const PASSWORD_COMMAND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Run `command` and return what it printed, without the line break at the end.
+pub async fn run(command: &str, timeout: Duration) -> Result<String> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let child = shell
+        .arg(command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| anyhow!("Failed to run the password command: {}", e))?;
+
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "The password command didn't finish within {} seconds",
+                timeout.as_secs()
+            )
+        })?
+        .map_err(|e| anyhow!("Failed to run the password command: {}", e))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(match stderr.trim() {
+            "" => anyhow!("The password command failed with {}", output.status),
+            stderr => anyhow!(
+                "The password command failed with {}: {}",
+                output.status,
+                stderr
+            ),
+        });
+    }
+    let stdout = String::from_utf8(output.stdout)
+        .map_err(|_| anyhow!("The password command printed something that isn't UTF-8"))?;
+    let password = stdout.trim_end_matches(['\r', '\n']);
+    if password.is_empty() {
+        return Err(anyhow!("The password command printed no password"));
+    }
+    Ok(password.to_string())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_run() {
+        let timeout = Duration::from_secs(5);
+        assert_eq!(run("echo 't0ken '", timeout).await.unwrap(), "t0ken ");
+
+        let error = run("echo expired >&2; exit 3", timeout).await.unwrap_err();
+        assert!(error.to_string().ends_with(": expired"), "{}", error);
+        assert!(run("true", timeout).await.is_err());
+
+        let error = run("sleep 5", Duration::from_millis(100))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("didn't finish"), "{}", error);
+    }
+}
@@ -0,0 +1,245 @@
+//! Parsers for libpq's connection service file (`~/.pg_service.conf`) and password
+//! file (`~/.pgpass`), used to import existing connection definitions.
+
+use std::path::PathBuf;
+
+/// A `[name]` section of a connection service file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceEntry {
+    pub name: String,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub dbname: Option<String>,
+    pub user: Option<String>,
+    pub password: Option<String>,
+}
+
+/// Parse the INI-style contents of a connection service file.
+///
+/// Returns the parsed services along with warnings for lines that could not be understood.
+pub fn parse_service_file(contents: &str) -> (Vec<ServiceEntry>, Vec<String>) {
+    let mut services: Vec<ServiceEntry> = Vec::new();
+    let mut warnings = Vec::new();
+
+    for (line_no, raw_line) in contents.lines().enumerate() {
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+
+        if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            services.push(ServiceEntry {
+                name: name.trim().to_string(),
+                host: None,
+                port: None,
+                dbname: None,
+                user: None,
+                password: None,
+            });
+            continue;
+        }
+
+        let Some((key, value)) = line.split_once('=') else {
+            warnings.push(format!("line {}: expected key=value", line_no + 1));
+            continue;
+        };
+        let Some(service) = services.last_mut() else {
+            warnings.push(format!(
+                "line {}: setting outside of a [service]",
+                line_no + 1
+            ));
+            continue;
+        };
+
+        let value = value.trim().to_string();
+        match key.trim() {
+            "host" | "hostaddr" => service.host = Some(value),
+            "port" => match value.parse() {
+                Ok(port) => service.port = Some(port),
+                Err(_) => warnings.push(format!("line {}: invalid port '{}'", line_no + 1, value)),
+            },
+            "dbname" => service.dbname = Some(value),
+            "user" => service.user = Some(value),
+            "password" => service.password = Some(value),
+            // Other libpq options (sslmode, application_name, ...) are not stored
+            _ => {}
+        }
+    }
+
+    (services, warnings)
+}
+
+/// One `hostname:port:database:username:password` line of a password file.
+#[derive(Debug, Clone, PartialEq)]
+struct PgPassEntry {
+    host: String,
+    port: String,
+    database: String,
+    username: String,
+    password: String,
+}
+
+/// The parsed contents of a `.pgpass` file.
+#[derive(Debug, Default)]
+pub struct PgPass {
+    entries: Vec<PgPassEntry>,
+}
+
+impl PgPass {
+    pub fn parse(contents: &str) -> PgPass {
+        let entries = contents
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = split_pgpass_line(line).into_iter();
+                Some(PgPassEntry {
+                    host: fields.next()?,
+                    port: fields.next()?,
+                    database: fields.next()?,
+                    username: fields.next()?,
+                    password: fields.next()?,
+                })
+            })
+            .collect();
+        PgPass { entries }
+    }
+
+    /// Find the password for a connection using libpq's rules: the first line whose
+    /// fields all match (literally or via `*`) wins.
+    pub fn find_password(
+        &self,
+        host: &str,
+        port: u16,
+        database: &str,
+        username: &str,
+    ) -> Option<&str> {
+        let port = port.to_string();
+        let matches = |pattern: &str, value: &str| pattern == "*" || pattern == value;
+        self.entries
+            .iter()
+            .find(|e| {
+                matches(&e.host, host)
+                    && matches(&e.port, &port)
+                    && matches(&e.database, database)
+                    && matches(&e.username, username)
+            })
+            .map(|e| e.password.as_str())
+    }
+}
+
+// Split a pgpass line on unescaped colons, resolving `\:` and `\\` escapes.
+// The password field keeps any further colons.
+fn split_pgpass_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut chars = line.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(next) = chars.next() {
+                    current.push(next);
+                }
+            }
+            ':' if fields.len() < 4 => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+/// Location of the user's service file, honouring `PGSERVICEFILE`.
+pub fn default_service_file() -> PathBuf {
+    std::env::var_os("PGSERVICEFILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_file(".pg_service.conf"))
+}
+
+/// Location of the user's password file, honouring `PGPASSFILE`.
+pub fn default_pgpass_file() -> PathBuf {
+    std::env::var_os("PGPASSFILE")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| home_file(".pgpass"))
+}
+
+fn home_file(name: &str) -> PathBuf {
+    let mut path = dirs::home_dir().unwrap_or_else(|| PathBuf::from("."));
+    path.push(name);
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_service_file() {
+        let contents = "\
+# comment
+[prod]
+host=db.example.com
+port=6543
+dbname=app
+user=admin
+
+[local]
+dbname = scratch
+sslmode=disable
+";
+        let (services, warnings) = parse_service_file(contents);
+        assert!(warnings.is_empty());
+        assert_eq!(services.len(), 2);
+        assert_eq!(services[0].name, "prod");
+        assert_eq!(services[0].host.as_deref(), Some("db.example.com"));
+        assert_eq!(services[0].port, Some(6543));
+        assert_eq!(services[0].user.as_deref(), Some("admin"));
+        assert_eq!(services[1].name, "local");
+        assert_eq!(services[1].dbname.as_deref(), Some("scratch"));
+        assert!(services[1].host.is_none());
+    }
+
+    #[test]
+    fn test_service_file_warnings() {
+        let (services, warnings) = parse_service_file("host=orphan\n[svc]\nport=abc\nnonsense\n");
+        assert_eq!(services.len(), 1);
+        assert_eq!(warnings.len(), 3);
+    }
+
+    #[test]
+    fn test_pgpass_wildcards_and_order() {
+        let pgpass = PgPass::parse(
+            "\
+# host:port:db:user:password
+db.example.com:5432:app:admin:exact
+*:*:app:admin:any-host
+*:*:*:*:fallback
+",
+        );
+        assert_eq!(
+            pgpass.find_password("db.example.com", 5432, "app", "admin"),
+            Some("exact")
+        );
+        assert_eq!(
+            pgpass.find_password("other", 5433, "app", "admin"),
+            Some("any-host")
+        );
+        assert_eq!(
+            pgpass.find_password("other", 5432, "misc", "guest"),
+            Some("fallback")
+        );
+    }
+
+    #[test]
+    fn test_pgpass_escapes() {
+        let pgpass = PgPass::parse("local\\:host:5432:app:admin:pa\\:ss:word\n");
+        assert_eq!(
+            pgpass.find_password("local:host", 5432, "app", "admin"),
+            Some("pa:ss:word")
+        );
+        assert_eq!(
+            pgpass.find_password("localhost", 5432, "app", "admin"),
+            None
+        );
+    }
+}
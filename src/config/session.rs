@@ -0,0 +1,138 @@
+//! Session state that is remembered between runs but is not part of the connection
+//! configuration, such as the last table viewed on each connection and when each
+//! connection was last opened.
+//!
+//! Stored separately in `~/.daedalus-cli/state.json` so that browsing never rewrites
+//! `config.json`.
+
+use super::Config;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where the user was when they last browsed a connection.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LastView {
+    pub table: String,
+    pub page: u32,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct SessionState {
+    #[serde(default)]
+    last_views: HashMap<String, LastView>,
+    /// Seconds since the Unix epoch at which each connection was last opened
+    #[serde(default)]
+    last_connected: HashMap<String, u64>,
+    #[serde(skip)]
+    dir: PathBuf,
+}
+
+impl SessionState {
+    /// Load the session state. Missing or unreadable state is not an error; it just means
+    /// there is nothing to restore.
+    pub fn load() -> SessionState {
+        Self::load_from(&Config::get_config_dir())
+    }
+
+    /// Load the session state kept in `dir`.
+    pub fn load_from(dir: &Path) -> SessionState {
+        let mut state: SessionState = fs::read_to_string(Self::get_state_file_path(dir))
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default();
+        state.dir = dir.to_path_buf();
+        state
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::get_state_file_path(&self.dir);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        Config::write_atomic(&path, serde_json::to_string_pretty(self)?.as_bytes())
+    }
+
+    pub fn last_view(&self, connection: &str) -> Option<&LastView> {
+        self.last_views.get(connection)
+    }
+
+    pub fn remember_view(&mut self, connection: &str, table: &str, page: u32) {
+        self.last_views.insert(
+            connection.to_string(),
+            LastView {
+                table: table.to_string(),
+                page,
+            },
+        );
+    }
+
+    /// Forget the remembered view for a connection, returning whether there was one.
+    pub fn forget_view(&mut self, connection: &str) -> bool {
+        self.last_views.remove(connection).is_some()
+    }
+
+    /// Note that a connection was opened just now.
+    pub fn remember_connected(&mut self, connection: &str) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        self.last_connected.insert(connection.to_string(), now);
+    }
+
+    /// Sort connection names most recently opened first. The sort is stable, so names
+    /// never opened keep their order after all the others.
+    pub fn sort_recent_first(&self, connections: &mut [String]) {
+        connections.sort_by_key(|name| std::cmp::Reverse(self.last_connected.get(name)));
+    }
+
+    fn get_state_file_path(dir: &Path) -> PathBuf {
+        dir.join("state.json")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_remember_and_forget_view() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        let mut state = SessionState::load_from(temp_dir.path());
+        assert!(state.last_view("conn").is_none());
+
+        state.remember_view("conn", "users", 3);
+        state.save().unwrap();
+
+        let mut loaded = SessionState::load_from(temp_dir.path());
+        assert_eq!(
+            loaded.last_view("conn"),
+            Some(&LastView {
+                table: "users".to_string(),
+                page: 3
+            })
+        );
+
+        assert!(loaded.forget_view("conn"));
+        assert!(!loaded.forget_view("conn"));
+        assert!(loaded.last_view("conn").is_none());
+    }
+
+    #[test]
+    fn test_sort_recent_first() {
+        let mut state = SessionState::default();
+        state.last_connected.insert("old".to_string(), 100);
+        state.last_connected.insert("new".to_string(), 200);
+        let mut names: Vec<String> = ["a", "old", "b", "new"].map(String::from).to_vec();
+        state.sort_recent_first(&mut names);
+        assert_eq!(names, ["new", "old", "a", "b"]);
+
+        state.remember_connected("b");
+        state.sort_recent_first(&mut names);
+        assert_eq!(names, ["b", "new", "old", "a"]);
+    }
+}
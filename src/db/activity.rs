@@ -0,0 +1,162 @@
+//! The server's backends, as shown by the activity view.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// What `pg_stat_activity` reports for a query when the user may not see it.
+pub const HIDDEN_QUERY: &str = "<insufficient privilege>";
+
+/// One client backend from `pg_stat_activity`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backend {
+    pub pid: i32,
+    pub username: String,
+    pub application_name: String,
+    pub state: String, // e.g. "active" or "idle in transaction"; empty when hidden
+    pub query: String, // The running or last query, or `HIDDEN_QUERY`
+    pub elapsed: Option<Duration>, // In the current state: how long the query has run if active
+    pub transaction_age: Option<Duration>, // Since the open transaction began, if there is one
+    pub blocked_by: Vec<i32>, // The backends holding up the lock it waits for; only filled in by `lock_waits`
+    pub blocking: Vec<i32>,   // The backends waiting for a lock it holds; likewise
+}
+
+/// Which backends the activity view lists.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ActivityFilter {
+    #[default]
+    All,
+    /// Sessions idle inside a transaction, oldest transaction first
+    IdleInTransaction,
+    /// Sessions waiting for a lock and those holding them up; see `lock_waits`
+    LockWaits,
+}
+
+impl Backend {
+    /// Whether the server withheld this backend's details, because it belongs to another
+    /// user and the current one lacks `pg_read_all_stats`.
+    pub fn is_hidden(&self) -> bool {
+        self.query == HIDDEN_QUERY
+    }
+
+    /// The query on a single line, for the activity table.
+    pub fn query_line(&self) -> String {
+        self.query.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+}
+
+/// Keep the backends that wait for a lock or hold one up, given the pids blocking each
+/// waiting backend as `pg_blocking_pids()` reports them. Blockers that aren't waiting
+/// themselves come first, since ending one of those frees the others, then those waiting,
+/// longest first. A blocker that isn't among `backends`, such as a prepared transaction,
+/// only appears in `blocked_by`.
+pub fn lock_waits(backends: Vec<Backend>, blocked_by: &HashMap<i32, Vec<i32>>) -> Vec<Backend> {
+    let mut waits: Vec<Backend> = backends
+        .into_iter()
+        .map(|mut backend| {
+            backend.blocked_by = blocked_by.get(&backend.pid).cloned().unwrap_or_default();
+            backend.blocking = blocked_by
+                .iter()
+                .filter(|(_, blockers)| blockers.contains(&backend.pid))
+                .map(|(pid, _)| *pid)
+                .collect();
+            backend.blocking.sort();
+            backend
+        })
+        .filter(|backend| !backend.blocked_by.is_empty() || !backend.blocking.is_empty())
+        .collect();
+    waits.sort_by(|a, b| {
+        (!a.blocked_by.is_empty())
+            .cmp(&!b.blocked_by.is_empty())
+            .then_with(|| b.elapsed.cmp(&a.elapsed))
+            .then_with(|| a.pid.cmp(&b.pid))
+    });
+    waits
+}
+
+/// Pids as a comma-separated list, for the activity table.
+pub fn format_pids(pids: &[i32]) -> String {
+    pids.iter()
+        .map(|pid| pid.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// A short human form of how long a backend has been in its state, e.g. `4m 07s`.
+pub fn format_elapsed(elapsed: Duration) -> String {
+    let seconds = elapsed.as_secs();
+    match seconds {
+        s if s < 60 => format!("{:.1}s", elapsed.as_secs_f64()),
+        s if s < 3600 => format!("{}m {:02}s", s / 60, s % 60),
+        s => format!("{}h {:02}m", s / 3600, (s % 3600) / 60),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_elapsed() {
+        assert_eq!(format_elapsed(Duration::from_millis(2500)), "2.5s");
+        assert_eq!(format_elapsed(Duration::from_secs(247)), "4m 07s");
+        assert_eq!(format_elapsed(Duration::from_secs(7260)), "2h 01m");
+    }
+
+    #[test]
+    fn test_query_line() {
+        let backend = Backend {
+            pid: 1,
+            username: "app".to_string(),
+            application_name: String::new(),
+            state: "active".to_string(),
+            query: "SELECT *\n  FROM users\n WHERE id = 1".to_string(),
+            elapsed: None,
+            transaction_age: None,
+            blocked_by: Vec::new(),
+            blocking: Vec::new(),
+        };
+        assert_eq!(backend.query_line(), "SELECT * FROM users WHERE id = 1");
+        assert!(!backend.is_hidden());
+    }
+
+    #[test]
+    fn test_lock_waits() {
+        let backend = |pid: i32, seconds: u64| Backend {
+            pid,
+            username: "app".to_string(),
+            application_name: String::new(),
+            state: "active".to_string(),
+            query: String::new(),
+            elapsed: Some(Duration::from_secs(seconds)),
+            transaction_age: None,
+            blocked_by: Vec::new(),
+            blocking: Vec::new(),
+        };
+        // 10 holds up 11, which holds up 12; 13 waits for a prepared transaction (pid 0)
+        let blocked_by = HashMap::from([(11, vec![10]), (12, vec![11]), (13, vec![0])]);
+        let waits = lock_waits(
+            vec![
+                backend(10, 300),
+                backend(11, 20),
+                backend(12, 60),
+                backend(13, 5),
+                backend(14, 900),
+            ],
+            &blocked_by,
+        );
+        let summary: Vec<_> = waits
+            .iter()
+            .map(|b| (b.pid, b.blocked_by.clone(), b.blocking.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (10, vec![], vec![11]),
+                (12, vec![11], vec![]),
+                (11, vec![10], vec![12]),
+                (13, vec![0], vec![]),
+            ]
+        );
+        assert_eq!(format_pids(&[10, 0]), "10, 0");
+    }
+}
@@ -0,0 +1,223 @@
+//! The structure of a table, as shown by the describe view.
+
+use super::sql::quote_identifier;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnDescription {
+    pub name: String,
+    pub data_type: String, // As printed by format_type, e.g. "character varying(50)"
+    pub not_null: bool,
+    pub default: Option<String>,
+    pub identity: bool,  // GENERATED ... AS IDENTITY
+    pub generated: bool, // GENERATED ALWAYS AS (...) STORED, whose expression is `default`
+}
+
+/// A primary key, unique, foreign key, check or exclusion constraint.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConstraintDescription {
+    pub name: String,
+    pub definition: String, // As printed by pg_get_constraintdef, e.g. "PRIMARY KEY (id)"
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexDescription {
+    pub name: String,
+    pub definition: String, // The full CREATE INDEX statement
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableDescription {
+    pub table: String,
+    pub columns: Vec<ColumnDescription>,
+    pub constraints: Vec<ConstraintDescription>,
+    pub indexes: Vec<IndexDescription>,
+}
+
+impl TableDescription {
+    /// Lines for the describe view, similar to psql's `\d`, followed by the
+    /// reconstructed DDL.
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines = vec![format!("Table \"{}\"", self.table), String::new()];
+
+        let name_width = self
+            .columns
+            .iter()
+            .map(|c| c.name.chars().count())
+            .chain(["Column".len()])
+            .max()
+            .unwrap_or(0);
+        let type_width = self
+            .columns
+            .iter()
+            .map(|c| c.data_type.chars().count())
+            .chain(["Type".len()])
+            .max()
+            .unwrap_or(0);
+
+        lines.push(format!(
+            "{:name_width$}  {:type_width$}  {:8}  Default",
+            "Column", "Type", "Nullable"
+        ));
+        for column in &self.columns {
+            lines.push(
+                format!(
+                    "{:name_width$}  {:type_width$}  {:8}  {}",
+                    column.name,
+                    column.data_type,
+                    if column.not_null { "not null" } else { "" },
+                    column.default.as_deref().unwrap_or("")
+                )
+                .trim_end()
+                .to_string(),
+            );
+        }
+
+        if !self.indexes.is_empty() {
+            lines.push(String::new());
+            lines.push("Indexes:".to_string());
+            for index in &self.indexes {
+                lines.push(format!("    {}", index.definition));
+            }
+        }
+
+        if !self.constraints.is_empty() {
+            lines.push(String::new());
+            lines.push("Constraints:".to_string());
+            for constraint in &self.constraints {
+                lines.push(format!("    {} {}", constraint.name, constraint.definition));
+            }
+        }
+
+        lines.push(String::new());
+        lines.push("DDL:".to_string());
+        lines.extend(self.create_table_sql().lines().map(str::to_string));
+        lines
+    }
+
+    /// A `CREATE TABLE` statement recreating the table, followed by `CREATE INDEX`
+    /// statements for the indexes that don't back a constraint.
+    pub fn create_table_sql(&self) -> String {
+        let mut definitions: Vec<String> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let mut definition =
+                    format!("{} {}", quote_identifier(&column.name), column.data_type);
+                if column.not_null {
+                    definition.push_str(" NOT NULL");
+                }
+                if let Some(default) = &column.default {
+                    definition.push_str(" DEFAULT ");
+                    definition.push_str(default);
+                }
+                definition
+            })
+            .collect();
+        definitions.extend(self.constraints.iter().map(|constraint| {
+            format!(
+                "CONSTRAINT {} {}",
+                quote_identifier(&constraint.name),
+                constraint.definition
+            )
+        }));
+
+        let mut sql = format!(
+            "CREATE TABLE {} (\n    {}\n);",
+            quote_identifier(&self.table),
+            definitions.join(",\n    ")
+        );
+        // Primary key and unique constraints create their own index of the same name
+        for index in &self.indexes {
+            if !self.constraints.iter().any(|c| c.name == index.name) {
+                sql.push('\n');
+                sql.push_str(&index.definition);
+                sql.push(';');
+            }
+        }
+        sql
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn projects() -> TableDescription {
+        TableDescription {
+            table: "projects".to_string(),
+            columns: vec![
+                ColumnDescription {
+                    name: "id".to_string(),
+                    data_type: "integer".to_string(),
+                    not_null: true,
+                    default: Some("nextval('projects_id_seq'::regclass)".to_string()),
+                    identity: false,
+                    generated: false,
+                },
+                ColumnDescription {
+                    name: "owner_id".to_string(),
+                    data_type: "integer".to_string(),
+                    not_null: false,
+                    default: None,
+                    identity: false,
+                    generated: false,
+                },
+            ],
+            constraints: vec![
+                ConstraintDescription {
+                    name: "projects_pkey".to_string(),
+                    definition: "PRIMARY KEY (id)".to_string(),
+                },
+                ConstraintDescription {
+                    name: "projects_owner_id_fkey".to_string(),
+                    definition: "FOREIGN KEY (owner_id) REFERENCES users(id)".to_string(),
+                },
+            ],
+            indexes: vec![
+                IndexDescription {
+                    name: "projects_pkey".to_string(),
+                    definition:
+                        "CREATE UNIQUE INDEX projects_pkey ON public.projects USING btree (id)"
+                            .to_string(),
+                },
+                IndexDescription {
+                    name: "projects_owner_idx".to_string(),
+                    definition:
+                        "CREATE INDEX projects_owner_idx ON public.projects USING btree (owner_id)"
+                            .to_string(),
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_create_table_sql() {
+        assert_eq!(
+            projects().create_table_sql(),
+            "CREATE TABLE \"projects\" (
+    \"id\" integer NOT NULL DEFAULT nextval('projects_id_seq'::regclass),
+    \"owner_id\" integer,
+    CONSTRAINT \"projects_pkey\" PRIMARY KEY (id),
+    CONSTRAINT \"projects_owner_id_fkey\" FOREIGN KEY (owner_id) REFERENCES users(id)
+);
+CREATE INDEX projects_owner_idx ON public.projects USING btree (owner_id);"
+        );
+    }
+
+    #[test]
+    fn test_to_lines() {
+        let lines = projects().to_lines();
+        assert_eq!(lines[0], "Table \"projects\"");
+        assert_eq!(lines[2], "Column    Type     Nullable  Default");
+        assert_eq!(
+            lines[3],
+            "id        integer  not null  nextval('projects_id_seq'::regclass)"
+        );
+        assert_eq!(lines[4], "owner_id  integer");
+        assert!(lines.contains(&"Indexes:".to_string()));
+        assert!(lines.contains(
+            &"    projects_owner_id_fkey FOREIGN KEY (owner_id) REFERENCES users(id)".to_string()
+        ));
+        assert!(lines.contains(&"DDL:".to_string()));
+    }
+}
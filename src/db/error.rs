@@ -0,0 +1,155 @@
+//! The errors returned by `DatabaseConnection`, classified by what went wrong so callers
+//! can react to the kind of failure rather than parse its message.
+
+use std::fmt;
+use tokio_postgres::error::SqlState;
+
+/// What went wrong talking to the database. Every variant carries the full message,
+/// which is what `Display` prints.
+#[derive(Debug)]
+pub enum DbError {
+    /// The connection couldn't be established
+    Connect(String),
+    /// The connection to the server was closed or broke while in use
+    ConnectionLost(String),
+    /// The table (or other relation) a query names doesn't exist
+    UndefinedTable(String),
+    /// The user lacks a privilege the query needs
+    PermissionDenied(String),
+    /// The query was canceled, e.g. by `statement_timeout`
+    Canceled(String),
+    /// Any other error the server reported
+    Server {
+        #[allow(dead_code)] // For callers matching on a particular code
+        code: SqlState,
+        message: String,
+    },
+    /// Anything else, such as a request refused before it reached the server
+    Other(String),
+}
+
+impl DbError {
+    /// Classify a tokio-postgres error, prefixing its message with `context`.
+    pub fn query(context: &str, error: &tokio_postgres::Error) -> DbError {
+        DbError::classify(error, format!("{}: {}", context, error_message(error)))
+    }
+
+    fn classify(error: &tokio_postgres::Error, message: String) -> DbError {
+        let Some(code) = error.code() else {
+            // Without a SQLSTATE the server didn't report the error; a closed connection
+            // or failed socket is the usual cause
+            let io_error = std::error::Error::source(error)
+                .is_some_and(|source| source.is::<std::io::Error>());
+            return if error.is_closed() || io_error {
+                DbError::ConnectionLost(message)
+            } else {
+                DbError::Other(message)
+            };
+        };
+        match code {
+            c if *c == SqlState::UNDEFINED_TABLE => DbError::UndefinedTable(message),
+            c if *c == SqlState::INSUFFICIENT_PRIVILEGE => DbError::PermissionDenied(message),
+            c if *c == SqlState::QUERY_CANCELED => DbError::Canceled(message),
+            // Class 08 is connection exceptions; the others are the server shutting down
+            c if c.code().starts_with("08")
+                || *c == SqlState::ADMIN_SHUTDOWN
+                || *c == SqlState::CRASH_SHUTDOWN =>
+            {
+                DbError::ConnectionLost(message)
+            }
+            c => DbError::Server {
+                code: c.clone(),
+                message,
+            },
+        }
+    }
+
+    /// A failed attempt to connect. The message includes what caused it, such as why a
+    /// TLS handshake failed, which tokio-postgres' own message leaves out.
+    pub fn connect(error: &tokio_postgres::Error) -> DbError {
+        let mut message = format!("Failed to connect to database: {}", error_message(error));
+        if error.as_db_error().is_none() {
+            let mut source = std::error::Error::source(error);
+            while let Some(cause) = source {
+                message.push_str(&format!(": {}", cause));
+                source = cause.source();
+            }
+        }
+        DbError::Connect(message)
+    }
+
+    /// The error that ended a connection while it was open, e.g. the server terminating it.
+    pub fn lost(error: &tokio_postgres::Error) -> DbError {
+        DbError::ConnectionLost(format!("Connection lost: {}", error_message(error)))
+    }
+
+    /// Whether the connection has to be reopened before anything else can be run.
+    pub fn is_connection_lost(&self) -> bool {
+        matches!(self, DbError::ConnectionLost(_))
+    }
+}
+
+impl From<tokio_postgres::Error> for DbError {
+    fn from(error: tokio_postgres::Error) -> DbError {
+        DbError::classify(&error, error_message(&error))
+    }
+}
+
+impl fmt::Display for DbError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DbError::Connect(message)
+            | DbError::ConnectionLost(message)
+            | DbError::UndefinedTable(message)
+            | DbError::PermissionDenied(message)
+            | DbError::Canceled(message)
+            | DbError::Server { message, .. }
+            | DbError::Other(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for DbError {}
+
+/// Whether an error from a call into the db module means the connection was lost.
+pub fn is_connection_lost(error: &anyhow::Error) -> bool {
+    error
+        .downcast_ref::<DbError>()
+        .is_some_and(DbError::is_connection_lost)
+}
+
+// The server's own message for errors it reported ("division by zero"), rather than
+// tokio-postgres' generic "db error"
+fn error_message(error: &tokio_postgres::Error) -> String {
+    match error.as_db_error() {
+        Some(db_error) => db_error.message().to_string(),
+        None => error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_is_the_message() {
+        let error = DbError::Server {
+            code: SqlState::DIVISION_BY_ZERO,
+            message: "Failed to execute custom query: division by zero".to_string(),
+        };
+        assert_eq!(
+            error.to_string(),
+            "Failed to execute custom query: division by zero"
+        );
+    }
+
+    #[test]
+    fn test_is_connection_lost_through_anyhow() {
+        let lost = anyhow::Error::from(DbError::ConnectionLost("connection closed".to_string()));
+        assert!(is_connection_lost(&lost));
+
+        let denied = anyhow::Error::from(DbError::PermissionDenied("denied".to_string()));
+        assert!(!is_connection_lost(&denied));
+        assert!(!is_connection_lost(&anyhow::anyhow!("connection closed")));
+    }
+}
@@ -0,0 +1,64 @@
+//! The schemas, relations and columns the object tree browses.
+
+/// What kind of relation a `pg_class` row is, from its `relkind`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RelationKind {
+    Table,
+    PartitionedTable,
+    View,
+    MaterializedView,
+    ForeignTable,
+}
+
+impl RelationKind {
+    /// The kind for a `relkind`, or None for indexes, sequences and other non-relations.
+    pub fn from_relkind(relkind: i8) -> Option<RelationKind> {
+        match relkind as u8 {
+            b'r' => Some(RelationKind::Table),
+            b'p' => Some(RelationKind::PartitionedTable),
+            b'v' => Some(RelationKind::View),
+            b'm' => Some(RelationKind::MaterializedView),
+            b'f' => Some(RelationKind::ForeignTable),
+            _ => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RelationKind::Table => "table",
+            RelationKind::PartitionedTable => "partitioned table",
+            RelationKind::View => "view",
+            RelationKind::MaterializedView => "materialized view",
+            RelationKind::ForeignTable => "foreign table",
+        }
+    }
+}
+
+/// A relation in a schema, whose rows can be browsed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relation {
+    pub name: String,
+    pub kind: RelationKind,
+}
+
+/// A column of a relation with its type as `format_type` writes it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelationColumn {
+    pub name: String,
+    pub data_type: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relation_kind() {
+        assert_eq!(
+            RelationKind::from_relkind(b'm' as i8),
+            Some(RelationKind::MaterializedView)
+        );
+        assert_eq!(RelationKind::from_relkind(b'i' as i8), None);
+        assert_eq!(RelationKind::PartitionedTable.label(), "partitioned table");
+    }
+}
@@ -0,0 +1,908 @@
+//! Helpers for building SQL text safely.
+
+use super::Maintenance;
+
+/// Quote an identifier (table or column name) for use in SQL.
+pub fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Quote a string as an SQL literal.
+pub fn quote_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+/// Whether values of the given column type can be written as bare numeric literals.
+pub fn is_numeric_type(data_type: &str) -> bool {
+    let base = data_type.split('(').next().unwrap_or("").trim();
+    matches!(
+        base,
+        "smallint"
+            | "integer"
+            | "bigint"
+            | "numeric"
+            | "decimal"
+            | "real"
+            | "double precision"
+            | "int2"
+            | "int4"
+            | "int8"
+            | "float4"
+            | "float8"
+    )
+}
+
+/// Whether `value` is a plain number such as `-12`, `3.5` or `1e-3` that can be written
+/// as a bare literal. Special values such as `NaN` and `Infinity` are not: they are only
+/// valid quoted.
+pub fn is_numeric_literal(value: &str) -> bool {
+    let unsigned = value.strip_prefix(['-', '+']).unwrap_or(value);
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (unsigned, None),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+    let exponent_ok = exponent.is_none_or(|exponent| {
+        let exponent = exponent.strip_prefix(['-', '+']).unwrap_or(exponent);
+        !exponent.is_empty() && digits(exponent)
+    });
+    !(whole.is_empty() && fraction.is_empty()) && digits(whole) && digits(fraction) && exponent_ok
+}
+
+/// Build an `INSERT` statement reproducing a row as it was displayed.
+///
+/// `columns` pairs each column name with its data type. Plain numbers in numeric
+/// columns are left unquoted and everything else is quoted as a string literal. The
+/// grid shows NULL as the text `NULL`, so a value reading `NULL` is written as SQL
+/// `NULL`, even for a text column that holds those four letters.
+pub fn insert_statement(table: &str, columns: &[(String, String)], values: &[String]) -> String {
+    let names = columns
+        .iter()
+        .map(|(name, _)| quote_identifier(name))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let literals = columns
+        .iter()
+        .zip(values)
+        .map(|((_, data_type), value)| {
+            if value == "NULL" {
+                "NULL".to_string()
+            } else if is_numeric_type(data_type) && is_numeric_literal(value) {
+                value.clone()
+            } else {
+                quote_literal(value)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    format!(
+        "INSERT INTO {} ({}) VALUES ({});",
+        quote_identifier(table),
+        names,
+        literals
+    )
+}
+
+/// Build an `INSERT` of one row with a `$n` parameter for each of `columns`, or of all
+/// defaults when there are none.
+pub fn parameterized_insert(table: &str, columns: &[String]) -> String {
+    if columns.is_empty() {
+        return format!("INSERT INTO {} DEFAULT VALUES", quote_identifier(table));
+    }
+    let names: Vec<String> = columns.iter().map(|name| quote_identifier(name)).collect();
+    let params: Vec<String> = (1..=columns.len()).map(|i| format!("${}", i)).collect();
+    format!(
+        "INSERT INTO {} ({}) VALUES ({})",
+        quote_identifier(table),
+        names.join(", "),
+        params.join(", ")
+    )
+}
+
+/// Build a query for one column of the row whose key column is `$1`.
+pub fn cell_query(table: &str, column: &str, key_column: &str) -> String {
+    format!(
+        "SELECT {} FROM {} WHERE {} = $1",
+        quote_identifier(column),
+        quote_identifier(table),
+        quote_identifier(key_column)
+    )
+}
+
+/// Build an `UPDATE` setting one column to `$1` in the row whose key column is `$2`.
+pub fn parameterized_update(table: &str, column: &str, key_column: &str) -> String {
+    format!(
+        "UPDATE {} SET {} = $1 WHERE {} = $2",
+        quote_identifier(table),
+        quote_identifier(column),
+        quote_identifier(key_column)
+    )
+}
+
+/// Build the statement running a maintenance command on a table, or the reason it's
+/// refused: `VACUUM` can't run inside a transaction block.
+pub fn maintenance_statement(
+    table: &str,
+    kind: Maintenance,
+    in_transaction: bool,
+) -> Result<String, &'static str> {
+    if kind == Maintenance::VacuumAnalyze && in_transaction {
+        return Err("VACUUM can't run inside a transaction; commit or roll back first");
+    }
+    Ok(format!("{} {}", kind.command(), quote_identifier(table)))
+}
+
+/// Split a script into individual statements on top-level semicolons.
+///
+/// Semicolons inside string literals, quoted identifiers, comments and dollar-quoted
+/// bodies do not end a statement. Statements that are empty or contain only comments
+/// are dropped, and the terminating semicolons are not included.
+pub fn split_statements(input: &str) -> Vec<String> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut i = 0;
+
+    let mut finish = |current: &mut String| {
+        let statement = current.trim();
+        if !strip_leading_comments(statement).is_empty() {
+            statements.push(statement.to_string());
+        }
+        current.clear();
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        match c {
+            '\'' | '"' => {
+                // E'...' strings allow backslash escapes
+                let backslash_escapes = c == '\''
+                    && i > 0
+                    && matches!(chars[i - 1], 'e' | 'E')
+                    && (i < 2 || !is_identifier_char(chars[i - 2]));
+                let end = skip_quoted(&chars, i, c, backslash_escapes);
+                current.extend(&chars[i..end]);
+                i = end;
+            }
+            '-' if next == Some('-') => {
+                let end = chars[i..]
+                    .iter()
+                    .position(|&c| c == '\n')
+                    .map_or(chars.len(), |p| i + p + 1);
+                current.extend(&chars[i..end]);
+                i = end;
+            }
+            '/' if next == Some('*') => {
+                let end = skip_block_comment(&chars, i);
+                current.extend(&chars[i..end]);
+                i = end;
+            }
+            '$' if i == 0 || !is_identifier_char(chars[i - 1]) => {
+                let end =
+                    dollar_tag(&chars, i).map_or(i + 1, |tag| skip_dollar_quoted(&chars, i, &tag));
+                current.extend(&chars[i..end]);
+                i = end;
+            }
+            ';' => {
+                finish(&mut current);
+                i += 1;
+            }
+            _ => {
+                current.push(c);
+                i += 1;
+            }
+        }
+    }
+    finish(&mut current);
+
+    statements
+}
+
+/// How many bind parameters a statement expects: the highest `n` of the `$n`
+/// placeholders outside strings, quoted identifiers and comments.
+pub fn parameter_count(statement: &str) -> usize {
+    let chars: Vec<char> = statement.chars().collect();
+    let mut count = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' | '"' => {
+                let backslash_escapes = c == '\''
+                    && i > 0
+                    && matches!(chars[i - 1], 'e' | 'E')
+                    && (i < 2 || !is_identifier_char(chars[i - 2]));
+                i = skip_quoted(&chars, i, c, backslash_escapes);
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                i = chars[i..]
+                    .iter()
+                    .position(|&c| c == '\n')
+                    .map_or(chars.len(), |p| i + p + 1)
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => i = skip_block_comment(&chars, i),
+            '$' if i == 0 || !is_identifier_char(chars[i - 1]) => {
+                let digits: String = chars[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit())
+                    .collect();
+                if digits.is_empty() {
+                    i = dollar_tag(&chars, i)
+                        .map_or(i + 1, |tag| skip_dollar_quoted(&chars, i, &tag));
+                } else {
+                    count = count.max(digits.parse().unwrap_or(0));
+                    i += 1 + digits.len();
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    count
+}
+
+/// Remove leading whitespace and `--`/`/* */` comments from a statement.
+pub fn strip_leading_comments(statement: &str) -> &str {
+    let mut rest = statement.trim_start();
+    loop {
+        if let Some(after) = rest.strip_prefix("--") {
+            rest = after
+                .find('\n')
+                .map_or("", |p| &after[p + 1..])
+                .trim_start();
+        } else if rest.starts_with("/*") {
+            let chars: Vec<char> = rest.chars().collect();
+            let end = skip_block_comment(&chars, 0);
+            let byte_end: usize = chars[..end].iter().map(|c| c.len_utf8()).sum();
+            rest = rest[byte_end..].trim_start();
+        } else {
+            return rest;
+        }
+    }
+}
+
+/// The first keyword of a statement in upper case, skipping leading comments and
+/// opening parentheses (e.g. `SELECT` for `/* report */ (select 1)`).
+pub fn leading_keyword(statement: &str) -> String {
+    strip_leading_comments(statement)
+        .trim_start_matches(|c: char| c == '(' || c.is_whitespace())
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect::<String>()
+        .to_ascii_uppercase()
+}
+
+/// Whether a statement is a `SELECT`, ignoring leading comments and case.
+pub fn is_select(statement: &str) -> bool {
+    leading_keyword(statement) == "SELECT"
+}
+
+/// Whether a statement produces a result set worth displaying, as opposed to a
+/// command (INSERT, UPDATE, DDL, ...) that only reports how many rows it touched.
+pub fn returns_rows(statement: &str) -> bool {
+    matches!(
+        leading_keyword(statement).as_str(),
+        "SELECT" | "WITH" | "VALUES" | "TABLE" | "SHOW" | "EXPLAIN"
+    )
+}
+
+/// Whether a statement is destructive enough to confirm before running: a `DROP`,
+/// `TRUNCATE` or `ALTER`, or a `DELETE` or `UPDATE` without a `WHERE` clause of its own.
+/// A `WHERE` in a subquery, string or comment doesn't count.
+pub fn is_destructive(statement: &str) -> bool {
+    match leading_keyword(statement).as_str() {
+        "DROP" | "TRUNCATE" | "ALTER" => true,
+        "DELETE" | "UPDATE" => !has_top_level_where(strip_leading_comments(statement)),
+        _ => false,
+    }
+}
+
+/// Whether a query can be used as a derived table (`SELECT * FROM (query) AS q`).
+///
+/// A `WITH` query can be, unless it changes data: a data-modifying statement
+/// (`WITH d AS (DELETE ... RETURNING *) SELECT ...`) is only allowed at the top level.
+pub fn is_wrappable(statement: &str) -> bool {
+    match leading_keyword(statement).as_str() {
+        "SELECT" | "VALUES" | "TABLE" => true,
+        "WITH" => !find_word(statement, |word, _| {
+            ["INSERT", "UPDATE", "DELETE", "MERGE"]
+                .iter()
+                .any(|keyword| word.eq_ignore_ascii_case(keyword))
+        }),
+        _ => false,
+    }
+}
+
+/// Fetch one page of a query's results. The query becomes a derived table, so its own
+/// `LIMIT`, `OFFSET` and `ORDER BY` are applied before paging.
+pub fn paged_query(query: &str, limit: i64, offset: i64) -> String {
+    format!(
+        "SELECT * FROM ({}\n) AS paged_query LIMIT {} OFFSET {}",
+        subquery_body(query),
+        limit,
+        offset
+    )
+}
+
+/// Like `paged_query`, but with every column cast to text, for a query run with bind
+/// parameters, whose results don't come back as text by themselves. The query's
+/// `columns` are renamed positionally so that duplicate or missing names don't matter.
+pub fn paged_text_query(query: &str, columns: usize, limit: i64, offset: i64) -> String {
+    if columns == 0 {
+        return paged_query(query, limit, offset);
+    }
+    let (casts, names) = text_columns(columns);
+    format!(
+        "SELECT {} FROM ({}\n) AS paged_query({}) LIMIT {} OFFSET {}",
+        casts,
+        subquery_body(query),
+        names,
+        limit,
+        offset
+    )
+}
+
+/// All of a query's rows with every column cast to text, renamed like `paged_text_query`
+/// does, so values come back as text whatever their type and NULL stays NULL.
+pub fn text_query(query: &str, columns: usize) -> String {
+    if columns == 0 {
+        return query.to_string();
+    }
+    let (casts, names) = text_columns(columns);
+    format!(
+        "SELECT {} FROM ({}\n) AS text_query({})",
+        casts,
+        subquery_body(query),
+        names
+    )
+}
+
+// The select list casting positional columns c1, c2, ... to text, and their names
+fn text_columns(columns: usize) -> (String, String) {
+    let names: Vec<String> = (1..=columns).map(|i| format!("c{}", i)).collect();
+    let casts: Vec<String> = names.iter().map(|name| format!("{}::text", name)).collect();
+    (casts.join(", "), names.join(", "))
+}
+
+/// Count the rows a query returns.
+///
+/// A trailing `ORDER BY` is dropped since it can't change the count and only makes the
+/// server sort. The query's own `LIMIT` is kept: pages are fetched with it applied, so
+/// the count must match.
+pub fn count_query(query: &str) -> String {
+    format!(
+        "SELECT COUNT(*) FROM ({}\n) AS count_query",
+        strip_trailing_order_by(subquery_body(query))
+    )
+}
+
+/// Count the rows a query returns, stopping once there are more than `max`: a result
+/// over `max` only says the query returns "many" rows, without running all of it.
+pub fn capped_count_query(query: &str, max: u64) -> String {
+    format!(
+        "SELECT COUNT(*) FROM (SELECT 1 FROM ({}\n) AS count_query LIMIT {}) AS capped_count",
+        strip_trailing_order_by(subquery_body(query)),
+        max.saturating_add(1)
+    )
+}
+
+// Remove a top-level ORDER BY that ends the query. One followed by LIMIT, OFFSET, FETCH
+// or a locking clause decides which rows those keep, so it stays.
+fn strip_trailing_order_by(query: &str) -> &str {
+    let chars: Vec<char> = query.chars().collect();
+    let mut depth = 0;
+    let mut order_by_at = None; // Char index of the last top-level ORDER BY
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' | '"' => i = skip_quoted(&chars, i, c, false),
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                i = chars[i..]
+                    .iter()
+                    .position(|&c| c == '\n')
+                    .map_or(chars.len(), |p| i + p + 1)
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => i = skip_block_comment(&chars, i),
+            '$' if i == 0 || !is_identifier_char(chars[i - 1]) => {
+                i = dollar_tag(&chars, i).map_or(i + 1, |tag| skip_dollar_quoted(&chars, i, &tag))
+            }
+            '(' => {
+                depth += 1;
+                i += 1;
+            }
+            ')' => {
+                depth -= 1;
+                i += 1;
+            }
+            c if is_identifier_char(c) => {
+                let start = i;
+                while i < chars.len() && is_identifier_char(chars[i]) {
+                    i += 1;
+                }
+                if depth != 0 {
+                    continue;
+                }
+                let word: String = chars[start..i].iter().collect::<String>().to_uppercase();
+                match word.as_str() {
+                    "ORDER" => {
+                        let rest: String = chars[i..].iter().collect();
+                        let rest = rest.trim_start();
+                        let follows_by = rest
+                            .get(..2)
+                            .is_some_and(|word| word.eq_ignore_ascii_case("by"))
+                            && !rest[2..].starts_with(is_identifier_char);
+                        if follows_by {
+                            order_by_at = Some(start);
+                        }
+                    }
+                    "LIMIT" | "OFFSET" | "FETCH" | "FOR" if order_by_at.is_some() => {
+                        return query;
+                    }
+                    _ => {}
+                }
+            }
+            _ => i += 1,
+        }
+    }
+
+    match order_by_at {
+        Some(at) => {
+            let byte_at: usize = chars[..at].iter().map(|c| c.len_utf8()).sum();
+            query[..byte_at].trim_end()
+        }
+        None => query,
+    }
+}
+
+// Whether `WHERE` appears outside parentheses, quotes and comments
+fn has_top_level_where(statement: &str) -> bool {
+    find_word(statement, |word, depth| {
+        depth == 0 && word.eq_ignore_ascii_case("where")
+    })
+}
+
+// Whether a word outside quotes and comments matches `found`, which is also given how
+// deeply the word is nested in parentheses
+fn find_word(statement: &str, found: impl Fn(&str, i32) -> bool) -> bool {
+    let chars: Vec<char> = statement.chars().collect();
+    let mut depth = 0;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            '\'' | '"' => {
+                let backslash_escapes = c == '\''
+                    && i > 0
+                    && matches!(chars[i - 1], 'e' | 'E')
+                    && (i < 2 || !is_identifier_char(chars[i - 2]));
+                i = skip_quoted(&chars, i, c, backslash_escapes);
+            }
+            '-' if chars.get(i + 1) == Some(&'-') => {
+                i = chars[i..]
+                    .iter()
+                    .position(|&c| c == '\n')
+                    .map_or(chars.len(), |p| i + p + 1)
+            }
+            '/' if chars.get(i + 1) == Some(&'*') => i = skip_block_comment(&chars, i),
+            '$' if i == 0 || !is_identifier_char(chars[i - 1]) => {
+                i = dollar_tag(&chars, i).map_or(i + 1, |tag| skip_dollar_quoted(&chars, i, &tag))
+            }
+            '(' => {
+                depth += 1;
+                i += 1;
+            }
+            ')' => {
+                depth -= 1;
+                i += 1;
+            }
+            c if is_identifier_char(c) => {
+                let start = i;
+                while i < chars.len() && is_identifier_char(chars[i]) {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if found(&word, depth) {
+                    return true;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    false
+}
+
+// A query ready to be placed in parentheses. Trailing semicolons are dropped, and callers
+// close the parenthesis on a new line so a trailing `--` comment can't swallow it.
+fn subquery_body(query: &str) -> &str {
+    query.trim().trim_end_matches(';').trim_end()
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+// Index just past the closing quote of a quoted section starting at `start`
+fn skip_quoted(chars: &[char], start: usize, quote: char, backslash_escapes: bool) -> usize {
+    let mut i = start + 1;
+    while i < chars.len() {
+        if backslash_escapes && chars[i] == '\\' {
+            i += 2;
+        } else if chars[i] == quote {
+            // A doubled quote is an escaped quote, not the end
+            if chars.get(i + 1) == Some(&quote) {
+                i += 2;
+            } else {
+                return i + 1;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    chars.len()
+}
+
+// Index just past a (possibly nested) block comment starting at `start`
+fn skip_block_comment(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        if chars[i] == '/' && chars.get(i + 1) == Some(&'*') {
+            depth += 1;
+            i += 2;
+        } else if chars[i] == '*' && chars.get(i + 1) == Some(&'/') {
+            depth -= 1;
+            i += 2;
+            if depth == 0 {
+                return i;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    chars.len()
+}
+
+// The `$tag$` opening a dollar-quoted string at `start`, if there is one
+fn dollar_tag(chars: &[char], start: usize) -> Option<String> {
+    let mut i = start + 1;
+    while i < chars.len() && chars[i] != '$' {
+        let valid = if i == start + 1 {
+            chars[i].is_alphabetic() || chars[i] == '_'
+        } else {
+            is_identifier_char(chars[i])
+        };
+        if !valid {
+            return None;
+        }
+        i += 1;
+    }
+    (i < chars.len()).then(|| chars[start..=i].iter().collect())
+}
+
+// Index just past the closing tag of a dollar-quoted string starting at `start`
+fn skip_dollar_quoted(chars: &[char], start: usize, tag: &str) -> usize {
+    let tag: Vec<char> = tag.chars().collect();
+    let mut i = start + tag.len();
+    while i + tag.len() <= chars.len() {
+        if chars[i..i + tag.len()] == tag[..] {
+            return i + tag.len();
+        }
+        i += 1;
+    }
+    chars.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quote_identifier() {
+        assert_eq!(quote_identifier("users"), "\"users\"");
+        assert_eq!(quote_identifier("we\"ird"), "\"we\"\"ird\"");
+    }
+
+    #[test]
+    fn test_quote_literal() {
+        assert_eq!(quote_literal("O'Brien"), "'O''Brien'");
+    }
+
+    #[test]
+    fn test_is_numeric_literal() {
+        for value in ["0", "-12", "+3", "3.5", ".5", "5.", "1e10", "-2.5E-3"] {
+            assert!(is_numeric_literal(value), "{}", value);
+        }
+        for value in [
+            "",
+            "-",
+            ".",
+            "NaN",
+            "inf",
+            "-Infinity",
+            "1e",
+            "1e+",
+            "1.2.3",
+            "0x1F",
+            "1 2",
+            "e5",
+        ] {
+            assert!(!is_numeric_literal(value), "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_insert_statement() {
+        let columns = vec![
+            ("id".to_string(), "integer".to_string()),
+            ("name".to_string(), "character varying(50)".to_string()),
+            ("score".to_string(), "numeric".to_string()),
+            ("note".to_string(), "text".to_string()),
+        ];
+        let values = vec![
+            "42".to_string(),
+            "O'Brien".to_string(),
+            "3.5".to_string(),
+            "NULL".to_string(),
+        ];
+        assert_eq!(
+            insert_statement("people", &columns, &values),
+            "INSERT INTO \"people\" (\"id\", \"name\", \"score\", \"note\") VALUES (42, 'O''Brien', 3.5, NULL);"
+        );
+
+        // Special numbers are quoted, and a value reading NULL is taken as NULL
+        let values = vec![
+            "-7".to_string(),
+            "NULL".to_string(),
+            "NaN".to_string(),
+            "NULL".to_string(),
+        ];
+        assert_eq!(
+            insert_statement("people", &columns, &values),
+            "INSERT INTO \"people\" (\"id\", \"name\", \"score\", \"note\") VALUES (-7, NULL, 'NaN', NULL);"
+        );
+        assert_eq!(
+            parameterized_insert("people", &["name".to_string(), "score".to_string()]),
+            "INSERT INTO \"people\" (\"name\", \"score\") VALUES ($1, $2)"
+        );
+        assert_eq!(
+            parameterized_insert("people", &[]),
+            "INSERT INTO \"people\" DEFAULT VALUES"
+        );
+        assert_eq!(
+            parameterized_update("people", "note", "id"),
+            "UPDATE \"people\" SET \"note\" = $1 WHERE \"id\" = $2"
+        );
+        assert_eq!(
+            cell_query("people", "note", "id"),
+            "SELECT \"note\" FROM \"people\" WHERE \"id\" = $1"
+        );
+    }
+
+    #[test]
+    fn test_maintenance_statement() {
+        assert_eq!(
+            maintenance_statement("people", Maintenance::VacuumAnalyze, false),
+            Ok("VACUUM (ANALYZE) \"people\"".to_string())
+        );
+        assert_eq!(
+            maintenance_statement("we\"ird", Maintenance::Analyze, false),
+            Ok("ANALYZE \"we\"\"ird\"".to_string())
+        );
+        // ANALYZE may run inside a transaction, VACUUM may not
+        assert_eq!(
+            maintenance_statement("people", Maintenance::Analyze, true),
+            Ok("ANALYZE \"people\"".to_string())
+        );
+        assert!(maintenance_statement("people", Maintenance::VacuumAnalyze, true).is_err());
+    }
+
+    #[test]
+    fn test_split_simple_statements() {
+        assert_eq!(
+            split_statements("SELECT 1; SELECT 2;\n  SELECT 3"),
+            vec!["SELECT 1", "SELECT 2", "SELECT 3"]
+        );
+        assert!(split_statements("  ;; ").is_empty());
+    }
+
+    #[test]
+    fn test_split_respects_quotes_and_comments() {
+        let script = "INSERT INTO t VALUES ('a;b', 'it''s;'); -- trailing; comment\n\
+                      SELECT \"odd;name\" FROM t /* block; /* nested; */ still */;\n\
+                      SELECT E'back\\'slash;'";
+        let statements = split_statements(script);
+        assert_eq!(statements.len(), 3);
+        assert_eq!(statements[0], "INSERT INTO t VALUES ('a;b', 'it''s;')");
+        assert!(statements[1].starts_with("-- trailing; comment"));
+        assert!(statements[1].ends_with("still */"));
+        assert_eq!(statements[2], "SELECT E'back\\'slash;'");
+    }
+
+    #[test]
+    fn test_split_dollar_quoting() {
+        let script = "CREATE FUNCTION f() RETURNS int AS $body$ SELECT 1; $body$ LANGUAGE sql;\n\
+                      DO $$ BEGIN PERFORM 1; END $$; SELECT $1";
+        let statements = split_statements(script);
+        assert_eq!(statements.len(), 3);
+        assert!(statements[0].ends_with("LANGUAGE sql"));
+        assert_eq!(statements[1], "DO $$ BEGIN PERFORM 1; END $$");
+        assert_eq!(statements[2], "SELECT $1");
+    }
+
+    #[test]
+    fn test_comment_only_statements_are_dropped() {
+        assert_eq!(
+            split_statements("SELECT 1; -- done\n/* really */"),
+            vec!["SELECT 1"]
+        );
+    }
+
+    #[test]
+    fn test_strip_leading_comments() {
+        assert_eq!(
+            strip_leading_comments("  -- hi\n /* there */ SELECT 1"),
+            "SELECT 1"
+        );
+        assert_eq!(strip_leading_comments("-- only a comment"), "");
+    }
+
+    #[test]
+    fn test_is_select() {
+        assert!(is_select("select 1"));
+        assert!(is_select("/* report */\n  SELECT * FROM t"));
+        assert!(!is_select("UPDATE t SET a = 1"));
+        assert!(!is_select("sel"));
+        assert!(!is_select("selection"));
+    }
+
+    #[test]
+    fn test_leading_keyword() {
+        assert_eq!(leading_keyword("  -- note\n update t set a = 1"), "UPDATE");
+        assert_eq!(leading_keyword("(select 1) union (select 2)"), "SELECT");
+        assert_eq!(leading_keyword("-- nothing"), "");
+        assert!(returns_rows("with x as (select 1) select * from x"));
+        assert!(returns_rows("EXPLAIN SELECT 1"));
+        assert!(!returns_rows("DELETE FROM t"));
+        assert!(!returns_rows("/* select */ insert into t values (1)"));
+    }
+
+    #[test]
+    fn test_paged_query_keeps_user_limit() {
+        assert_eq!(
+            paged_query("SELECT * FROM users LIMIT 5", 20, 40),
+            "SELECT * FROM (SELECT * FROM users LIMIT 5\n) AS paged_query LIMIT 20 OFFSET 40"
+        );
+    }
+
+    #[test]
+    fn test_paged_query_keeps_order_by() {
+        assert_eq!(
+            paged_query("SELECT name FROM users ORDER BY name DESC;", 20, 0),
+            "SELECT * FROM (SELECT name FROM users ORDER BY name DESC\n) AS paged_query LIMIT 20 OFFSET 0"
+        );
+    }
+
+    #[test]
+    fn test_wrapped_queries_with_trailing_comment() {
+        let query = "SELECT * FROM users -- just the users";
+        let paged = paged_query(query, 20, 0);
+        assert!(paged.contains("-- just the users\n) AS paged_query LIMIT 20"));
+        assert_eq!(
+            count_query(query),
+            "SELECT COUNT(*) FROM (SELECT * FROM users -- just the users\n) AS count_query"
+        );
+    }
+
+    #[test]
+    fn test_paged_text_query_renames_and_casts_columns() {
+        assert_eq!(
+            paged_text_query("SELECT id, id FROM users WHERE id > $1", 2, 20, 0),
+            "SELECT c1::text, c2::text FROM (SELECT id, id FROM users WHERE id > $1\n) AS paged_query(c1, c2) LIMIT 20 OFFSET 0"
+        );
+    }
+
+    #[test]
+    fn test_parameter_count() {
+        assert_eq!(parameter_count("SELECT * FROM users"), 0);
+        assert_eq!(
+            parameter_count("SELECT * FROM users WHERE id = $2 OR name = $1"),
+            2
+        );
+        // Placeholders in strings, identifiers, comments and dollar quotes don't count
+        assert_eq!(
+            parameter_count("SELECT '$1', \"$2\" -- $3\n/* $4 */ FROM t WHERE a = $$ $5 $$"),
+            0
+        );
+        assert_eq!(parameter_count("SELECT $tag$ $9 $tag$, $1"), 1);
+        assert_eq!(parameter_count("SELECT E'it\\'s $2', $1"), 1);
+    }
+
+    #[test]
+    fn test_is_wrappable() {
+        assert!(is_wrappable("select 1"));
+        assert!(is_wrappable("VALUES (1), (2)"));
+        assert!(!is_wrappable(
+            "WITH d AS (DELETE FROM t RETURNING *) SELECT * FROM d"
+        ));
+        assert!(!is_wrappable(
+            "with n as (select 1) insert into t select * from n"
+        ));
+        assert!(!is_wrappable("EXPLAIN SELECT 1"));
+
+        // Read-only CTEs can be paged like any SELECT, whatever their strings and
+        // comments say
+        assert!(is_wrappable(
+            "WITH recent AS (SELECT * FROM events WHERE kind <> 'delete') SELECT * FROM recent"
+        ));
+        assert!(is_wrappable(
+            "WITH x AS (SELECT 1 AS \"update\") -- no insert here\nSELECT * FROM x"
+        ));
+    }
+
+    #[test]
+    fn test_is_destructive() {
+        assert!(is_destructive("DELETE FROM users"));
+        assert!(is_destructive("update users set active = false;"));
+        assert!(is_destructive(
+            "-- cleanup\n/* all of it */ DELETE FROM logs"
+        ));
+        assert!(is_destructive("DROP TABLE users"));
+        assert!(is_destructive("truncate logs"));
+        assert!(is_destructive("ALTER TABLE users ADD COLUMN x int"));
+        assert!(!is_destructive("DELETE FROM users WHERE id = 1"));
+        assert!(!is_destructive(
+            "UPDATE users SET a = 1\nwhere id IN (SELECT 1)"
+        ));
+        assert!(!is_destructive("SELECT * FROM users"));
+        assert!(!is_destructive("INSERT INTO logs VALUES ('where')"));
+        // A WHERE that isn't the statement's own doesn't limit it
+        assert!(is_destructive(
+            "UPDATE users SET a = (SELECT b FROM t WHERE t.id = 1)"
+        ));
+        assert!(is_destructive("DELETE FROM users -- WHERE id = 1"));
+        assert!(is_destructive("UPDATE t SET note = 'where' RETURNING *"));
+        assert!(is_destructive("UPDATE t SET note = $$ where $$"));
+        assert!(is_destructive("UPDATE \"where\" SET a = 1"));
+        assert!(is_destructive("UPDATE t SET elsewhere = 1"));
+    }
+
+    #[test]
+    fn test_count_query_drops_trailing_order_by() {
+        assert_eq!(
+            count_query("SELECT * FROM users ORDER BY name DESC;"),
+            "SELECT COUNT(*) FROM (SELECT * FROM users\n) AS count_query"
+        );
+        // ORDER BY inside a subquery, string or comment is not the query's own
+        assert_eq!(
+            count_query("SELECT * FROM (SELECT * FROM t ORDER BY a) s WHERE b = 'order by x'"),
+            "SELECT COUNT(*) FROM (SELECT * FROM (SELECT * FROM t ORDER BY a) s WHERE b = 'order by x'\n) AS count_query"
+        );
+        assert_eq!(
+            count_query("SELECT 'é' AS ordered FROM t ORDER BY é"),
+            "SELECT COUNT(*) FROM (SELECT 'é' AS ordered FROM t\n) AS count_query"
+        );
+        assert_eq!(
+            count_query("SELECT a AS border FROM t -- order by a"),
+            "SELECT COUNT(*) FROM (SELECT a AS border FROM t -- order by a\n) AS count_query"
+        );
+    }
+
+    #[test]
+    fn test_capped_count_query() {
+        assert_eq!(
+            capped_count_query("SELECT * FROM events ORDER BY at;", 1000),
+            "SELECT COUNT(*) FROM (SELECT 1 FROM (SELECT * FROM events\n) AS count_query LIMIT 1001) AS capped_count"
+        );
+    }
+
+    #[test]
+    fn test_count_query_keeps_limit() {
+        assert_eq!(
+            count_query("SELECT * FROM users ORDER BY id LIMIT 5"),
+            "SELECT COUNT(*) FROM (SELECT * FROM users ORDER BY id LIMIT 5\n) AS count_query"
+        );
+    }
+}
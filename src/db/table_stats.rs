@@ -0,0 +1,189 @@
+//! Per-table activity from `pg_stat_user_tables`, as shown by the table stats view.
+//!
+//! The counters run from the last time the server's statistics were reset, and are
+//! missing altogether for a table nothing has touched since, so every number is optional
+//! and sorts after the others.
+
+use std::cmp::Ordering;
+
+/// What `pg_stat_user_tables` counts for one table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableStats {
+    pub schema: String,
+    pub name: String,
+    pub seq_scan: Option<i64>,
+    pub idx_scan: Option<i64>, // None when the table has no indexes
+    pub inserted: Option<i64>,
+    pub updated: Option<i64>,
+    pub deleted: Option<i64>,
+    pub live: Option<i64>,
+    pub dead: Option<i64>,
+    pub last_autovacuum: Option<String>, // None if autovacuum hasn't run on it
+}
+
+/// A column of the table stats view to sort by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum StatsColumn {
+    Name,
+    SeqScan,
+    IdxScan,
+    Inserted,
+    Updated,
+    Deleted,
+    Live,
+    Dead,
+    LastAutovacuum,
+}
+
+impl StatsColumn {
+    pub const ALL: [StatsColumn; 9] = [
+        StatsColumn::Name,
+        StatsColumn::SeqScan,
+        StatsColumn::IdxScan,
+        StatsColumn::Inserted,
+        StatsColumn::Updated,
+        StatsColumn::Deleted,
+        StatsColumn::Live,
+        StatsColumn::Dead,
+        StatsColumn::LastAutovacuum,
+    ];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            StatsColumn::Name => "Table",
+            StatsColumn::SeqScan => "Seq scans",
+            StatsColumn::IdxScan => "Index scans",
+            StatsColumn::Inserted => "Inserted",
+            StatsColumn::Updated => "Updated",
+            StatsColumn::Deleted => "Deleted",
+            StatsColumn::Live => "Live rows",
+            StatsColumn::Dead => "Dead rows",
+            StatsColumn::LastAutovacuum => "Last autovacuum",
+        }
+    }
+
+    /// The column `step` places to the right, wrapping around.
+    pub fn step(self, step: isize) -> StatsColumn {
+        let len = Self::ALL.len() as isize;
+        let index = Self::ALL.iter().position(|c| *c == self).unwrap() as isize;
+        Self::ALL[(index + step).rem_euclid(len) as usize]
+    }
+
+    fn count(self, stats: &TableStats) -> Option<i64> {
+        match self {
+            StatsColumn::SeqScan => stats.seq_scan,
+            StatsColumn::IdxScan => stats.idx_scan,
+            StatsColumn::Inserted => stats.inserted,
+            StatsColumn::Updated => stats.updated,
+            StatsColumn::Deleted => stats.deleted,
+            StatsColumn::Live => stats.live,
+            StatsColumn::Dead => stats.dead,
+            StatsColumn::Name | StatsColumn::LastAutovacuum => None,
+        }
+    }
+}
+
+impl TableStats {
+    /// The table's name, qualified by its schema unless that's `public`.
+    pub fn qualified_name(&self) -> String {
+        if self.schema == "public" {
+            self.name.clone()
+        } else {
+            format!("{}.{}", self.schema, self.name)
+        }
+    }
+
+    /// The cells of its row in the view, in the order of `StatsColumn::ALL`. Missing
+    /// numbers are left blank and a table never autovacuumed says so.
+    pub fn cells(&self) -> Vec<String> {
+        StatsColumn::ALL
+            .iter()
+            .map(|column| match column {
+                StatsColumn::Name => self.qualified_name(),
+                StatsColumn::LastAutovacuum => {
+                    self.last_autovacuum.clone().unwrap_or("never".to_string())
+                }
+                column => column
+                    .count(self)
+                    .map(|n| n.to_string())
+                    .unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+/// Sort by `column`, names ascending and the rest descending (busiest and most recently
+/// vacuumed first) unless `reversed`. Tables missing the value come last either way.
+pub fn sort(stats: &mut [TableStats], column: StatsColumn, reversed: bool) {
+    let descending = (column != StatsColumn::Name) != reversed;
+    stats.sort_by(|a, b| {
+        let ordering = match column {
+            StatsColumn::Name => compare(
+                Some((&a.schema, &a.name)),
+                Some((&b.schema, &b.name)),
+                descending,
+            ),
+            StatsColumn::LastAutovacuum => compare(
+                a.last_autovacuum.as_ref(),
+                b.last_autovacuum.as_ref(),
+                descending,
+            ),
+            column => compare(column.count(a), column.count(b), descending),
+        };
+        ordering.then_with(|| (&a.schema, &a.name).cmp(&(&b.schema, &b.name)))
+    });
+}
+
+// None after every value, whichever way the values go
+fn compare<T: Ord>(a: Option<T>, b: Option<T>, descending: bool) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) if descending => b.cmp(&a),
+        (Some(a), Some(b)) => a.cmp(&b),
+        (a, b) => b.is_some().cmp(&a.is_some()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(name: &str, seq_scan: Option<i64>, last_autovacuum: Option<&str>) -> TableStats {
+        TableStats {
+            schema: "public".to_string(),
+            name: name.to_string(),
+            seq_scan,
+            idx_scan: None,
+            inserted: Some(0),
+            updated: Some(0),
+            deleted: Some(0),
+            live: Some(0),
+            dead: Some(0),
+            last_autovacuum: last_autovacuum.map(String::from),
+        }
+    }
+
+    fn names(stats: &[TableStats]) -> Vec<&str> {
+        stats.iter().map(|s| s.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_sort_keeps_missing_values_last() {
+        let mut all = vec![
+            stats("orders", Some(3), Some("2026-10-01 04:00")),
+            stats("audit", None, None),
+            stats("users", Some(40), Some("2026-10-12 09:30")),
+        ];
+        sort(&mut all, StatsColumn::SeqScan, false);
+        assert_eq!(names(&all), ["users", "orders", "audit"]);
+        sort(&mut all, StatsColumn::SeqScan, true);
+        assert_eq!(names(&all), ["orders", "users", "audit"]);
+        sort(&mut all, StatsColumn::LastAutovacuum, false);
+        assert_eq!(names(&all), ["users", "orders", "audit"]);
+        sort(&mut all, StatsColumn::Name, false);
+        assert_eq!(names(&all), ["audit", "orders", "users"]);
+
+        assert_eq!(all[0].cells()[2], "");
+        assert_eq!(all[0].cells()[8], "never");
+        assert_eq!(StatsColumn::Name.step(-1), StatsColumn::LastAutovacuum);
+    }
+}
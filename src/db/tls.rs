@@ -0,0 +1,297 @@
+//! TLS for connections, set up from libpq's `sslmode`, `sslrootcert`, `sslcert` and
+//! `sslkey` options.
+//!
+//! As with libpq, `prefer` and `require` encrypt without checking who the server is,
+//! `verify-ca` checks its certificate against the root certificates and `verify-full` also
+//! checks that the certificate was issued for the host connected to. Without `sslrootcert`
+//! the system's root certificates are used.
+
+use super::{DbError, Result};
+use crate::config::ConnectionOptions;
+use rustls::client::WebPkiServerVerifier;
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::{CryptoProvider, verify_tls12_signature, verify_tls13_signature};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rustls::{
+    CertificateError, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+};
+use std::path::Path;
+use std::sync::Arc;
+use tokio_postgres_rustls::MakeRustlsConnect;
+
+// How much of the server's certificate to check
+#[derive(Clone, Copy, PartialEq)]
+enum Verify {
+    Nothing,
+    Ca,
+    Full,
+}
+
+/// The TLS connector for a connection's options, or None if `sslmode` is `disable`.
+///
+/// Certificate and key files are read here, so a missing or unreadable one is reported
+/// by name rather than as a failed handshake.
+pub fn connector(options: &ConnectionOptions) -> Result<Option<MakeRustlsConnect>> {
+    let verify = match options.sslmode.as_deref() {
+        Some("disable") => return Ok(None),
+        Some("verify-ca") => Verify::Ca,
+        Some("verify-full") => Verify::Full,
+        // libpq checks the chain for `require` too when given a root certificate
+        Some("require") if options.sslrootcert.is_some() => Verify::Ca,
+        _ => Verify::Nothing,
+    };
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| DbError::Connect(format!("Failed to set up TLS: {}", e)))?;
+    let builder = match verify {
+        Verify::Nothing => builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider))),
+        Verify::Ca | Verify::Full => {
+            let roots = root_certificates(options.sslrootcert.as_deref())?;
+            let verifier = WebPkiServerVerifier::builder_with_provider(Arc::new(roots), provider)
+                .build()
+                .map_err(|e| DbError::Connect(format!("Failed to set up TLS: {}", e)))?;
+            if verify == Verify::Full {
+                builder.with_webpki_verifier(verifier)
+            } else {
+                builder
+                    .dangerous()
+                    .with_custom_certificate_verifier(Arc::new(IgnoreHostname(verifier)))
+            }
+        }
+    };
+
+    let config = match (&options.sslcert, &options.sslkey) {
+        (Some(cert), Some(key)) => builder
+            .with_client_auth_cert(
+                read_certificates(cert, "client certificate")?,
+                read_private_key(key)?,
+            )
+            .map_err(|e| {
+                DbError::Connect(format!(
+                    "Client certificate {} doesn't go with key {}: {}",
+                    cert.display(),
+                    key.display(),
+                    e
+                ))
+            })?,
+        (None, None) => builder.with_no_client_auth(),
+        _ => {
+            return Err(DbError::Connect(
+                "A client certificate needs both sslcert and sslkey".to_string(),
+            ));
+        }
+    };
+    Ok(Some(MakeRustlsConnect::new(config)))
+}
+
+// The certificates in `sslrootcert`, or the system's if it isn't given
+fn root_certificates(sslrootcert: Option<&Path>) -> Result<RootCertStore> {
+    let mut roots = RootCertStore::empty();
+    match sslrootcert {
+        Some(path) => {
+            for certificate in read_certificates(path, "SSL root certificate")? {
+                roots.add(certificate).map_err(|e| {
+                    DbError::Connect(format!(
+                        "Invalid SSL root certificate {}: {}",
+                        path.display(),
+                        e
+                    ))
+                })?;
+            }
+        }
+        None => {
+            roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+            if roots.is_empty() {
+                return Err(DbError::Connect(
+                    "No system root certificates found to verify the server with; give one with sslrootcert"
+                        .to_string(),
+                ));
+            }
+        }
+    }
+    Ok(roots)
+}
+
+fn read_file(path: &Path, what: &str) -> Result<Vec<u8>> {
+    std::fs::read(path)
+        .map_err(|e| DbError::Connect(format!("Cannot read {} {}: {}", what, path.display(), e)))
+}
+
+fn read_certificates(path: &Path, what: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let certificates = CertificateDer::pem_slice_iter(&read_file(path, what)?)
+        .collect::<std::result::Result<Vec<_>, _>>()
+        .map_err(|e| DbError::Connect(format!("Invalid {} {}: {}", what, path.display(), e)))?;
+    if certificates.is_empty() {
+        return Err(DbError::Connect(format!(
+            "No PEM certificates found in {} {}",
+            what,
+            path.display()
+        )));
+    }
+    Ok(certificates)
+}
+
+fn read_private_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    PrivateKeyDer::from_pem_slice(&read_file(path, "client key")?).map_err(|e| {
+        DbError::Connect(format!(
+            "No PEM private key found in client key {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+// Encrypts without checking the server's certificate, for `prefer` and `require`
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+// Checks the certificate chain but not the host name it was issued for, for `verify-ca`
+#[derive(Debug)]
+struct IgnoreHostname(Arc<WebPkiServerVerifier>);
+
+impl ServerCertVerifier for IgnoreHostname {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, rustls::Error> {
+        match self
+            .0
+            .verify_server_cert(end_entity, intermediates, server_name, ocsp_response, now)
+        {
+            Err(rustls::Error::InvalidCertificate(
+                CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. },
+            )) => Ok(ServerCertVerified::assertion()),
+            result => result,
+        }
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, rustls::Error> {
+        self.0.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn connector_error(options: ConnectionOptions) -> String {
+        match connector(&options) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_disable_has_no_connector() {
+        let options = ConnectionOptions {
+            sslmode: Some("disable".to_string()),
+            ..Default::default()
+        };
+        assert!(connector(&options).unwrap().is_none());
+        assert!(connector(&ConnectionOptions::default()).unwrap().is_some());
+    }
+
+    #[test]
+    fn test_missing_files_are_named() {
+        let error = connector_error(ConnectionOptions {
+            sslmode: Some("verify-full".to_string()),
+            sslrootcert: Some(PathBuf::from("/nonexistent/root.crt")),
+            ..Default::default()
+        });
+        assert!(
+            error.starts_with("Cannot read SSL root certificate /nonexistent/root.crt:"),
+            "{}",
+            error
+        );
+
+        let error = connector_error(ConnectionOptions {
+            sslcert: Some(PathBuf::from("/nonexistent/client.crt")),
+            sslkey: Some(PathBuf::from("/nonexistent/client.key")),
+            ..Default::default()
+        });
+        assert!(
+            error.starts_with("Cannot read client certificate /nonexistent/client.crt:"),
+            "{}",
+            error
+        );
+
+        let error = connector_error(ConnectionOptions {
+            sslcert: Some(PathBuf::from("/nonexistent/client.crt")),
+            ..Default::default()
+        });
+        assert_eq!(error, "A client certificate needs both sslcert and sslkey");
+    }
+}
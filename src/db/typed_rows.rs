@@ -0,0 +1,106 @@
+//! Rows fetched for exporting, with what each column's type makes of its values.
+//!
+//! Pages of rows are read as text with NULL written as "NULL", which is all the grid
+//! needs but loses what the values were. Exports that can tell a number or a NULL apart
+//! from a string fetch the rows again as `TypedRows` instead, or stream them as
+//! `ExportRows`.
+
+use super::{DbError, LossyText, Result};
+use futures_util::StreamExt;
+use std::pin::Pin;
+use tokio_postgres::types::Type;
+use tokio_postgres::{RowStream, SimpleQueryMessage, SimpleQueryStream};
+
+/// How a column's values are written where the output has types of its own, as in JSON.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ValueKind {
+    Number,
+    Boolean,
+    Json,
+    Text,
+}
+
+impl ValueKind {
+    pub fn of(ty: &Type) -> ValueKind {
+        match *ty {
+            Type::INT2
+            | Type::INT4
+            | Type::INT8
+            | Type::OID
+            | Type::FLOAT4
+            | Type::FLOAT8
+            | Type::NUMERIC => ValueKind::Number,
+            Type::BOOL => ValueKind::Boolean,
+            Type::JSON | Type::JSONB => ValueKind::Json,
+            _ => ValueKind::Text,
+        }
+    }
+}
+
+/// Rows with their columns' names and kinds. Values are the server's text for them, and
+/// None is NULL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypedRows {
+    pub columns: Vec<(String, ValueKind)>,
+    pub rows: Vec<Vec<Option<String>>>,
+}
+
+/// A statement's rows as the server sends them, so that an export can stop partway, or
+/// write a result bigger than it could hold twice, without running the statement again.
+pub struct ExportRows {
+    pub columns: Vec<(String, ValueKind)>,
+    pub(super) rows: ExportStream,
+}
+
+pub(super) enum ExportStream {
+    /// A wrappable query, read as text through `text_query`
+    Typed(Pin<Box<RowStream>>),
+    /// Any other statement, whose values the simple query protocol returns as text
+    Simple(Pin<Box<SimpleQueryStream>>),
+}
+
+impl ExportRows {
+    /// The next row, or None once the statement has finished.
+    pub async fn next_row(&mut self) -> Result<Option<Vec<Option<String>>>> {
+        let failed =
+            |e: tokio_postgres::Error| DbError::query("Failed to fetch rows to export", &e);
+        match &mut self.rows {
+            ExportStream::Typed(stream) => match stream.next().await {
+                Some(row) => {
+                    let row = row.map_err(failed)?;
+                    Ok(Some(
+                        (0..row.len())
+                            .map(|i| row.get::<_, Option<LossyText>>(i).map(|text| text.0))
+                            .collect(),
+                    ))
+                }
+                None => Ok(None),
+            },
+            ExportStream::Simple(stream) => {
+                while let Some(message) = stream.next().await {
+                    if let SimpleQueryMessage::Row(row) = message.map_err(failed)? {
+                        return Ok(Some(
+                            (0..row.len())
+                                .map(|i| row.get(i).map(str::to_string))
+                                .collect(),
+                        ));
+                    }
+                }
+                Ok(None)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_kind() {
+        assert_eq!(ValueKind::of(&Type::NUMERIC), ValueKind::Number);
+        assert_eq!(ValueKind::of(&Type::BOOL), ValueKind::Boolean);
+        assert_eq!(ValueKind::of(&Type::JSONB), ValueKind::Json);
+        assert_eq!(ValueKind::of(&Type::TIMESTAMPTZ), ValueKind::Text);
+    }
+}
@@ -0,0 +1,171 @@
+//! Comparing a table's rows between two connections, e.g. staging and production.
+//!
+//! Rows are matched by their primary key value. Columns are matched by name and type as
+//! the table view shows them (`email (text)`), so a column whose type differs between the
+//! two databases is reported as missing from each side rather than compared.
+
+use std::collections::{HashMap, HashSet};
+
+/// The rows of one side of a comparison, with the index of the key column.
+pub struct TableRows {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub key_index: usize,
+}
+
+/// A row present on both sides whose values differ in some columns.
+#[derive(Debug, PartialEq)]
+pub struct ChangedRow {
+    pub key: String,
+    pub cells: Vec<(String, String, String)>, // Column, value on the left, value on the right
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct TableDiff {
+    pub columns_only_in_left: Vec<String>,
+    pub columns_only_in_right: Vec<String>,
+    pub rows_only_in_left: Vec<String>, // Keys, in the left side's order
+    pub rows_only_in_right: Vec<String>, // Keys, in the right side's order
+    pub changed: Vec<ChangedRow>,
+    pub identical: usize,
+}
+
+impl TableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.columns_only_in_left.is_empty()
+            && self.columns_only_in_right.is_empty()
+            && self.rows_only_in_left.is_empty()
+            && self.rows_only_in_right.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+/// Compare two sides' rows by key, looking only at the columns both have.
+pub fn diff_rows(left: &TableRows, right: &TableRows) -> TableDiff {
+    let right_columns: HashMap<&str, usize> = right
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, c)| (c.as_str(), i))
+        .collect();
+    let left_columns: HashSet<&str> = left.columns.iter().map(String::as_str).collect();
+    // Column indices on each side of the columns they share
+    let shared: Vec<(usize, usize)> = left
+        .columns
+        .iter()
+        .enumerate()
+        .filter_map(|(i, c)| Some((i, *right_columns.get(c.as_str())?)))
+        .collect();
+
+    let mut diff = TableDiff {
+        columns_only_in_left: left
+            .columns
+            .iter()
+            .filter(|c| !right_columns.contains_key(c.as_str()))
+            .cloned()
+            .collect(),
+        columns_only_in_right: right
+            .columns
+            .iter()
+            .filter(|c| !left_columns.contains(c.as_str()))
+            .cloned()
+            .collect(),
+        ..TableDiff::default()
+    };
+
+    let right_rows: HashMap<&str, &Vec<String>> = right
+        .rows
+        .iter()
+        .map(|row| (row[right.key_index].as_str(), row))
+        .collect();
+    let mut left_keys = HashSet::new();
+    for row in &left.rows {
+        let key = row[left.key_index].as_str();
+        left_keys.insert(key);
+        let Some(other) = right_rows.get(key) else {
+            diff.rows_only_in_left.push(key.to_string());
+            continue;
+        };
+        let cells: Vec<(String, String, String)> = shared
+            .iter()
+            .filter(|&&(l, r)| row[l] != other[r])
+            .map(|&(l, r)| (left.columns[l].clone(), row[l].clone(), other[r].clone()))
+            .collect();
+        if cells.is_empty() {
+            diff.identical += 1;
+        } else {
+            diff.changed.push(ChangedRow {
+                key: key.to_string(),
+                cells,
+            });
+        }
+    }
+    diff.rows_only_in_right = right
+        .rows
+        .iter()
+        .map(|row| &row[right.key_index])
+        .filter(|key| !left_keys.contains(key.as_str()))
+        .cloned()
+        .collect();
+    diff
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn side(columns: &[&str], rows: &[&[&str]]) -> TableRows {
+        TableRows {
+            columns: columns.iter().map(|c| c.to_string()).collect(),
+            rows: rows
+                .iter()
+                .map(|row| row.iter().map(|v| v.to_string()).collect())
+                .collect(),
+            key_index: 0,
+        }
+    }
+
+    #[test]
+    fn test_diff_rows() {
+        let left = side(
+            &["id (integer)", "email (text)", "nickname (text)"],
+            &[&["1", "a@x", "a"], &["2", "b@x", "b"], &["3", "c@x", "c"]],
+        );
+        let right = side(
+            &["id (integer)", "email (text)", "age (integer)"],
+            &[
+                &["4", "d@x", "40"],
+                &["2", "b@y", "20"],
+                &["1", "a@x", "10"],
+            ],
+        );
+        let diff = diff_rows(&left, &right);
+        assert_eq!(diff.columns_only_in_left, ["nickname (text)"]);
+        assert_eq!(diff.columns_only_in_right, ["age (integer)"]);
+        assert_eq!(diff.rows_only_in_left, ["3"]);
+        assert_eq!(diff.rows_only_in_right, ["4"]);
+        assert_eq!(
+            diff.changed,
+            [ChangedRow {
+                key: "2".to_string(),
+                cells: vec![(
+                    "email (text)".to_string(),
+                    "b@x".to_string(),
+                    "b@y".to_string()
+                )],
+            }]
+        );
+        assert_eq!(diff.identical, 1);
+        assert!(!diff.is_empty());
+    }
+
+    #[test]
+    fn test_identical_tables() {
+        let rows: &[&[&str]] = &[&["1", "NULL"], &["2", "x"]];
+        let left = side(&["id (integer)", "note (text)"], rows);
+        let right = side(&["id (integer)", "note (text)"], rows);
+        let diff = diff_rows(&left, &right);
+        assert!(diff.is_empty());
+        assert_eq!(diff.identical, 2);
+    }
+}
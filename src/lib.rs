@@ -23,10 +23,12 @@
 //!
 //! - `config`: Handles connection storage and retrieval
 //! - `db`: PostgreSQL connection and query functions
+//! - `diff`: Comparing a table's rows between two connections
 //! - `tui`: TUI rendering and interaction logic
 
 pub mod config;
 pub mod db;
+pub mod diff;
 pub mod tui;
 
 pub use config::Config;
@@ -0,0 +1,114 @@
+//! Element-aware display of array columns.
+//!
+//! Arrays arrive in the server's text form, e.g. `{1,2,NULL}` or `{"a,b",c}`. Only the
+//! outermost level is split into elements; the rows of a multi-dimensional array stay
+//! in their text form.
+
+/// Whether a column type as reported by the columns query is an array, e.g. `int4[]`.
+pub fn is_array_type(data_type: &str) -> bool {
+    data_type.ends_with("[]")
+}
+
+/// The elements of an array's text form, with quotes and escapes removed, or `None` if
+/// the value isn't an array.
+pub fn parse_elements(value: &str) -> Option<Vec<String>> {
+    // Arrays with non-default bounds are prefixed like `[0:2]=`
+    let value = match value.strip_prefix('[') {
+        Some(_) => &value[value.find('=')? + 1..],
+        None => value,
+    };
+    let inner = value.strip_prefix('{')?.strip_suffix('}')?;
+    if inner.is_empty() {
+        return Some(Vec::new());
+    }
+
+    let mut elements = Vec::new();
+    let mut current = String::new();
+    let mut chars = inner.chars();
+    let mut in_quotes = false;
+    let mut depth = 0;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_quotes || depth > 0 => {
+                if depth > 0 {
+                    current.push(c);
+                }
+                current.extend(chars.next());
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                if depth > 0 {
+                    current.push(c);
+                }
+            }
+            '{' if !in_quotes => {
+                depth += 1;
+                current.push(c);
+            }
+            '}' if !in_quotes => {
+                depth -= 1;
+                current.push(c);
+            }
+            ',' if !in_quotes && depth == 0 => elements.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    elements.push(current);
+    Some(elements)
+}
+
+/// The grid form of an array: its element count ahead of the value.
+pub fn grid_text(value: &str) -> Option<String> {
+    let count = parse_elements(value)?.len();
+    Some(format!("({}) {}", count, value))
+}
+
+/// The field detail form of an array: one numbered element per line.
+pub fn detail_text(value: &str) -> Option<String> {
+    let elements = parse_elements(value)?;
+    let mut text = format!(
+        "{} element{}\n",
+        elements.len(),
+        if elements.len() == 1 { "" } else { "s" }
+    );
+    for (i, element) in elements.iter().enumerate() {
+        text.push_str(&format!("\n[{}] {}", i + 1, element));
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn elements(value: &str) -> Vec<String> {
+        parse_elements(value).unwrap()
+    }
+
+    #[test]
+    fn test_parse_elements() {
+        assert_eq!(elements("{1,2,NULL}"), ["1", "2", "NULL"]);
+        assert_eq!(
+            elements(r#"{"a,b","say \"hi\"",plain}"#),
+            ["a,b", r#"say "hi""#, "plain"]
+        );
+        assert_eq!(elements("{{1,2},{3,4}}"), ["{1,2}", "{3,4}"]);
+        assert_eq!(elements("[0:1]={7,8}"), ["7", "8"]);
+        assert!(elements("{}").is_empty());
+        assert_eq!(parse_elements("NULL"), None);
+        assert_eq!(parse_elements("plain text"), None);
+    }
+
+    #[test]
+    fn test_display_forms() {
+        assert_eq!(
+            grid_text("{read,write}").as_deref(),
+            Some("(2) {read,write}")
+        );
+        assert_eq!(grid_text("{}").as_deref(), Some("(0) {}"));
+        assert_eq!(
+            detail_text("{read}").as_deref(),
+            Some("1 element\n\n[1] read")
+        );
+    }
+}
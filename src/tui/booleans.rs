@@ -0,0 +1,45 @@
+//! Display of boolean columns in the table view.
+//!
+//! Values arrive as the server's text form, `true` or `false`. The single letters psql
+//! prints, `t` and `f`, are understood too.
+
+use crate::config::BoolDisplay;
+
+/// Whether a column type as reported by `information_schema` is a boolean.
+pub fn is_boolean_type(data_type: &str) -> bool {
+    data_type == "boolean"
+}
+
+/// `value` shown the way `display` asks for, or `None` to show it unchanged. NULL and
+/// anything else that isn't a boolean is left alone.
+pub fn display(value: &str, display: BoolDisplay) -> Option<String> {
+    let value = match value {
+        "true" | "t" => true,
+        "false" | "f" => false,
+        _ => return None,
+    };
+    let shown = match (display, value) {
+        (BoolDisplay::Words, true) => "true",
+        (BoolDisplay::Words, false) => "false",
+        (BoolDisplay::Checks, true) => "✓",
+        (BoolDisplay::Checks, false) => "✗",
+        (BoolDisplay::Letters, true) => "t",
+        (BoolDisplay::Letters, false) => "f",
+    };
+    Some(shown.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(display("true", BoolDisplay::Checks).as_deref(), Some("✓"));
+        assert_eq!(display("f", BoolDisplay::Words).as_deref(), Some("false"));
+        assert_eq!(display("false", BoolDisplay::Letters).as_deref(), Some("f"));
+        assert_eq!(display("NULL", BoolDisplay::Checks), None);
+        assert!(is_boolean_type("boolean"));
+        assert!(!is_boolean_type("boolean[]"));
+    }
+}
@@ -0,0 +1,123 @@
+//! Editing the value of one cell in the table view, opened with 'e'.
+//!
+//! The row is found again by its primary key, so only tables with a single-column
+//! primary key can be edited. The new value is shown once more for confirmation before
+//! the `UPDATE` runs. NULL is a state of its own, switched with Ctrl+N, so a text value
+//! that reads "NULL" stays text.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CellEdit {
+    pub table: String,
+    pub column: String,
+    pub key_column: String,
+    pub key_value: String,
+    pub value: String,    // As typed, starting from the current value
+    pub null: bool,       // Whether the value is SQL NULL, in which case `value` is set aside
+    pub confirming: bool, // Whether the typed value is being confirmed
+}
+
+impl CellEdit {
+    /// Start editing a cell whose current value is `value`, or NULL if it's None.
+    pub fn new(
+        table: String,
+        column: String,
+        key_column: String,
+        key_value: String,
+        value: Option<String>,
+    ) -> CellEdit {
+        CellEdit {
+            table,
+            column,
+            key_column,
+            key_value,
+            null: value.is_none(),
+            value: value.unwrap_or_default(),
+            confirming: false,
+        }
+    }
+
+    /// Type text. Typing over NULL starts a new value.
+    pub fn push_str(&mut self, text: &str) {
+        if self.null {
+            self.null = false;
+            self.value.clear();
+        }
+        self.value.push_str(text);
+    }
+
+    /// Delete the last character, or clear NULL to an empty value.
+    pub fn backspace(&mut self) {
+        if self.null {
+            self.null = false;
+            self.value.clear();
+        } else {
+            self.value.pop();
+        }
+    }
+
+    /// Switch between NULL and the value typed before it.
+    pub fn toggle_null(&mut self) {
+        self.null = !self.null;
+    }
+
+    /// The value to store, None for SQL NULL.
+    pub fn new_value(&self) -> Option<String> {
+        (!self.null).then(|| self.value.clone())
+    }
+
+    /// The question asked before the update runs.
+    pub fn confirmation(&self) -> String {
+        let value = match self.new_value() {
+            Some(value) => format!("'{}'", value),
+            None => "NULL".to_string(),
+        };
+        format!(
+            "Set {} to {} where {} = {}?",
+            self.column, value, self.key_column, self.key_value
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cell_edit_values() {
+        let mut edit = CellEdit::new(
+            "people".to_string(),
+            "note".to_string(),
+            "id".to_string(),
+            "7".to_string(),
+            None,
+        );
+        assert_eq!(edit.new_value(), None);
+        assert_eq!(edit.confirmation(), "Set note to NULL where id = 7?");
+
+        edit.push_str("hello");
+        assert_eq!(edit.new_value(), Some("hello".to_string()));
+        assert_eq!(edit.confirmation(), "Set note to 'hello' where id = 7?");
+
+        // NULL keeps the typed value aside, and backspace clears it
+        edit.toggle_null();
+        assert_eq!(edit.new_value(), None);
+        edit.toggle_null();
+        assert_eq!(edit.new_value(), Some("hello".to_string()));
+        edit.toggle_null();
+        edit.backspace();
+        assert_eq!(edit.new_value(), Some(String::new()));
+    }
+
+    #[test]
+    fn test_text_reading_null_stays_text() {
+        let edit = CellEdit::new(
+            "people".to_string(),
+            "note".to_string(),
+            "id".to_string(),
+            "7".to_string(),
+            Some("NULL".to_string()),
+        );
+        assert_eq!(edit.new_value(), Some("NULL".to_string()));
+        assert_eq!(edit.confirmation(), "Set note to 'NULL' where id = 7?");
+    }
+}
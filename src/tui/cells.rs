@@ -0,0 +1,192 @@
+//! Fitting cell values into the columns of the data grid.
+//!
+//! Widths are the columns text takes on screen rather than bytes or chars: CJK characters
+//! and most emoji take two, and combining marks none. Text is only ever cut between
+//! grapheme clusters, so an accent stays with its letter.
+
+use std::borrow::Cow;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// How many columns `text` takes on screen.
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// `text` cut to fit `width` columns, ending in `…` when anything was cut off. A wide
+/// character that would only half fit is left out, so the result may be a column short.
+pub fn ellipsize(text: &str, width: usize) -> Cow<'_, str> {
+    if display_width(text) <= width {
+        return Cow::Borrowed(text);
+    }
+    let room = width.saturating_sub(1); // Keeping a column for the `…`
+    let mut shown = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        used += grapheme.width();
+        if used > room {
+            break;
+        }
+        shown.push_str(grapheme);
+    }
+    if width > 0 {
+        shown.push('…');
+    }
+    Cow::Owned(shown)
+}
+
+/// `text` followed by spaces to make it `width` columns wide.
+pub fn pad(text: &str, width: usize) -> String {
+    format!(
+        "{}{}",
+        text,
+        " ".repeat(width.saturating_sub(display_width(text)))
+    )
+}
+
+/// How wide each column wants to be: as wide as its header or widest value, but no wider
+/// than `max`, and at least one column.
+pub fn content_widths(headers: &[String], rows: &[Vec<String>], max: usize) -> Vec<u16> {
+    headers
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            let widest = rows
+                .iter()
+                .filter_map(|row| row.get(i))
+                .map(|value| display_width(value))
+                .chain([display_width(header)])
+                .max()
+                .unwrap_or(0);
+            widest.clamp(1, max) as u16
+        })
+        .collect()
+}
+
+/// Widths for columns that want `wanted` columns each, sharing `available` columns. When
+/// they don't all fit, each gives up room in proportion to how wide it wanted to be, but
+/// isn't made narrower than `min` unless it wanted less than that anyway.
+pub fn fit_widths(wanted: &[u16], available: u16, min: u16) -> Vec<u16> {
+    let total: u32 = wanted.iter().map(|&width| width as u32).sum();
+    if total <= available as u32 {
+        return wanted.to_vec();
+    }
+    wanted
+        .iter()
+        .map(|&width| {
+            let share = (width as u32 * available as u32 / total) as u16;
+            share.max(width.min(min))
+        })
+        .collect()
+}
+
+/// The status bar hint for a selected cell that doesn't fit its column.
+pub fn truncated_hint(value: &str) -> String {
+    format!(
+        "Cell truncated: {} characters, Enter shows all of it",
+        value.chars().count()
+    )
+}
+
+/// The byte ranges of the rows `line` wraps into at `width` columns. Lines break between
+/// words where they can and anywhere between graphemes where a word is too long for a row.
+/// An empty line is a single empty row.
+pub fn wrap_ranges(line: &str, width: usize) -> Vec<std::ops::Range<usize>> {
+    let width = width.max(1);
+    let mut rows = Vec::new();
+    let mut start = 0; // Where the current row starts
+    let mut used = 0;
+    for (offset, word) in line.split_word_bound_indices() {
+        let word_width = display_width(word);
+        // Spaces at the end of a row hang past its edge rather than starting the next one
+        if used + word_width <= width || (used > 0 && word.trim().is_empty()) {
+            used += word_width;
+            continue;
+        }
+        if used > 0 && word_width <= width {
+            rows.push(start..offset);
+            start = offset;
+            used = word_width;
+            continue;
+        }
+        // Too long for a row of its own, so it's split wherever the row fills up
+        for (index, grapheme) in word.grapheme_indices(true) {
+            let grapheme_width = grapheme.width();
+            if used + grapheme_width > width && used > 0 {
+                rows.push(start..offset + index);
+                start = offset + index;
+                used = 0;
+            }
+            used += grapheme_width;
+        }
+    }
+    rows.push(start..line.len());
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ellipsize() {
+        assert_eq!(ellipsize("users", 5), "users");
+        assert!(matches!(ellipsize("users", 5), Cow::Borrowed(_)));
+        assert_eq!(ellipsize("user_roles", 5), "user…");
+        assert_eq!(ellipsize("user_roles", 1), "…");
+        assert_eq!(ellipsize("user_roles", 0), "");
+        // "é" as "e" and a combining accent is one column, and isn't split
+        assert_eq!(ellipsize("cafe\u{301} au lait", 5), "cafe\u{301}…");
+        assert_eq!(display_width("cafe\u{301}"), 4);
+    }
+
+    #[test]
+    fn test_ellipsize_by_display_width() {
+        // Each of these Japanese characters takes two columns
+        let text = "東京都の人口";
+        assert_eq!(display_width(text), 12);
+        assert_eq!(ellipsize(text, 12), text);
+        assert_eq!(ellipsize(text, 7), "東京都…");
+        // The fourth character would only half fit, so it's left out
+        assert_eq!(ellipsize(text, 8), "東京都…");
+        assert_eq!(display_width(&ellipsize(text, 8)), 7);
+        assert_eq!(ellipsize("ab東京", 4), "ab…");
+        assert_eq!(pad("東京", 6), "東京  ");
+    }
+
+    #[test]
+    fn test_wrap_ranges() {
+        let rows = |line: &'static str, width| -> Vec<&str> {
+            wrap_ranges(line, width)
+                .into_iter()
+                .map(|range| &line[range])
+                .collect()
+        };
+        assert_eq!(rows("", 10), [""]);
+        assert_eq!(rows("select * from users", 10), ["select * ", "from users"]);
+        assert_eq!(rows("select   from", 7), ["select   ", "from"]);
+        assert_eq!(rows("abcdefghij klm", 4), ["abcd", "efgh", "ij ", "klm"]);
+        // Two columns each, so three fit in a row of seven
+        assert_eq!(rows("東京都の人口", 7), ["東京都", "の人口"]);
+    }
+
+    #[test]
+    fn test_content_widths() {
+        let headers = vec!["id".to_string(), "description".to_string()];
+        let rows = vec![
+            vec!["1".to_string(), "short".to_string()],
+            vec!["1024".to_string(), "東京".repeat(30)],
+        ];
+        assert_eq!(content_widths(&headers, &rows, 40), [4, 40]);
+        assert_eq!(content_widths(&headers, &[], 40), [2, 11]);
+        assert_eq!(content_widths(&[String::new()], &[], 40), [1]);
+    }
+
+    #[test]
+    fn test_fit_widths() {
+        assert_eq!(fit_widths(&[4, 40], 80, 8), [4, 40]);
+        // 60 columns wanted in 30: each gets half of what it wanted, but the narrow
+        // column keeps its 4 since that's under the minimum
+        assert_eq!(fit_widths(&[4, 16, 40], 30, 8), [4, 8, 20]);
+    }
+}
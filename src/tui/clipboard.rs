@@ -0,0 +1,29 @@
+//! Clipboard access through the terminal.
+//!
+//! Uses the OSC 52 escape sequence, which most modern terminal emulators (and tmux with
+//! `set-clipboard on`) forward to the system clipboard, including over SSH.
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::io::{self, Write};
+
+/// Copy `text` to the system clipboard.
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    write!(stdout, "{}", osc52_sequence(text))?;
+    stdout.flush()
+}
+
+fn osc52_sequence(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", STANDARD.encode(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(osc52_sequence("hi"), "\x1b]52;c;aGk=\x07");
+    }
+}
@@ -0,0 +1,91 @@
+//! The overlay for choosing which columns the table view shows.
+
+use ratatui::widgets::ListState;
+
+#[derive(Debug, Clone)]
+pub struct ColumnPicker {
+    pub columns: Vec<(String, bool)>, // Every column of the table, and whether it's shown
+    pub state: ListState,
+}
+
+impl ColumnPicker {
+    pub fn new(columns: Vec<String>, hidden: &[String]) -> ColumnPicker {
+        let columns: Vec<(String, bool)> = columns
+            .into_iter()
+            .map(|name| {
+                let visible = !hidden.contains(&name);
+                (name, visible)
+            })
+            .collect();
+        let mut state = ListState::default();
+        if !columns.is_empty() {
+            state.select(Some(0));
+        }
+        ColumnPicker { columns, state }
+    }
+
+    pub fn next(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some((i + 1) % self.columns.len()));
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state
+                .select(Some(i.checked_sub(1).unwrap_or(self.columns.len() - 1)));
+        }
+    }
+
+    /// Show or hide the selected column. Returns false, changing nothing, when that
+    /// would hide the last visible column.
+    pub fn toggle(&mut self) -> bool {
+        let Some(i) = self.state.selected() else {
+            return true;
+        };
+        let visible = self.columns.iter().filter(|(_, visible)| *visible).count();
+        if self.columns[i].1 && visible == 1 {
+            return false;
+        }
+        self.columns[i].1 = !self.columns[i].1;
+        true
+    }
+
+    pub fn hidden(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .filter(|(_, visible)| !visible)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn picker() -> ColumnPicker {
+        let columns = ["id", "name", "notes"].map(String::from).to_vec();
+        ColumnPicker::new(columns, &["notes".to_string()])
+    }
+
+    #[test]
+    fn test_toggle_updates_hidden_columns() {
+        let mut picker = picker();
+        assert_eq!(picker.hidden(), vec!["notes"]);
+
+        assert!(picker.toggle());
+        picker.previous();
+        assert!(picker.toggle());
+        assert_eq!(picker.hidden(), vec!["id"]);
+    }
+
+    #[test]
+    fn test_last_visible_column_cannot_be_hidden() {
+        let mut picker = picker();
+        assert!(picker.toggle());
+        picker.next();
+        assert!(!picker.toggle());
+        assert_eq!(picker.hidden(), vec!["id", "notes"]);
+    }
+}
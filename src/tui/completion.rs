@@ -0,0 +1,142 @@
+//! Tab completion of table and column names in the query input.
+//!
+//! There is no SQL parsing here: the word before the cursor is matched against known
+//! identifiers, case-insensitively.
+
+use std::collections::HashSet;
+
+/// An in-progress completion of the word before the cursor.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub start: usize, // Char index where the completed word starts
+    pub candidates: Vec<String>,
+    pub selected: usize,
+}
+
+impl Completion {
+    /// Collect the names that complete the word ending at `cursor` (a char index).
+    /// Returns `None` if there is no word there or nothing matches it.
+    pub fn new<'a>(
+        input: &str,
+        cursor: usize,
+        names: impl IntoIterator<Item = &'a str>,
+    ) -> Option<Completion> {
+        let start = word_start(input, cursor);
+        let prefix: String = input
+            .chars()
+            .skip(start)
+            .take(cursor - start)
+            .collect::<String>()
+            .to_lowercase();
+        if prefix.is_empty() {
+            return None;
+        }
+
+        let mut candidates: Vec<String> = names
+            .into_iter()
+            .filter(|name| name.to_lowercase().starts_with(&prefix))
+            .map(str::to_string)
+            .collect();
+        candidates.sort_by_key(|name| name.to_lowercase());
+        candidates.dedup();
+
+        (!candidates.is_empty()).then_some(Completion {
+            start,
+            candidates,
+            selected: 0,
+        })
+    }
+
+    pub fn selected(&self) -> &str {
+        &self.candidates[self.selected]
+    }
+
+    /// Move to the next candidate, wrapping around after the last.
+    pub fn next(&mut self) {
+        self.selected = (self.selected + 1) % self.candidates.len();
+    }
+}
+
+/// The char index where the identifier ending at `cursor` starts.
+pub fn word_start(input: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = input.chars().take(cursor).collect();
+    chars
+        .iter()
+        .rposition(|&c| !is_identifier_char(c))
+        .map_or(0, |p| p + 1)
+}
+
+/// Replace the chars from `start` to `end` with `replacement`, returning the new text and
+/// the char index just after the replacement.
+pub fn replace_chars(input: &str, start: usize, end: usize, replacement: &str) -> (String, usize) {
+    let mut result: String = input.chars().take(start).collect();
+    result.push_str(replacement);
+    result.extend(input.chars().skip(end));
+    (result, start + replacement.chars().count())
+}
+
+/// The distinct identifiers in `input`, lower-cased.
+pub fn identifiers(input: &str) -> HashSet<String> {
+    input
+        .split(|c: char| !is_identifier_char(c))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_start() {
+        assert_eq!(word_start("SELECT * FROM us", 16), 14);
+        assert_eq!(word_start("SELECT u.na", 11), 9);
+        assert_eq!(word_start("abc", 3), 0);
+        assert_eq!(word_start("SELECT ", 7), 7);
+    }
+
+    #[test]
+    fn test_completion_matches_case_insensitively() {
+        let names = ["users", "user_roles", "Projects", "orders"];
+        let completion = Completion::new("select * from US", 16, names).unwrap();
+        assert_eq!(completion.start, 14);
+        assert_eq!(completion.candidates, vec!["user_roles", "users"]);
+
+        let completion = Completion::new("select * from pro", 17, names).unwrap();
+        assert_eq!(completion.selected(), "Projects");
+
+        assert!(Completion::new("select * from zz", 16, names).is_none());
+        assert!(Completion::new("select * from ", 14, names).is_none());
+    }
+
+    #[test]
+    fn test_completion_cycles() {
+        let mut completion = Completion::new("a", 1, ["ab", "ac"]).unwrap();
+        assert_eq!(completion.selected(), "ab");
+        completion.next();
+        assert_eq!(completion.selected(), "ac");
+        completion.next();
+        assert_eq!(completion.selected(), "ab");
+    }
+
+    #[test]
+    fn test_replace_chars() {
+        assert_eq!(
+            replace_chars("select é fr", 7, 8, "users"),
+            ("select users fr".to_string(), 12)
+        );
+    }
+
+    #[test]
+    fn test_identifiers() {
+        let words = identifiers("SELECT u.name FROM Users u");
+        assert!(words.contains("users"));
+        assert!(words.contains("name"));
+        assert!(!words.contains("Users"));
+    }
+}
@@ -0,0 +1,131 @@
+//! Display of enum and composite columns.
+//!
+//! Composite values arrive in the server's text form, e.g. `(1,"Main St",)`, where a
+//! field is quoted when it needs to be and left empty when it's NULL. Enums arrive as
+//! their label and only get more in the field detail view, where the other labels are
+//! listed.
+
+use crate::db::UserType;
+
+/// The fields of a composite's text form, with quotes and escapes removed and `None`
+/// for NULL, or `None` if the value isn't a composite.
+pub fn parse_fields(value: &str) -> Option<Vec<Option<String>>> {
+    let inner = value.strip_prefix('(')?.strip_suffix(')')?;
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false; // Whether the current field had quotes, so isn't NULL
+    let mut in_quotes = false;
+    let mut chars = inner.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            // A doubled quote inside quotes is a literal one
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => {
+                in_quotes = !in_quotes;
+                quoted = true;
+            }
+            ',' if !in_quotes => {
+                let field = std::mem::take(&mut current);
+                fields.push((quoted || !field.is_empty()).then_some(field));
+                quoted = false;
+            }
+            _ => current.push(c),
+        }
+    }
+    fields.push((quoted || !current.is_empty()).then_some(current));
+    Some(fields)
+}
+
+/// The grid form of a composite: its fields in parentheses, without quotes.
+pub fn grid_text(value: &str) -> Option<String> {
+    let fields: Vec<String> = parse_fields(value)?
+        .into_iter()
+        .map(|field| field.unwrap_or_else(|| "NULL".to_string()))
+        .collect();
+    Some(format!("({})", fields.join(", ")))
+}
+
+/// The field detail form of a value of a user-defined type: a composite's fields one
+/// per line with their names and types, or an enum's label followed by all its labels.
+pub fn detail_text(value: &str, user_type: &UserType) -> Option<String> {
+    match user_type {
+        UserType::Enum(labels) => Some(format!("{}\n\nOne of: {}", value, labels.join(", "))),
+        UserType::Composite(names) => {
+            let fields = parse_fields(value)?;
+            let width = names
+                .iter()
+                .map(|(name, data_type)| name.chars().count() + data_type.chars().count() + 3)
+                .max()
+                .unwrap_or(0);
+            let lines: Vec<String> = fields
+                .iter()
+                .enumerate()
+                .map(|(i, field)| {
+                    let label = match names.get(i) {
+                        Some((name, data_type)) => format!("{} ({})", name, data_type),
+                        None => format!("field {}", i + 1),
+                    };
+                    format!(
+                        "{:width$}: {}",
+                        label,
+                        field.as_deref().unwrap_or("NULL"),
+                        width = width
+                    )
+                })
+                .collect();
+            Some(lines.join("\n"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_fields() {
+        assert_eq!(
+            parse_fields(r#"(1,"Main St, Apt 2",,"")"#).unwrap(),
+            [
+                Some("1".to_string()),
+                Some("Main St, Apt 2".to_string()),
+                None,
+                Some(String::new())
+            ]
+        );
+        assert_eq!(
+            parse_fields(r#"("say ""hi""","back\\slash")"#).unwrap(),
+            [
+                Some("say \"hi\"".to_string()),
+                Some("back\\slash".to_string())
+            ]
+        );
+        assert_eq!(parse_fields("plain"), None);
+        assert_eq!(
+            grid_text(r#"(1,"Main St",)"#).unwrap(),
+            "(1, Main St, NULL)"
+        );
+    }
+
+    #[test]
+    fn test_detail_text() {
+        let address = UserType::Composite(vec![
+            ("street".to_string(), "text".to_string()),
+            ("zip".to_string(), "int4".to_string()),
+        ]);
+        assert_eq!(
+            detail_text(r#"("Main St",)"#, &address).unwrap(),
+            "street (text): Main St\nzip (int4)   : NULL"
+        );
+
+        let mood = UserType::Enum(vec!["sad".to_string(), "happy".to_string()]);
+        assert_eq!(
+            detail_text("happy", &mood).unwrap(),
+            "happy\n\nOne of: sad, happy"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! Rows as tab- or comma-separated text, for copying several at once.
+//!
+//! Both start with a line of column names. NULL is written as an empty field, which is
+//! what spreadsheets make of a blank cell, while a text value of "NULL" is kept as it is.
+
+use crate::db::typed_rows::TypedRows;
+
+/// Tab-separated values, the format spreadsheets paste into separate cells. Tabs and line
+/// breaks inside a value are turned into spaces, since TSV has no way to quote them.
+pub fn tsv(rows: &TypedRows) -> String {
+    delimited(rows, '\t', |value| value.replace(['\t', '\r', '\n'], " "))
+}
+
+/// Comma-separated values as in RFC 4180, with values that need it quoted.
+pub fn csv(rows: &TypedRows) -> String {
+    delimited(rows, ',', |value| {
+        if value.contains([',', '"', '\r', '\n']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    })
+}
+
+fn delimited(rows: &TypedRows, separator: char, field: impl Fn(&str) -> String) -> String {
+    let line = |values: &mut dyn Iterator<Item = Option<&str>>| {
+        let fields: Vec<String> = values
+            .map(|value| value.map(&field).unwrap_or_default())
+            .collect();
+        fields.join(&separator.to_string()) + "\n"
+    };
+    let mut text = line(&mut rows.columns.iter().map(|(name, _)| Some(name.as_str())));
+    for row in &rows.rows {
+        text.push_str(&line(&mut row.iter().map(Option::as_deref)));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::typed_rows::ValueKind;
+
+    #[test]
+    fn test_tsv_and_csv() {
+        let value = |v: &str| Some(v.to_string());
+        let rows = TypedRows {
+            columns: vec![
+                ("id".to_string(), ValueKind::Number),
+                ("note".to_string(), ValueKind::Text),
+            ],
+            rows: vec![
+                vec![value("1"), value("plain")],
+                vec![value("2"), value("a, \"quoted\"\tand\nbroken")],
+                vec![value("3"), None],
+                vec![value("4"), value("NULL")],
+            ],
+        };
+
+        assert_eq!(
+            tsv(&rows),
+            "id\tnote\n1\tplain\n2\ta, \"quoted\" and broken\n3\t\n4\tNULL\n"
+        );
+        assert_eq!(
+            csv(&rows),
+            "id,note\n1,plain\n2,\"a, \"\"quoted\"\"\tand\nbroken\"\n3,\n4,NULL\n"
+        );
+    }
+}
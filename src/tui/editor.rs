@@ -0,0 +1,272 @@
+//! Editing and cursor movement in the multi-line query input.
+//!
+//! The cursor is kept as a char index into the whole input, never a byte index, so it
+//! stays on a char boundary whatever is typed; rows and columns are worked out from the
+//! line breaks when they're needed.
+
+/// Insert `c` at the char index `cursor`, returning the cursor just after it.
+pub fn insert_char(text: &mut String, cursor: usize, c: char) -> usize {
+    text.insert(byte_index(text, cursor), c);
+    cursor + 1
+}
+
+/// Insert `pasted` at the char index `cursor`, returning the cursor just after it. Line
+/// breaks are normalized to `\n`, as terminals often send `\r` for them.
+pub fn insert_str(text: &mut String, cursor: usize, pasted: &str) -> usize {
+    let pasted = pasted.replace("\r\n", "\n").replace('\r', "\n");
+    text.insert_str(byte_index(text, cursor), &pasted);
+    cursor + pasted.chars().count()
+}
+
+/// Delete the char before the char index `cursor`, returning the cursor moved back
+/// over it.
+pub fn delete_before(text: &mut String, cursor: usize) -> usize {
+    if cursor == 0 {
+        return 0;
+    }
+    text.remove(byte_index(text, cursor - 1));
+    cursor - 1
+}
+
+pub fn move_right(text: &str, cursor: usize) -> usize {
+    (cursor + 1).min(text.chars().count())
+}
+
+/// Delete the chars between the char indices `start` and `cursor`, returning the cursor
+/// moved back to `start`.
+pub fn delete_range(text: &mut String, start: usize, cursor: usize) -> usize {
+    text.replace_range(byte_index(text, start)..byte_index(text, cursor), "");
+    start
+}
+
+// Words are runs of identifier chars or runs of punctuation, so `users.id` is three
+// words and `(` or `::` stops a jump the way a space does
+#[derive(PartialEq)]
+enum CharClass {
+    Space,
+    Identifier,
+    Punctuation,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Space
+    } else if c.is_alphanumeric() || c == '_' || c == '$' {
+        CharClass::Identifier
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+/// The start of the word before `cursor`, skipping any whitespace in between.
+pub fn word_left(text: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = text.chars().take(cursor).collect();
+    let mut i = chars.len();
+    while i > 0 && char_class(chars[i - 1]) == CharClass::Space {
+        i -= 1;
+    }
+    if let Some(&c) = chars.get(i.wrapping_sub(1)) {
+        let class = char_class(c);
+        while i > 0 && char_class(chars[i - 1]) == class {
+            i -= 1;
+        }
+    }
+    i
+}
+
+/// The end of the word after `cursor`, skipping any whitespace in between.
+pub fn word_right(text: &str, cursor: usize) -> usize {
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = cursor.min(chars.len());
+    while i < chars.len() && char_class(chars[i]) == CharClass::Space {
+        i += 1;
+    }
+    if let Some(&c) = chars.get(i) {
+        let class = char_class(c);
+        while i < chars.len() && char_class(chars[i]) == class {
+            i += 1;
+        }
+    }
+    i
+}
+
+fn byte_index(text: &str, cursor: usize) -> usize {
+    text.char_indices()
+        .nth(cursor)
+        .map_or(text.len(), |(i, _)| i)
+}
+
+/// The row and column of the char index `cursor`, both counted from zero.
+pub fn line_col(text: &str, cursor: usize) -> (usize, usize) {
+    let before: Vec<char> = text.chars().take(cursor).collect();
+    let row = before.iter().filter(|&&c| c == '\n').count();
+    let col = before.iter().rev().take_while(|&&c| c != '\n').count();
+    (row, col)
+}
+
+/// The char index of `col` on line `row`, clamped to the end of that line, or to the end
+/// of the text if there are fewer lines.
+pub fn cursor_at(text: &str, row: usize, col: usize) -> usize {
+    let mut index = 0;
+    for (i, line) in text.split('\n').enumerate() {
+        let len = line.chars().count();
+        if i == row {
+            return index + col.min(len);
+        }
+        index += len + 1;
+    }
+    text.chars().count()
+}
+
+/// The cursor moved to the same column on the previous line, or to the start of the
+/// text on the first line.
+pub fn move_up(text: &str, cursor: usize) -> usize {
+    match line_col(text, cursor) {
+        (0, _) => 0,
+        (row, col) => cursor_at(text, row - 1, col),
+    }
+}
+
+/// The cursor moved to the same column on the next line, or to the end of the text on
+/// the last line.
+pub fn move_down(text: &str, cursor: usize) -> usize {
+    let (row, col) = line_col(text, cursor);
+    if row + 1 >= line_count(text) {
+        text.chars().count()
+    } else {
+        cursor_at(text, row + 1, col)
+    }
+}
+
+pub fn line_start(text: &str, cursor: usize) -> usize {
+    let (_, col) = line_col(text, cursor);
+    cursor - col
+}
+
+pub fn line_end(text: &str, cursor: usize) -> usize {
+    let (row, _) = line_col(text, cursor);
+    cursor_at(text, row, usize::MAX)
+}
+
+pub fn line_count(text: &str) -> usize {
+    text.split('\n').count()
+}
+
+/// How far to scroll lines shown `width` columns wide so the cursor, at column `cursor_x`
+/// of its line, stays in view. The `current` offset is kept while the cursor is in view,
+/// so the text only shifts once the cursor goes past an edge.
+pub fn horizontal_scroll(current: usize, cursor_x: usize, width: usize) -> usize {
+    if cursor_x < current {
+        cursor_x
+    } else if cursor_x >= current + width {
+        (cursor_x + 1).saturating_sub(width)
+    } else {
+        current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUERY: &str = "SELECT *\nFROM users\nWHERE id = 1";
+
+    #[test]
+    fn test_editing_around_multibyte_chars() {
+        let mut text = String::new();
+        let mut cursor = 0;
+        for c in "café 🎉!".chars() {
+            cursor = insert_char(&mut text, cursor, c);
+        }
+        assert_eq!(cursor, 7);
+
+        // Step back over "!" and the emoji, which are one char each however many bytes
+        cursor -= 2;
+        cursor = insert_char(&mut text, cursor, 'x');
+        assert_eq!(text, "café x🎉!");
+        cursor = move_right(&text, cursor);
+        cursor = move_right(&text, cursor);
+        cursor = move_right(&text, cursor);
+        assert_eq!(cursor, 8);
+
+        cursor = delete_before(&mut text, cursor);
+        cursor = delete_before(&mut text, cursor);
+        assert_eq!(text, "café x");
+        cursor = delete_before(&mut text, cursor - 2);
+        assert_eq!((text.as_str(), cursor), ("caf x", 3));
+        assert_eq!(delete_before(&mut text, 0), 0);
+    }
+
+    #[test]
+    fn test_insert_str_normalizes_line_breaks() {
+        let mut text = "SELECT 1".to_string();
+        let cursor = insert_str(&mut text, 6, "\r\n  é,\r");
+        assert_eq!(text, "SELECT\n  é,\n 1");
+        assert_eq!(cursor, 12);
+    }
+
+    #[test]
+    fn test_line_col() {
+        assert_eq!(line_col(QUERY, 0), (0, 0));
+        assert_eq!(line_col(QUERY, 8), (0, 8));
+        assert_eq!(line_col(QUERY, 9), (1, 0));
+        assert_eq!(line_col(QUERY, 14), (1, 5));
+        assert_eq!(line_col("é\nü", 3), (1, 1));
+    }
+
+    #[test]
+    fn test_move_up_and_down_keep_column() {
+        // From "FROM |users" up to "SELE|CT *" is column 5 on both lines
+        assert_eq!(move_up(QUERY, 14), 5);
+        assert_eq!(move_down(QUERY, 5), 14);
+        // Column 11 of "WHERE id = 1" is clamped to the end of "FROM users"
+        assert_eq!(move_up(QUERY, 31), 19);
+        assert_eq!(move_up(QUERY, 3), 0);
+        assert_eq!(move_down(QUERY, 25), QUERY.chars().count());
+    }
+
+    #[test]
+    fn test_word_movement_stops_at_punctuation() {
+        let text = "SELECT u.id,  count(*)";
+        assert_eq!(word_left(text, 22), 19); // Before "(*)"
+        assert_eq!(word_left(text, 19), 14); // "count"
+        assert_eq!(word_left(text, 14), 11); // Over the spaces to ","
+        assert_eq!(word_left(text, 11), 9); // "id" but not "u."
+        assert_eq!(word_left(text, 3), 0);
+        assert_eq!(word_right(text, 0), 6);
+        assert_eq!(word_right(text, 6), 8); // Over the space to the end of "u"
+        assert_eq!(word_right(text, 11), 12); // Just ","
+        assert_eq!(word_right(text, 12), 19);
+        assert_eq!(word_right(text, 22), 22);
+        // Line breaks are whitespace too
+        assert_eq!(word_left("a\nbé", 2), 0);
+    }
+
+    #[test]
+    fn test_delete_range() {
+        let mut text = "SELECT naïve_col FROM t".to_string();
+        let cursor = word_left(&text, 16);
+        assert_eq!(delete_range(&mut text, cursor, 16), 7);
+        assert_eq!(text, "SELECT  FROM t");
+    }
+
+    #[test]
+    fn test_line_start_and_end() {
+        assert_eq!(line_start(QUERY, 14), 9);
+        assert_eq!(line_end(QUERY, 14), 19);
+        assert_eq!(line_end(QUERY, 0), 8);
+        assert_eq!(line_end("", 0), 0);
+    }
+
+    #[test]
+    fn test_horizontal_scroll() {
+        // Typing past the right edge shifts the text a column at a time
+        assert_eq!(horizontal_scroll(0, 9, 10), 0);
+        assert_eq!(horizontal_scroll(0, 10, 10), 1);
+        assert_eq!(horizontal_scroll(1, 11, 10), 2);
+        // Moving back within view keeps the offset, and past the left edge follows it
+        assert_eq!(horizontal_scroll(30, 35, 10), 30);
+        assert_eq!(horizontal_scroll(30, 12, 10), 12);
+        assert_eq!(horizontal_scroll(5, 0, 10), 0);
+    }
+}
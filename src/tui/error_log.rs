@@ -0,0 +1,78 @@
+//! Keeping the errors the TUI showed in `errors.log`, for looking into them afterwards.
+//!
+//! Each error is a line starting with when it happened and the connection in use, with
+//! the lines of multi-line messages indented under it. Once the file passes
+//! `MAX_LOG_BYTES` it's moved to `errors.log.1`, replacing the one before, so at most two
+//! files' worth are kept.
+
+use chrono::Local;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::Path;
+
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+
+/// Append `error`, shown while connected to `connection`, to the log at `path`.
+pub fn append(path: &Path, connection: Option<&str>, error: &str) -> io::Result<()> {
+    if fs::metadata(path).is_ok_and(|metadata| metadata.len() >= MAX_LOG_BYTES) {
+        let mut rotated = path.as_os_str().to_owned();
+        rotated.push(".1");
+        fs::rename(path, rotated)?;
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut options = OpenOptions::new();
+    options.create(true).append(true);
+    // Errors can quote the SQL that failed, so only the owner may read them
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    file.write_all(entry(&Local::now().to_rfc3339(), connection, error).as_bytes())
+}
+
+fn entry(timestamp: &str, connection: Option<&str>, error: &str) -> String {
+    format!(
+        "{} [{}] {}\n",
+        timestamp,
+        connection.unwrap_or("no connection"),
+        error.trim_end().replace('\n', "\n    ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entry() {
+        assert_eq!(
+            entry(
+                "2026-01-02T03:04:05+00:00",
+                Some("mydb"),
+                "Query failed:\nsyntax error\n"
+            ),
+            "2026-01-02T03:04:05+00:00 [mydb] Query failed:\n    syntax error\n"
+        );
+        assert_eq!(
+            entry("2026-01-02T03:04:05+00:00", None, "Connection refused"),
+            "2026-01-02T03:04:05+00:00 [no connection] Connection refused\n"
+        );
+    }
+
+    #[test]
+    fn test_log_is_rotated() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("errors.log");
+        fs::write(&path, vec![b'x'; MAX_LOG_BYTES as usize]).unwrap();
+        append(&path, Some("mydb"), "Connection lost").unwrap();
+
+        let rotated = fs::read(dir.path().join("errors.log.1")).unwrap();
+        assert_eq!(rotated.len(), MAX_LOG_BYTES as usize);
+        let log = fs::read_to_string(&path).unwrap();
+        assert!(log.ends_with(" [mydb] Connection lost\n"), "{}", log);
+    }
+}
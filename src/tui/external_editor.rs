@@ -0,0 +1,111 @@
+//! Editing the query in the user's own editor.
+//!
+//! The TUI gives up the terminal while the editor runs, the way `git commit` does: raw
+//! mode, the alternate screen, mouse capture and bracketed paste are all turned off and
+//! then back on once the editor exits.
+
+use anyhow::{Result, anyhow};
+use crossterm::event::{
+    DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+};
+use crossterm::execute;
+use crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+};
+use std::fs;
+use std::io::{self, Write};
+use std::process::Command;
+
+/// Open `text` in `$VISUAL` or `$EDITOR` and return the text as it was saved, without
+/// the line break editors add at the end. The caller must redraw the whole screen after.
+pub fn edit(text: &str) -> Result<String> {
+    let command = editor_command(
+        std::env::var("VISUAL").ok().as_deref(),
+        std::env::var("EDITOR").ok().as_deref(),
+    );
+    // A new file only we can read, under a name nobody can guess beforehand, since the
+    // query may hold passwords or data; it's removed when dropped, whatever goes wrong
+    let mut file = tempfile::Builder::new()
+        .prefix("daedalus-query-")
+        .suffix(".sql")
+        .tempfile()?;
+    file.write_all(text.as_bytes())?;
+    file.flush()?;
+    let path = file.path();
+
+    suspend_terminal()?;
+    let status = Command::new(&command[0])
+        .args(&command[1..])
+        .arg(path)
+        .status();
+    resume_terminal()?;
+
+    match status {
+        Ok(status) if status.success() => fs::read_to_string(path)
+            .map(strip_final_newline)
+            .map_err(Into::into),
+        Ok(status) => Err(anyhow!("{} exited with {}", command[0], status)),
+        Err(e) => Err(anyhow!("Cannot run {}: {}", command[0], e)),
+    }
+}
+
+// The editor to run and its arguments: $VISUAL, else $EDITOR, else a platform default.
+// The variables may hold arguments too, e.g. "code --wait".
+fn editor_command(visual: Option<&str>, editor: Option<&str>) -> Vec<String> {
+    let default = if cfg!(windows) { "notepad" } else { "vi" };
+    let command: Vec<String> = [visual, editor]
+        .into_iter()
+        .flatten()
+        .map(|value| {
+            value
+                .split_whitespace()
+                .map(String::from)
+                .collect::<Vec<_>>()
+        })
+        .find(|words| !words.is_empty())
+        .unwrap_or_default();
+    if command.is_empty() {
+        vec![default.to_string()]
+    } else {
+        command
+    }
+}
+
+fn strip_final_newline(text: String) -> String {
+    match text.strip_suffix('\n') {
+        Some(stripped) => stripped.strip_suffix('\r').unwrap_or(stripped).to_string(),
+        None => text,
+    }
+}
+
+fn suspend_terminal() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    let _ = execute!(stdout, DisableBracketedPaste);
+    execute!(stdout, LeaveAlternateScreen, DisableMouseCapture)?;
+    disable_raw_mode()
+}
+
+fn resume_terminal() -> io::Result<()> {
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+    let _ = execute!(stdout, EnableBracketedPaste);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_editor_command() {
+        assert_eq!(
+            editor_command(Some("code --wait"), Some("nano")),
+            ["code", "--wait"]
+        );
+        assert_eq!(editor_command(Some(" "), Some("nano")), ["nano"]);
+        let default = if cfg!(windows) { "notepad" } else { "vi" };
+        assert_eq!(editor_command(None, None), [default]);
+        assert_eq!(strip_final_newline("a\nb\r\n".to_string()), "a\nb");
+    }
+}
@@ -0,0 +1,95 @@
+//! Fuzzy filtering of the connection and table lists.
+//!
+//! A pattern matches when its chars appear in order in the candidate, ignoring case, like
+//! fzf. Matches are ranked so that runs of consecutive chars and matches at the start of
+//! words come first.
+
+/// How well `pattern` matches `candidate`, higher being better, or `None` if it doesn't.
+pub fn fuzzy_score(pattern: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut next = 0; // Where to look for the next pattern char
+    let mut previous: Option<usize> = None;
+
+    for p in pattern.chars().flat_map(char::to_lowercase) {
+        let offset = candidate[next..]
+            .iter()
+            .position(|c| c.to_lowercase().eq(std::iter::once(p)))?;
+        let index = next + offset;
+        score += match previous {
+            Some(previous) if previous + 1 == index => 3,
+            _ if index == 0 || !candidate[index - 1].is_alphanumeric() => 2,
+            _ => 0,
+        };
+        score -= offset as i64; // Chars skipped over
+        previous = Some(index);
+        next = index + 1;
+    }
+    Some(score)
+}
+
+/// The indices into `items` of those matching `pattern`, best first. Ties keep the
+/// list's order, and an empty pattern matches everything.
+pub fn filter_indices(items: &[String], pattern: &str) -> Vec<usize> {
+    let mut matches: Vec<(i64, usize)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(i, item)| Some((fuzzy_score(pattern, item)?, i)))
+        .collect();
+    matches.sort_by_key(|&(score, i)| (std::cmp::Reverse(score), i));
+    matches.into_iter().map(|(_, i)| i).collect()
+}
+
+/// The selection after moving one step forward or back through `visible`, wrapping at
+/// either end. A selection that isn't visible moves to the first visible item.
+pub fn step(visible: &[usize], selected: Option<usize>, forward: bool) -> Option<usize> {
+    let len = visible.len();
+    let position = selected.and_then(|s| visible.iter().position(|&v| v == s));
+    let next = match position {
+        Some(p) if forward => (p + 1) % len,
+        Some(p) => (p + len - 1) % len,
+        None => 0,
+    };
+    visible.get(next).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn items() -> Vec<String> {
+        ["users", "user_roles", "orders", "order_items"]
+            .map(String::from)
+            .to_vec()
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("usr", "users").is_some());
+        assert!(fuzzy_score("USR", "users").is_some());
+        assert_eq!(fuzzy_score("sru", "users"), None);
+        assert_eq!(fuzzy_score("", "users"), Some(0));
+        // Consecutive chars beat scattered ones
+        assert!(fuzzy_score("ord", "orders") > fuzzy_score("ord", "old_records"));
+    }
+
+    #[test]
+    fn test_filter_indices() {
+        assert_eq!(filter_indices(&items(), ""), [0, 1, 2, 3]);
+        assert_eq!(filter_indices(&items(), "oi"), [3]);
+        // A better match comes first even when it's later in the list
+        let tables = ["old_records", "orders"].map(String::from);
+        assert_eq!(filter_indices(&tables, "ord"), [1, 0]);
+        assert!(filter_indices(&items(), "xyz").is_empty());
+    }
+
+    #[test]
+    fn test_step_maps_back_to_real_indices() {
+        let visible = [3, 1];
+        assert_eq!(step(&visible, Some(3), true), Some(1));
+        assert_eq!(step(&visible, Some(1), true), Some(3));
+        assert_eq!(step(&visible, Some(3), false), Some(1));
+        assert_eq!(step(&visible, Some(0), true), Some(3));
+        assert_eq!(step(&[], Some(0), true), None);
+    }
+}
@@ -0,0 +1,305 @@
+//! Syntax highlighting for the query input.
+//!
+//! The lexer only needs to be good enough to colour the input: it works on chars so the
+//! cursor can be placed by char index, and an unterminated string or comment simply runs
+//! to the end of the input, which makes it easy to spot.
+
+use super::theme::Theme;
+use ratatui::style::Style;
+use ratatui::text::{Line, Span};
+
+// Separated by whitespace
+const KEYWORDS: &str = "\
+    ADD ALL ALTER AND ANY AS ASC BEGIN BETWEEN BY CASCADE CASE CAST CHECK COLUMN COMMIT \
+    CONSTRAINT CREATE CROSS DEFAULT DELETE DESC DISTINCT DROP ELSE END EXCEPT EXISTS EXPLAIN \
+    FALSE FETCH FOR FOREIGN FROM FULL GRANT GROUP HAVING ILIKE IN INDEX INNER INSERT \
+    INTERSECT INTO IS JOIN KEY LATERAL LEFT LIKE LIMIT NOT NULL OFFSET ON OR ORDER OUTER \
+    OVER PARTITION PRIMARY REFERENCES RETURNING RIGHT ROLLBACK SELECT SET SHOW TABLE THEN \
+    TRUE TRUNCATE UNION UNIQUE UPDATE USING VALUES VIEW WHEN WHERE WINDOW WITH";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TokenKind {
+    Keyword,
+    String, // Including dollar-quoted strings
+    Number,
+    Comment,
+    Other, // Identifiers, quoted identifiers, operators and whitespace
+}
+
+impl TokenKind {
+    fn style(self, theme: &Theme) -> Style {
+        match self {
+            TokenKind::Keyword => theme.keyword,
+            TokenKind::String => theme.string,
+            TokenKind::Number => theme.number,
+            TokenKind::Comment => theme.comment,
+            TokenKind::Other => theme.accent,
+        }
+    }
+}
+
+/// Split `input` into tokens, as (kind, text) pairs that together make up the input.
+pub fn tokenize(input: &str) -> Vec<(TokenKind, String)> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        let start = i;
+        let kind = if c == '-' && next == Some('-') {
+            while i < chars.len() && chars[i] != '\n' {
+                i += 1;
+            }
+            TokenKind::Comment
+        } else if c == '/' && next == Some('*') {
+            i = skip_block_comment(&chars, i);
+            TokenKind::Comment
+        } else if c == '\'' {
+            i = skip_string(&chars, i, false);
+            TokenKind::String
+        } else if (c == 'E' || c == 'e') && next == Some('\'') {
+            i = skip_string(&chars, i + 1, true);
+            TokenKind::String
+        } else if c == '"' {
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            TokenKind::Other
+        } else if let Some(tag_len) = dollar_tag_len(&chars, i) {
+            i = skip_dollar_quoted(&chars, i, tag_len);
+            TokenKind::String
+        } else if c.is_ascii_digit() {
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            TokenKind::Number
+        } else if c.is_alphabetic() || c == '_' {
+            while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '$')) {
+                i += 1;
+            }
+            let word: String = chars[start..i].iter().collect::<String>().to_uppercase();
+            if KEYWORDS.split_whitespace().any(|keyword| keyword == word) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Other
+            }
+        } else {
+            i += 1;
+            TokenKind::Other
+        };
+        tokens.push((kind, chars[start..i].iter().collect()));
+    }
+
+    tokens
+}
+
+/// Styled spans for `input`, with `cursor` drawn before the char at that index when given.
+pub fn highlighted_spans(input: &str, cursor: Option<usize>, theme: &Theme) -> Vec<Span<'static>> {
+    let cursor_span = || Span::styled("|", TokenKind::Other.style(theme));
+    let mut spans = Vec::new();
+    let mut offset = 0;
+
+    for (kind, text) in tokenize(input) {
+        let len = text.chars().count();
+        match cursor {
+            Some(cursor) if cursor >= offset && cursor < offset + len => {
+                let before: String = text.chars().take(cursor - offset).collect();
+                let after: String = text.chars().skip(cursor - offset).collect();
+                if !before.is_empty() {
+                    spans.push(Span::styled(before, kind.style(theme)));
+                }
+                spans.push(cursor_span());
+                spans.push(Span::styled(after, kind.style(theme)));
+            }
+            _ => spans.push(Span::styled(text, kind.style(theme))),
+        }
+        offset += len;
+    }
+    if cursor.is_some_and(|cursor| cursor >= offset) {
+        spans.push(cursor_span());
+    }
+
+    spans
+}
+
+/// `highlighted_spans` broken into lines at the input's line breaks.
+pub fn highlighted_lines(input: &str, cursor: Option<usize>, theme: &Theme) -> Vec<Line<'static>> {
+    let mut lines = vec![Line::default()];
+    for span in highlighted_spans(input, cursor, theme) {
+        let mut parts = span.content.split('\n');
+        if let Some(first) = parts.next()
+            && !first.is_empty()
+        {
+            lines
+                .last_mut()
+                .unwrap()
+                .push_span(Span::styled(first.to_string(), span.style));
+        }
+        for part in parts {
+            lines.push(Line::from(Span::styled(part.to_string(), span.style)));
+        }
+    }
+    lines
+}
+
+/// The index just past a string starting with the quote at `start`.
+fn skip_string(chars: &[char], start: usize, backslash_escapes: bool) -> usize {
+    let mut i = start + 1;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if backslash_escapes => i += 2,
+            '\'' if chars.get(i + 1) == Some(&'\'') => i += 2,
+            '\'' => return i + 1,
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+/// The index just past a (possibly nested) block comment starting at `start`.
+fn skip_block_comment(chars: &[char], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i < chars.len() {
+        match (chars[i], chars.get(i + 1)) {
+            ('/', Some('*')) => {
+                depth += 1;
+                i += 2;
+            }
+            ('*', Some('/')) => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    chars.len()
+}
+
+/// The length of the `$tag$` opening a dollar-quoted string at `start`, if there is one.
+/// Parameters like `$1` are not tags.
+fn dollar_tag_len(chars: &[char], start: usize) -> Option<usize> {
+    if chars[start] != '$' {
+        return None;
+    }
+    let mut i = start + 1;
+    if chars.get(i).is_some_and(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+        i += 1;
+    }
+    (chars.get(i) == Some(&'$')).then_some(i + 1 - start)
+}
+
+fn skip_dollar_quoted(chars: &[char], start: usize, tag_len: usize) -> usize {
+    let tag = &chars[start..start + tag_len];
+    let mut i = start + tag_len;
+    while i + tag_len <= chars.len() {
+        if &chars[i..i + tag_len] == tag {
+            return i + tag_len;
+        }
+        i += 1;
+    }
+    chars.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(input: &str) -> Vec<(TokenKind, &str)> {
+        // Whitespace is dropped to keep the expectations short
+        let tokens = tokenize(input);
+        let mut result = Vec::new();
+        let mut rest = input;
+        for (kind, text) in tokens {
+            let (token, tail) = rest.split_at(text.len());
+            rest = tail;
+            if !token.trim().is_empty() {
+                result.push((kind, token));
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            kinds("select name, 42 from users -- note"),
+            vec![
+                (TokenKind::Keyword, "select"),
+                (TokenKind::Other, "name"),
+                (TokenKind::Other, ","),
+                (TokenKind::Number, "42"),
+                (TokenKind::Keyword, "from"),
+                (TokenKind::Other, "users"),
+                (TokenKind::Comment, "-- note"),
+            ]
+        );
+        assert_eq!(
+            kinds("'it''s' \"from\" $1 $$ select $$ E'\\'x'"),
+            vec![
+                (TokenKind::String, "'it''s'"),
+                (TokenKind::Other, "\"from\""),
+                (TokenKind::Other, "$"),
+                (TokenKind::Number, "1"),
+                (TokenKind::String, "$$ select $$"),
+                (TokenKind::String, "E'\\'x'"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unterminated_string_runs_to_end() {
+        assert_eq!(
+            kinds("select 'abc from t"),
+            vec![
+                (TokenKind::Keyword, "select"),
+                (TokenKind::String, "'abc from t"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_highlighted_spans_place_cursor_by_char() {
+        let text = |spans: Vec<Span>| {
+            spans
+                .iter()
+                .map(|s| s.content.to_string())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            text(highlighted_spans("'héllo'", Some(3), &Theme::default())),
+            vec!["'hé", "|", "llo'"]
+        );
+        assert_eq!(
+            text(highlighted_spans("a é", Some(3), &Theme::default())),
+            vec!["a", " ", "é", "|"]
+        );
+        assert_eq!(
+            text(highlighted_spans("ab", Some(0), &Theme::default())),
+            vec!["|", "ab"]
+        );
+        assert_eq!(
+            text(highlighted_spans("ab", None, &Theme::default())),
+            vec!["ab"]
+        );
+    }
+
+    #[test]
+    fn test_highlighted_lines_split_at_line_breaks() {
+        let lines = highlighted_lines("select 'a\nb'\nfrom t", Some(19), &Theme::default());
+        let text: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        assert_eq!(text, vec!["select 'a", "b'", "from t|"]);
+        assert_eq!(lines[1].spans[0].style, Theme::default().string);
+    }
+}
@@ -0,0 +1,94 @@
+//! The overlay showing how a column's values are distributed: its commonest values, each
+//! with its count, share of the rows and a bar scaled to the commonest.
+
+use super::cells;
+use crate::db::ValueCounts;
+
+// How many of the commonest values are shown
+pub const HISTOGRAM_VALUES: i64 = 20;
+
+// Tables with more rows than this ask before being grouped, since it reads all of them
+pub const HISTOGRAM_CONFIRM_ROWS: i64 = 100_000;
+
+// The widest the value column gets before values are cut short
+const MAX_VALUE_WIDTH: usize = 30;
+
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    pub table: String,
+    pub column: String,
+    pub counts: ValueCounts,
+}
+
+impl Histogram {
+    pub fn title(&self) -> String {
+        let shown = self.counts.values.len() as i64;
+        format!(
+            " {}.{}: {} of {} distinct value(s) in {} row(s) ",
+            self.table, self.column, shown, self.counts.distinct, self.counts.total
+        )
+    }
+
+    /// One line per value, fitting `width` columns: the value, its count, its share of
+    /// the rows and a bar as long as the space left allows for the commonest value.
+    pub fn lines(&self, width: usize) -> Vec<String> {
+        let label = |value: &Option<String>| value.as_deref().unwrap_or("NULL").to_string();
+        let value_width = self
+            .counts
+            .values
+            .iter()
+            .map(|(value, _)| cells::display_width(&label(value)))
+            .max()
+            .unwrap_or(0)
+            .min(MAX_VALUE_WIDTH);
+        let count_width = self
+            .counts
+            .values
+            .first()
+            .map_or(1, |(_, count)| count.to_string().len());
+        let max = self.counts.values.first().map_or(1, |(_, count)| *count);
+        let bar_width = width.saturating_sub(value_width + count_width + 9);
+
+        self.counts
+            .values
+            .iter()
+            .map(|(value, count)| {
+                let share = *count as f64 * 100.0 / self.counts.total.max(1) as f64;
+                let bar = (*count as f64 / max.max(1) as f64 * bar_width as f64).round() as usize;
+                format!(
+                    "{} {:>count_width$} {:>5.1}% {}",
+                    cells::pad(&cells::ellipsize(&label(value), value_width), value_width),
+                    count,
+                    share,
+                    "█".repeat(bar.max(1)),
+                )
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lines() {
+        let histogram = Histogram {
+            table: "tasks".to_string(),
+            column: "status".to_string(),
+            counts: ValueCounts {
+                values: vec![(Some("done".to_string()), 30), (None, 10)],
+                distinct: 3,
+                total: 50,
+            },
+        };
+        assert_eq!(
+            histogram.title(),
+            " tasks.status: 2 of 3 distinct value(s) in 50 row(s) "
+        );
+        assert_eq!(
+            histogram.lines(30),
+            ["done 30  60.0% ███████████████", "NULL 10  20.0% █████"]
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! The form for inserting a row into the table being viewed, opened with 'a'.
+//!
+//! Each column gets a field. A field left empty takes the column's default, or NULL when
+//! it has none; `NULL` typed as a value stands for SQL NULL as in the parameter form.
+//! Identity and generated columns are filled in by the server, so they're listed as auto
+//! and can't be edited.
+
+use crate::db::describe::ColumnDescription;
+
+#[derive(Debug, Clone)]
+pub struct InsertForm {
+    pub table: String,
+    pub columns: Vec<ColumnDescription>,
+    pub values: Vec<String>, // As typed, one per column
+    pub selected: usize,     // Never an auto column, unless every column is
+}
+
+impl InsertForm {
+    pub fn new(table: String, columns: Vec<ColumnDescription>) -> InsertForm {
+        let values = vec![String::new(); columns.len()];
+        let mut form = InsertForm {
+            table,
+            columns,
+            values,
+            selected: 0,
+        };
+        if form.is_auto(0) {
+            form.next();
+        }
+        form
+    }
+
+    /// Whether the server fills in the column, so the form leaves it alone.
+    pub fn is_auto(&self, index: usize) -> bool {
+        self.columns
+            .get(index)
+            .is_some_and(|column| column.identity || column.generated)
+    }
+
+    /// The value being typed, or None when the selected column is auto.
+    pub fn input(&mut self) -> Option<&mut String> {
+        if self.is_auto(self.selected) {
+            return None;
+        }
+        self.values.get_mut(self.selected)
+    }
+
+    /// Move to the next editable column, returning false if this was the last.
+    pub fn next(&mut self) -> bool {
+        match (self.selected + 1..self.columns.len()).find(|&i| !self.is_auto(i)) {
+            Some(next) => {
+                self.selected = next;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if let Some(previous) = (0..self.selected).rev().find(|&i| !self.is_auto(i)) {
+            self.selected = previous;
+        }
+    }
+
+    /// A short note on what an empty field means for a column.
+    pub fn hint(&self, index: usize) -> String {
+        let column = &self.columns[index];
+        if self.is_auto(index) {
+            "auto".to_string()
+        } else if let Some(default) = &column.default {
+            format!("default {}", default)
+        } else if column.not_null {
+            "required".to_string()
+        } else {
+            "NULL if empty".to_string()
+        }
+    }
+
+    /// The columns to insert with their values, leaving out auto columns and empty fields
+    /// of columns with a default, or an error naming a required column left empty.
+    pub fn row(&self) -> Result<Vec<(String, Option<String>)>, String> {
+        let mut row = Vec::new();
+        for (i, (column, value)) in self.columns.iter().zip(&self.values).enumerate() {
+            if self.is_auto(i) || (value.is_empty() && column.default.is_some()) {
+                continue;
+            }
+            if value.is_empty() && column.not_null {
+                return Err(format!("{} is required", column.name));
+            }
+            let value = (!value.is_empty() && value != "NULL").then(|| value.clone());
+            row.push((column.name.clone(), value));
+        }
+        Ok(row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(
+        name: &str,
+        not_null: bool,
+        default: Option<&str>,
+        identity: bool,
+    ) -> ColumnDescription {
+        ColumnDescription {
+            name: name.to_string(),
+            data_type: "text".to_string(),
+            not_null,
+            default: default.map(str::to_string),
+            identity,
+            generated: false,
+        }
+    }
+
+    #[test]
+    fn test_insert_form_row() {
+        let mut form = InsertForm::new(
+            "people".to_string(),
+            vec![
+                column("id", true, None, true),
+                column("name", true, None, false),
+                column("created", true, Some("now()"), false),
+                column("note", false, None, false),
+            ],
+        );
+        // The identity column is skipped over
+        assert_eq!(form.selected, 1);
+        assert_eq!(form.hint(0), "auto");
+        assert_eq!(form.row(), Err("name is required".to_string()));
+
+        form.input().unwrap().push_str("Ada");
+        assert!(form.next());
+        assert_eq!(form.hint(form.selected), "default now()");
+        assert!(form.next());
+        assert!(!form.next());
+        assert_eq!(
+            form.row().unwrap(),
+            [
+                ("name".to_string(), Some("Ada".to_string())),
+                ("note".to_string(), None)
+            ]
+        );
+
+        form.input().unwrap().push_str("NULL");
+        form.previous();
+        form.input().unwrap().push_str("2024-01-01");
+        form.previous();
+        form.previous();
+        assert_eq!(form.selected, 1);
+        assert_eq!(
+            form.row().unwrap(),
+            [
+                ("name".to_string(), Some("Ada".to_string())),
+                ("created".to_string(), Some("2024-01-01".to_string())),
+                ("note".to_string(), None)
+            ]
+        );
+    }
+}
@@ -0,0 +1,101 @@
+//! Rows as a JSON array of objects, for copying with their types intact.
+//!
+//! Each row is an object on its own line, with its columns in order. Numbers, booleans
+//! and json columns are written as JSON values and NULL as `null`; everything else, and
+//! a value JSON has no way to write such as `NaN`, is a string.
+
+use crate::db::typed_rows::{TypedRows, ValueKind};
+use std::str::FromStr;
+
+pub fn json(rows: &TypedRows) -> String {
+    let objects: Vec<String> = rows
+        .rows
+        .iter()
+        .map(|row| {
+            let fields: Vec<String> = rows
+                .columns
+                .iter()
+                .zip(row)
+                .map(|((name, kind), value)| {
+                    format!("{}: {}", string(name), json_value(*kind, value.as_deref()))
+                })
+                .collect();
+            format!("  {{{}}}", fields.join(", "))
+        })
+        .collect();
+    if objects.is_empty() {
+        return "[]\n".to_string();
+    }
+    format!("[\n{}\n]\n", objects.join(",\n"))
+}
+
+fn json_value(kind: ValueKind, value: Option<&str>) -> String {
+    let Some(value) = value else {
+        return "null".to_string();
+    };
+    match kind {
+        // The server's text is kept, so a numeric's digits aren't rounded through a float
+        ValueKind::Number if serde_json::Number::from_str(value).is_ok() => value.to_string(),
+        ValueKind::Boolean if matches!(value, "t" | "true") => "true".to_string(),
+        ValueKind::Boolean if matches!(value, "f" | "false") => "false".to_string(),
+        ValueKind::Json => match serde_json::from_str::<serde_json::Value>(value) {
+            Ok(parsed) => parsed.to_string(),
+            Err(_) => string(value),
+        },
+        _ => string(value),
+    }
+}
+
+fn string(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_keeps_types() {
+        let rows = TypedRows {
+            columns: vec![
+                ("id".to_string(), ValueKind::Number),
+                ("price".to_string(), ValueKind::Number),
+                ("active".to_string(), ValueKind::Boolean),
+                ("tags".to_string(), ValueKind::Json),
+                ("note".to_string(), ValueKind::Text),
+            ],
+            rows: vec![
+                vec![
+                    Some("42".to_string()),
+                    Some("19.90".to_string()),
+                    Some("true".to_string()),
+                    Some("[\"a\", 1]".to_string()),
+                    Some("NULL".to_string()),
+                ],
+                vec![
+                    Some("7".to_string()),
+                    Some("NaN".to_string()),
+                    Some("f".to_string()),
+                    None,
+                    None,
+                ],
+            ],
+        };
+        assert_eq!(
+            json(&rows),
+            concat!(
+                "[\n",
+                "  {\"id\": 42, \"price\": 19.90, \"active\": true, \"tags\": [\"a\",1], \"note\": \"NULL\"},\n",
+                "  {\"id\": 7, \"price\": \"NaN\", \"active\": false, \"tags\": null, \"note\": null}\n",
+                "]\n"
+            )
+        );
+        assert_eq!(
+            json(&TypedRows {
+                columns: Vec::new(),
+                rows: Vec::new()
+            }),
+            "[]\n"
+        );
+    }
+}
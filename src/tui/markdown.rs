@@ -0,0 +1,115 @@
+//! Rows as a GitHub-flavored Markdown table, for pasting into tickets and docs.
+//!
+//! Takes the same rows as the CSV and TSV writers. Cells are padded so the columns line
+//! up in the source too, and numeric columns are right-aligned. NULL is an empty cell,
+//! as in the other formats.
+
+use super::cells;
+use crate::db::typed_rows::{TypedRows, ValueKind};
+
+pub fn table(rows: &TypedRows) -> String {
+    let headers = &rows.columns;
+    let header_cells: Vec<String> = headers.iter().map(|(name, _)| cell(name)).collect();
+    let body: Vec<Vec<String>> = rows
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| value.as_deref().map(cell).unwrap_or_default())
+                .collect()
+        })
+        .collect();
+
+    // The separator row's dashes need at least three columns
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|i| {
+            body.iter()
+                .filter_map(|row| row.get(i))
+                .chain([&header_cells[i]])
+                .map(|cell| cells::display_width(cell))
+                .max()
+                .unwrap_or(0)
+                .max(3)
+        })
+        .collect();
+    let numeric: Vec<bool> = headers
+        .iter()
+        .map(|(_, kind)| *kind == ValueKind::Number)
+        .collect();
+
+    let line = |cells: Vec<String>| format!("| {} |\n", cells.join(" | "));
+    let pad = |i: usize, value: &str| {
+        let padding = " ".repeat(widths[i].saturating_sub(cells::display_width(value)));
+        if numeric[i] {
+            format!("{}{}", padding, value)
+        } else {
+            format!("{}{}", value, padding)
+        }
+    };
+
+    let mut text = line(
+        header_cells
+            .iter()
+            .enumerate()
+            .map(|(i, h)| pad(i, h))
+            .collect(),
+    );
+    text.push_str(&line(
+        widths
+            .iter()
+            .zip(&numeric)
+            .map(|(&width, &numeric)| match numeric {
+                true => format!("{}:", "-".repeat(width - 1)),
+                false => "-".repeat(width),
+            })
+            .collect(),
+    ));
+    for row in &body {
+        text.push_str(&line(
+            row.iter().enumerate().map(|(i, v)| pad(i, v)).collect(),
+        ));
+    }
+    text
+}
+
+// A value made safe for a table cell: pipes escaped and line breaks as <br>, since a
+// cell can't span lines
+fn cell(value: &str) -> String {
+    value
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\r', '\n'], "<br>")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table() {
+        let value = |v: &str| Some(v.to_string());
+        let rows = TypedRows {
+            columns: vec![
+                ("id".to_string(), ValueKind::Number),
+                ("note".to_string(), ValueKind::Text),
+            ],
+            rows: vec![
+                vec![value("1"), value("a|b")],
+                vec![value("10"), value("two\nlines")],
+                vec![None, value("NULL")],
+                vec![value("NaN"), None],
+            ],
+        };
+        assert_eq!(
+            table(&rows),
+            concat!(
+                "|  id | note         |\n",
+                "| --: | ------------ |\n",
+                "|   1 | a\\|b         |\n",
+                "|  10 | two<br>lines |\n",
+                "|     | NULL         |\n",
+                "| NaN |              |\n",
+            )
+        );
+    }
+}
@@ -1,15 +1,154 @@
-use crate::db::DatabaseConnection;
+use crate::config::session::SessionState;
+use crate::config::{ConnectionInfo, ConnectionOrder, RowCountMode, SavedQuery};
+use crate::db::activity::{self, ActivityFilter};
+use crate::db::table_stats::{self, StatsColumn};
+use crate::db::typed_rows::{TypedRows, ValueKind};
+use crate::db::{self, Database, DatabaseConnection, KeysetColumn, PageCursor, QueryPage, sql};
 use anyhow::Result;
-use crossterm::event::{self, Event, KeyCode};
+use cell_edit::CellEdit;
+use column_picker::ColumnPicker;
+use completion::Completion;
+use crossterm::event::{
+    self, Event, KeyCode, KeyModifiers, MouseButton, MouseEvent, MouseEventKind,
+};
+use histogram::{HISTOGRAM_CONFIRM_ROWS, HISTOGRAM_VALUES, Histogram};
+use insert_form::InsertForm;
+use mouse::{ClickTargets, ClickTracker};
+use object_tree::{Load, ObjectTree};
+use page_cache::{CachedPage, PageCache};
+use params::ParamPrompt;
+use query_cache::{CachedResult, QueryCache};
 use ratatui::{
     Frame, Terminal,
     backend::Backend,
     layout::{Constraint, Direction, Layout},
-    style::{Color, Modifier, Style},
-    text::{Span, Text},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Row, Table, TableState},
+    style::Style,
+    text::{Line, Span, Text},
+    widgets::{
+        Block, Borders, Clear, List, ListItem, ListState, Paragraph, Row, Table, TableState, Wrap,
+    },
 };
+use search::TextSearch;
+use std::collections::{HashMap, HashSet};
 use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use theme::Theme;
+use timestamps::TimestampDisplay;
+use timing::QueryTiming;
+
+mod arrays;
+mod booleans;
+mod cell_edit;
+mod cells;
+mod clipboard;
+mod column_picker;
+mod completion;
+mod composites;
+pub mod delimited;
+mod editor;
+mod error_log;
+mod external_editor;
+mod filter;
+mod highlight;
+mod histogram;
+mod insert_form;
+pub mod json_rows;
+pub mod markdown;
+mod mouse;
+mod object_tree;
+mod page_cache;
+mod params;
+mod query_cache;
+mod query_file;
+mod search;
+pub mod theme;
+mod timestamps;
+mod timing;
+
+// How long the UI can sit idle before the connection is pinged
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(60);
+
+// How often the UI checks, while idle, whether the server dropped the connection
+const CONNECTION_CHECK_INTERVAL: Duration = Duration::from_secs(2);
+
+// How often the connecting screen's spinner turns
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+// How often the query input is redrawn so its cursor blinks
+const CURSOR_BLINK_INTERVAL: Duration = Duration::from_millis(500);
+
+// In `RowCountMode::Auto`, tables estimated to hold at least this many rows aren't counted exactly
+const EXACT_COUNT_LIMIT: i64 = 1_000_000;
+
+// How often the activity view reloads pg_stat_activity
+const ACTIVITY_REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+
+// Tables kept in the recent tables switcher, each selectable with its digit
+const RECENT_TABLES_LIMIT: usize = 9;
+
+// How much `<` and `>` narrow or widen a column, and the narrowest and widest it can be
+const COLUMN_RESIZE_STEP: i32 = 2;
+const MIN_COLUMN_WIDTH: i32 = 3;
+const MAX_COLUMN_WIDTH: i32 = 200;
+
+// The widest an automatically sized column gets, and the narrowest it's squeezed to when
+// the columns don't all fit
+const MAX_AUTO_COLUMN_WIDTH: usize = 40;
+const MIN_AUTO_COLUMN_WIDTH: u16 = 8;
+
+/// What happened to one statement of a multi-statement batch.
+#[derive(Debug, PartialEq, Clone)]
+pub enum StatementOutcome {
+    Rows(u64),     // Ran; number of rows affected or returned
+    Error(String), // Failed; the rest of the batch was not run
+    NotRun,        // Skipped because an earlier statement failed
+    Results,       // The final SELECT, shown in the results table
+}
+
+/// The text formats rows are copied to the clipboard in.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum CopyFormat {
+    Tsv,
+    Csv,
+    Json,
+    Markdown,
+}
+
+impl CopyFormat {
+    fn render(self, rows: &TypedRows) -> String {
+        match self {
+            CopyFormat::Tsv => delimited::tsv(rows),
+            CopyFormat::Csv => delimited::csv(rows),
+            CopyFormat::Json => json_rows::json(rows),
+            CopyFormat::Markdown => markdown::table(rows),
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            CopyFormat::Tsv => "TSV",
+            CopyFormat::Csv => "CSV",
+            CopyFormat::Json => "JSON",
+            CopyFormat::Markdown => "a Markdown table",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct StatementSummary {
+    pub statement: String,
+    pub outcome: StatementOutcome,
+}
+
+/// The key values at either end of the loaded table page, used to seek to its neighbours.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PageBounds {
+    pub table: String,
+    pub page: u32,
+    pub first_key: String,
+    pub last_key: String,
+}
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum AppState {
@@ -19,6 +158,12 @@ pub enum AppState {
     FieldDetail, // New state for detailed field view
     CustomQuery,
     CustomQueryInput,
+    TransactionPending, // A mutating query ran inside a transaction awaiting commit or rollback
+    TableDescription,   // Columns, constraints, indexes and DDL of the selected table
+    RecordView,         // The selected row with one column per line, like psql's \x
+    Activity,           // The server's backends from pg_stat_activity, refreshing
+    ObjectTree,         // Schemas, their relations and the relations' columns
+    TableStats,         // Scan, row and autovacuum counts per table from pg_stat_user_tables
     Connecting,
     ConnectionError,
 }
@@ -35,28 +180,114 @@ pub struct App {
     pub current_table: Option<String>,
     pub table_columns: Vec<String>,
     pub table_data: Vec<Vec<String>>,
+    pub table_content_widths: Vec<u16>, // How wide each column of the page wants to be
+    pub marked_rows: HashSet<usize>,    // Rows of the page picked with Space or 'v' for copying
+    pub mark_anchor: Option<usize>,     // Where a range marked with 'v' starts
     pub current_page: u32,
     pub max_page: u32,
+    pub total_count: i64, // Total rows in the current table, as fetched with the page
+    pub total_count_estimated: bool, // Whether total_count is the planner's estimate
+    pub table_timing: QueryTiming, // How long the table page and its count took
+    pub table_page_sql: Option<String>, // The statement that fetched the table page
+    pub exact_count_tables: HashSet<String>, // Tables the user asked to count exactly this session
+    pub keyset_columns: HashMap<String, Option<KeysetColumn>>, // Primary keys usable for paging, by table
+    pub page_bounds: Option<PageBounds>,
+    pub page_cache: PageCache,      // Recently viewed pages and row counts
+    pub query_cache: QueryCache,    // Recent custom query results, when enabled
+    pub row_prompt: Option<String>, // Row number being typed after 'g' in the table view
+    pub column_picker: Option<ColumnPicker>, // Open while choosing the table view's columns
+    pub histogram: Option<Histogram>, // Open while showing the selected column's commonest values
+    pub histogram_confirm: Option<(String, String)>, // Table and column of a big table to group, awaiting 'y'
+    pub insert_form: Option<InsertForm>,             // Open while entering a row to insert
+    pub cell_edit: Option<CellEdit>,                 // Open while editing the selected cell's value
+    pub object_tree: Option<ObjectTree>, // Kept while tables are opened from the tree, to return to
+    pub timestamp_display: TimestampDisplay, // How timestamp columns are shown in the table view
+    pub bool_display: crate::config::BoolDisplay, // Likewise boolean columns, from the settings
+    pub recent_tables: HashMap<String, Vec<String>>, // Most recently opened first, by connection
+    pub table_switcher: Option<ListState>, // Open while choosing from the recent tables
+    pub list_filter: Option<String>,     // Typed after '/' to narrow the connection or table list
+    pub filtered_list_state: ListState,  // Scrolling of the list while it's filtered
+    // Describe view
+    pub description_lines: Vec<String>,
+    pub description_scroll: u16,
     pub items_per_page: u32,
     pub error_message: Option<String>,
     pub connection_status: Option<String>,
     // Custom query fields
     pub custom_query_input: String,
     pub custom_query_cursor_position: usize,
+    pub custom_query_hscroll: usize, // Columns the input is scrolled right to keep a long line's cursor in view
     pub custom_query_result_columns: Vec<String>,
     pub custom_query_result_data: Vec<Vec<String>>,
+    pub custom_query_content_widths: Vec<u16>, // How wide each result column wants to be
     pub custom_query_current_page: u32,
     pub custom_query_max_page: u32,
+    pub custom_query_total_count: Option<i64>, // None when the rows couldn't be counted
+    pub custom_query_count_capped: bool, // Whether that's because there are over max_count_rows
+    pub max_count_rows: Option<u64>,     // The current connection's limit on counting query rows
+    pub custom_query_statement: String,  // The statement whose results are paged through
+    pub custom_query_params: Vec<Option<String>>, // Values bound to the statement's $n placeholders
+    pub param_prompt: Option<ParamPrompt>, // Open while asking for those values
+    pub destructive_confirm: Option<String>, // What's typed while confirming a destructive query
+    pub custom_query_batch: Vec<StatementSummary>, // Per-statement results when several were run
+    pub custom_query_rows_affected: Option<u64>, // Set when a single non-SELECT statement was run
+    pub custom_query_timing: QueryTiming, // How long the results page or statement took
+    pub custom_query_page_sql: Option<String>, // The statement that fetched the results page
+    pub show_sql: bool, // Whether the statement behind the table or results is shown below them
+    pub transactional: bool, // Run mutating queries inside a transaction that must be committed
+    pub completion: Option<Completion>, // Set while Tab is cycling through completions
+    pub saved_query_name: Option<String>, // Name being typed after Ctrl+S to save the query
+    pub query_file_path: Option<String>, // Path being typed after Ctrl+O to load SQL from a file
+    pub query_file_error: Option<String>, // Why that file couldn't be loaded, shown until the next key
+    pub saved_query_picker: Option<ListState>, // Open while choosing a saved query to load
+    pub column_names: HashMap<String, Vec<String>>, // Columns offered for completion, fetched per table on first use
+    pub user_types: HashMap<String, Option<db::UserType>>, // Column types looked up so far, None if not an enum or composite
     // Field detail view
     pub selected_field_value: Option<String>, // Store the value for detailed view
     pub field_detail_scroll: u16,             // Track scroll position for long field values
+    pub field_detail_max_scroll: u16, // How far the value can scroll, set when it's rendered
     pub field_detail_origin_state: Option<AppState>, // Track the original state when entering field detail view
+    pub detail_search: Option<TextSearch>, // Set once '/' is pressed in the field detail view
+    // Record view
+    pub record_scroll: u16,
+    pub record_origin_state: Option<AppState>, // The grid to return to
+    // Activity view
+    pub backends: Vec<activity::Backend>,
+    pub activity_state: TableState,
+    pub activity_confirm: Option<(i32, bool)>, // Backend pid, and whether to terminate rather than cancel, awaiting 'y'
+    pub activity_filter: activity::ActivityFilter, // Which backends the activity view lists
+    // Table stats view
+    pub table_stats: Vec<table_stats::TableStats>,
+    pub table_stats_state: TableState,
+    pub stats_sort: StatsColumn, // The column the table stats are sorted by
+    pub stats_sort_reversed: bool,
+    pub stats_reset: Option<String>, // When the database's statistics were last reset
+    pub maintenance_confirm: Option<String>, // Table to vacuum or analyze, awaiting 'y' or 'a'
+    // Connection details that did not come from the config (e.g. DATABASE_URL)
+    pub direct_connection: Option<ConnectionInfo>,
+    pub status_message: Option<String>, // Transient feedback shown in the status bar until the next key press
+    pub connection_name: Option<String>, // Name of the connection currently in use
+    pub last_connection_attempt: Option<String>, // Named in errors.log, even when connecting failed
+    pub session: SessionState,          // Remembered positions from previous runs
+    pub theme: Theme,
+    pub click_targets: ClickTargets, // Where the current view's rows were drawn
+    pub cell_hint: Option<String>,   // Set while drawing when the selected cell is cut short
+    pub clicks: ClickTracker,
 }
 
 impl App {
-    #[allow(dead_code)]
     pub fn new() -> Result<App> {
-        let config = crate::config::Config::load()?;
+        App::new_in(&crate::config::Config::get_config_dir())
+    }
+
+    /// Create an app whose config and session state are kept in `dir`.
+    pub fn new_in(dir: &Path) -> Result<App> {
+        let config = crate::config::Config::load_from(dir)?;
+        config.unlock()?;
+        let theme = Theme::preset(config.settings.theme);
+        let bool_display = config.settings.bool_display;
+        let max_count_rows = config.settings.max_count_rows;
+        let query_cache = QueryCache::new(config.settings.query_cache_ttl.map(Duration::from_secs));
 
         Ok(App {
             state: AppState::ConnectionSelection,
@@ -70,67 +301,121 @@ impl App {
             current_table: None,
             table_columns: Vec::new(),
             table_data: Vec::new(),
+            marked_rows: HashSet::new(),
+            mark_anchor: None,
+            table_content_widths: Vec::new(),
             current_page: 0,
             max_page: 0,
+            total_count: 0,
+            total_count_estimated: false,
+            table_timing: QueryTiming::default(),
+            table_page_sql: None,
+            exact_count_tables: HashSet::new(),
+            keyset_columns: HashMap::new(),
+            page_bounds: None,
+            page_cache: PageCache::default(),
+            query_cache,
+            row_prompt: None,
+            column_picker: None,
+            histogram: None,
+            histogram_confirm: None,
+            insert_form: None,
+            cell_edit: None,
+            object_tree: None,
+            timestamp_display: TimestampDisplay::default(),
+            bool_display,
+            recent_tables: HashMap::new(),
+            table_switcher: None,
+            list_filter: None,
+            filtered_list_state: ListState::default(),
+            description_lines: Vec::new(),
+            description_scroll: 0,
             items_per_page: 20,
             error_message: None,
             connection_status: None,
             // Custom query fields
             custom_query_input: String::new(),
             custom_query_cursor_position: 0,
+            custom_query_hscroll: 0,
             custom_query_result_columns: Vec::new(),
             custom_query_result_data: Vec::new(),
+            custom_query_content_widths: Vec::new(),
             custom_query_current_page: 0,
             custom_query_max_page: 0,
+            custom_query_total_count: Some(0),
+            custom_query_count_capped: false,
+            max_count_rows,
+            custom_query_statement: String::new(),
+            custom_query_params: Vec::new(),
+            param_prompt: None,
+            saved_query_name: None,
+            query_file_path: None,
+            query_file_error: None,
+            saved_query_picker: None,
+            destructive_confirm: None,
+            custom_query_batch: Vec::new(),
+            custom_query_rows_affected: None,
+            custom_query_timing: QueryTiming::default(),
+            custom_query_page_sql: None,
+            show_sql: false,
+            transactional: false,
+            completion: None,
+            column_names: HashMap::new(),
+            user_types: HashMap::new(),
             selected_field_value: None,
             field_detail_scroll: 0,
+            field_detail_max_scroll: 0,
             field_detail_origin_state: None,
+            detail_search: None,
+            record_scroll: 0,
+            record_origin_state: None,
+            backends: Vec::new(),
+            activity_state: TableState::default(),
+            activity_confirm: None,
+            activity_filter: activity::ActivityFilter::default(),
+            table_stats: Vec::new(),
+            table_stats_state: TableState::default(),
+            stats_sort: StatsColumn::SeqScan,
+            stats_sort_reversed: false,
+            stats_reset: None,
+            maintenance_confirm: None,
+            direct_connection: None,
+            status_message: None,
+            connection_name: None,
+            last_connection_attempt: None,
+            session: SessionState::load_from(dir),
+            theme,
+            click_targets: ClickTargets::default(),
+            cell_hint: None,
+            clicks: ClickTracker::default(),
         })
     }
 
     pub fn new_with_connection(connection_name: String) -> Result<App> {
-        let config = crate::config::Config::load()?;
+        Ok(App::new()?.connecting_to(connection_name))
+    }
 
-        let mut app = App {
-            state: AppState::Connecting,
-            config,
-            connection: None,
-            connections_list_state: ListState::default(),
-            tables_list_state: ListState::default(),
-            table_data_state: TableState::default(),
-            field_selection_state: None,
-            tables: Vec::new(),
-            current_table: None,
-            table_columns: Vec::new(),
-            table_data: Vec::new(),
-            current_page: 0,
-            max_page: 0,
-            items_per_page: 20,
-            error_message: None,
-            connection_status: Some(format!("Connecting to {}...", connection_name)),
-            // Custom query fields
-            custom_query_input: String::new(),
-            custom_query_cursor_position: 0,
-            custom_query_result_columns: Vec::new(),
-            custom_query_result_data: Vec::new(),
-            custom_query_current_page: 0,
-            custom_query_max_page: 0,
-            selected_field_value: None,
-            field_detail_scroll: 0,
-            field_detail_origin_state: None,
-        };
+    // The app in the connecting state for the connection named `connection_name`
+    fn connecting_to(mut self, connection_name: String) -> App {
+        self.state = AppState::Connecting;
+        self.connection_status = Some(format!("Connecting to {}...", connection_name));
 
         // Pre-select the connection by name if it exists
-        let connections = app.config.list_connections();
-        if let Some(index) = connections.iter().position(|conn| conn == &connection_name) {
-            app.connections_list_state.select(Some(index));
-        }
+        self.select_connection(Some(&connection_name));
 
+        self
+    }
+
+    /// Create an app that connects with the given details instead of a saved connection.
+    pub fn new_with_connection_info(info: ConnectionInfo) -> Result<App> {
+        let name = info.name.clone();
+        let mut app = App::new_with_connection(name)?;
+        app.direct_connection = Some(info);
         Ok(app)
     }
 
     pub fn init(&mut self) {
-        let connections = self.config.list_connections();
+        let connections = self.connection_names();
         if !connections.is_empty() {
             self.connections_list_state.select(Some(0));
         }
@@ -139,7 +424,7 @@ impl App {
     pub async fn connect_to_selected(&mut self) -> Result<()> {
         match self.connections_list_state.selected() {
             Some(index) => {
-                let connections = self.config.list_connections();
+                let connections = self.connection_names();
                 if index < connections.len() {
                     let conn_name = &connections[index];
                     self.connect_to_saved_connection(conn_name).await
@@ -153,425 +438,2782 @@ impl App {
 
     pub async fn connect_to_saved_connection(&mut self, name: &str) -> Result<()> {
         self.connection_status = Some(format!("Connecting to {}...", name));
+        self.last_connection_attempt = Some(name.to_string());
         self.state = AppState::Connecting;
 
-        match self.config.get_connection(name) {
-            Some(conn_info) => {
-                match self.config.decrypt_connection_password(&conn_info) {
-                    Ok(password) => {
-                        match DatabaseConnection::connect(
-                            &conn_info.host,
-                            conn_info.port,
-                            &conn_info.database,
-                            &conn_info.username,
-                            &password,
-                        )
-                        .await
-                        {
-                            Ok(connection) => {
-                                self.connection = Some(connection);
-                                self.connection_status = Some(format!("Connected to {}", name));
-
-                                // Load tables after connecting
-                                if let Err(e) = self.load_tables().await {
-                                    self.error_message =
-                                        Some(format!("Error loading tables: {}", e));
-                                    self.state = AppState::ConnectionError;
-                                } else {
-                                    self.state = AppState::TableList;
-                                }
-                            }
-                            Err(e) => {
-                                self.error_message = Some(format!("Connection error: {}", e));
-                                self.state = AppState::ConnectionError;
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        self.error_message = Some(format!("Error decrypting password: {}", e));
-                        self.state = AppState::ConnectionError;
+        let result = self.open_connection(name).await;
+        self.finish_connecting(name, result).await;
+        Ok(())
+    }
+
+    // Switch to the connection just opened, or show why opening it failed
+    async fn finish_connecting(
+        &mut self,
+        name: &str,
+        result: Result<(DatabaseConnection, Vec<String>)>,
+    ) {
+        match result {
+            Ok((connection, warnings)) => {
+                self.connection = Some(connection);
+                self.connection_name = Some(name.to_string());
+                self.connection_status = Some(format!("Connected to {}", name));
+                if self.config.has_connection(name) {
+                    // It moves to the top of a list sorted by recent use
+                    self.session.remember_connected(name);
+                    let _ = self.session.save();
+                    self.select_connection(Some(name));
+                }
+                self.max_count_rows = self
+                    .connection_info(name)
+                    .and_then(|info| info.max_count_rows)
+                    .or(self.config.settings.max_count_rows);
+
+                // Load tables after connecting
+                if let Err(e) = self.load_tables().await {
+                    self.error_message = Some(format!("Error loading tables: {:#}", e));
+                    self.state = AppState::ConnectionError;
+                } else {
+                    self.state = AppState::TableList;
+                    self.restore_last_view(name).await;
+                    if !warnings.is_empty() {
+                        self.status_message = Some(warnings.join("; "));
                     }
                 }
             }
-            None => {
-                self.error_message = Some("Connection not found".to_string());
+            Err(e) => {
+                self.error_message = Some(format!("{:#}", e));
                 self.state = AppState::ConnectionError;
             }
         }
+    }
 
-        Ok(())
+    // The saved connection of this name, or the one given by URL
+    fn connection_info(&self, name: &str) -> Option<ConnectionInfo> {
+        match &self.direct_connection {
+            Some(info) if info.name == name => Some(info.clone()),
+            _ => self.config.get_connection(name),
+        }
     }
 
-    pub async fn load_tables(&mut self) -> Result<()> {
-        if let Some(conn) = &self.connection {
-            self.tables = conn.list_tables().await?;
-            if !self.tables.is_empty() {
-                self.tables_list_state.select(Some(0));
+    // Look up a connection by name, open it and run its `on_connect` statements, returning
+    // the errors of any optional ones that failed
+    async fn open_connection(&self, name: &str) -> Result<(DatabaseConnection, Vec<String>)> {
+        self.connection_opener(name)?.await
+    }
+
+    // What `open_connection` does once the connection's details are looked up, as a
+    // future that doesn't borrow the app so it can run while the app is redrawn
+    fn connection_opener(
+        &self,
+        name: &str,
+    ) -> Result<impl Future<Output = Result<(DatabaseConnection, Vec<String>)>> + Send + 'static>
+    {
+        let conn_info = self
+            .connection_info(name)
+            .ok_or_else(|| anyhow::anyhow!("Connection not found"))?;
+
+        Ok(async move {
+            // A password command runs in here, so the spinner shows while it does
+            let password = conn_info.connect_password().await?;
+            let connection = DatabaseConnection::connect(
+                &conn_info.host,
+                conn_info.port,
+                &conn_info.database,
+                &conn_info.username,
+                &password,
+                &conn_info.options,
+            )
+            .await
+            .map_err(|e| anyhow::anyhow!("Connection error: {}", e))?;
+            let warnings = connection.run_on_connect(&conn_info.on_connect).await?;
+            Ok((connection, warnings))
+        })
+    }
+
+    /// Make sure the server hasn't dropped the connection, reconnecting once if it has.
+    ///
+    /// Returns false (with the app in `ConnectionError`) if the reconnect failed.
+    pub async fn check_connection(&mut self) -> bool {
+        let dropped = self
+            .connection
+            .as_ref()
+            .is_some_and(|c| c.is_closed() || c.lost_reason().is_some());
+        if !dropped || self.connection_name.is_none() {
+            return true;
+        }
+        self.reconnect().await
+    }
+
+    // Reopen the current connection after losing it, returning false (with the app in
+    // `ConnectionError`) if that failed
+    async fn reconnect(&mut self) -> bool {
+        let Some(name) = self.connection_name.clone() else {
+            return false;
+        };
+
+        // Why, if the connection broke rather than the server closing it cleanly
+        let reason = self.connection.as_ref().and_then(|c| c.lost_reason());
+        tracing::warn!(
+            "Connection '{}' was lost ({}); reconnecting",
+            name,
+            reason.as_deref().unwrap_or("closed")
+        );
+        match self.open_connection(&name).await {
+            Ok((connection, _)) => {
+                self.connection = Some(connection);
+                let lost = reason.as_deref().unwrap_or("Connection was lost");
+                if matches!(self.state, AppState::TransactionPending) {
+                    // The server discarded the open transaction along with the connection
+                    self.state = AppState::CustomQuery;
+                    self.status_message = Some(format!(
+                        "{}; the open transaction was rolled back; reconnected",
+                        lost
+                    ));
+                } else {
+                    self.status_message = Some(format!("{}; reconnected", lost));
+                }
+                true
+            }
+            Err(e) => {
+                self.connection = None;
+                self.error_message = Some(match reason {
+                    Some(reason) => format!("{}; reconnecting failed: {:#}", reason, e),
+                    None => format!("Connection lost: {:#}", e),
+                });
+                self.state = AppState::ConnectionError;
+                false
             }
         }
-        Ok(())
     }
 
-    pub async fn load_table_data(&mut self) -> Result<()> {
-        if let (Some(table), Some(conn)) = (&self.current_table, &self.connection) {
-            let offset = (self.current_page * self.items_per_page) as i64;
-            let limit = self.items_per_page as i64;
+    /// Ping the server so idle timeouts on the server or in between don't close the
+    /// connection, and notice if they already have.
+    pub async fn keepalive(&mut self) {
+        if let Some(conn) = &self.connection
+            && let Err(e) = conn.ping().await
+            && e.is_connection_lost()
+        {
+            self.reconnect().await;
+        }
+    }
+
+    // Reopen the table and page last viewed on this connection, if it still exists
+    async fn restore_last_view(&mut self, name: &str) {
+        let Some(view) = self.session.last_view(name).cloned() else {
+            return;
+        };
+        let Some(index) = self.tables.iter().position(|t| t == &view.table) else {
+            // The table was dropped or renamed since it was last viewed
+            self.session.forget_view(name);
+            let _ = self.session.save();
+            self.status_message = Some(format!(
+                "Previously viewed table '{}' no longer exists",
+                view.table
+            ));
+            return;
+        };
 
-            let (columns, data) = conn.get_table_data(table, offset, limit).await?;
+        self.tables_list_state.select(Some(index));
+        self.remember_recent_table(&view.table);
+        self.current_table = Some(view.table);
+        self.current_page = view.page;
+        self.state = AppState::TableData;
+
+        let mut result = self.load_table_data().await;
+        // The table may have shrunk since the page was remembered
+        if result.is_ok() && self.current_page > 0 && self.current_page >= self.max_page {
+            self.current_page = 0;
+            result = self.load_table_data().await;
+        }
+        if let Err(e) = result {
+            self.error_message = Some(format!("Error loading table data: {:#}", e));
+            self.state = AppState::ConnectionError;
+        }
+    }
 
-            self.table_columns = columns;
-            self.table_data = data;
+    /// Show the data of the table selected in the table list.
+    pub async fn open_selected_table(&mut self) {
+        if let Some(index) = self.tables_list_state.selected()
+            && index < self.tables.len()
+        {
+            self.list_filter = None;
+            self.open_table(&self.tables[index].clone()).await;
+        }
+    }
 
-            // Calculate max page based on table count
-            let total_count = conn.get_table_count(table).await?;
-            self.max_page = ((total_count as f64) / (self.items_per_page as f64)).ceil() as u32;
+    async fn open_table(&mut self, table: &str) {
+        self.current_table = Some(table.to_string());
+        self.remember_recent_table(table);
+        // Reset pagination when loading a new table, and show it fresh unless it was
+        // fetched within the cache's time to live
+        self.current_page = 0;
+        match self.query_cache.ttl() {
+            Some(ttl) => self.page_cache.expire_table(table, ttl),
+            None => self.page_cache.invalidate_table(table),
+        }
+        self.state = AppState::TableData;
 
-            if !self.table_data.is_empty() {
-                self.table_data_state.select(Some(0));
-            }
+        // Load data for the selected table
+        if let Err(e) = self.load_table_data().await {
+            self.error_message = Some(format!("Error loading table data: {:#}", e));
+            self.state = AppState::ConnectionError;
+        }
+    }
+
+    /// Open the object tree with the connection's schemas, all collapsed.
+    pub async fn open_object_tree(&mut self) -> Result<()> {
+        if let Some(conn) = &self.connection {
+            self.object_tree = Some(ObjectTree::new(conn.list_schemas().await?));
+            self.state = AppState::ObjectTree;
         }
         Ok(())
     }
 
-    pub fn next_connection(&mut self) {
-        let i = match self.connections_list_state.selected() {
-            Some(i) => {
-                if i >= self.config.list_connections().len() - 1 {
-                    0
-                } else {
-                    i + 1
-                }
-            }
-            None => 0,
+    /// Expand the selected node of the object tree, loading its children first if this
+    /// is the first time.
+    pub async fn expand_tree_node(&mut self) -> Result<()> {
+        let (Some(tree), Some(conn)) = (self.object_tree.as_mut(), &self.connection) else {
+            return Ok(());
         };
-        self.connections_list_state.select(Some(i));
+        match tree.expand() {
+            Some(Load::Relations(schema)) => {
+                let relations = conn.list_relations(&schema).await?;
+                tree.set_relations(&schema, relations);
+            }
+            Some(Load::Columns(schema, relation)) => {
+                let columns = conn.list_relation_columns(&schema, &relation).await?;
+                tree.set_columns(&schema, &relation, columns);
+            }
+            None => return Ok(()),
+        }
+        tree.expand();
+        Ok(())
     }
 
-    pub fn previous_connection(&mut self) {
-        let i = match self.connections_list_state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.config.list_connections().len() - 1
-                } else {
-                    i - 1
-                }
-            }
-            None => 0,
+    /// Show the rows of the relation selected in the object tree, or expand the selected
+    /// schema. Relations in `public` open in the table view like tables from the table
+    /// list; the table view only reads that schema, so others are shown as the results
+    /// of a query selecting from them.
+    pub async fn open_tree_relation(&mut self) -> Result<()> {
+        let Some((schema, relation)) = self
+            .object_tree
+            .as_ref()
+            .and_then(|tree| tree.selected_relation())
+            .map(|(schema, relation)| (schema.to_string(), relation.name.clone()))
+        else {
+            return self.expand_tree_node().await;
         };
-        self.connections_list_state.select(Some(i));
+        self.open_relation(&schema, &relation).await
     }
 
-    pub fn next_table(&mut self) {
-        let i = match self.tables_list_state.selected() {
-            Some(i) => {
-                if i >= self.tables.len() - 1 {
-                    0
-                } else {
-                    i + 1
-                }
-            }
-            None => 0,
-        };
-        self.tables_list_state.select(Some(i));
+    /// Show a relation's rows: in the table view if it's in `public`, which the table
+    /// view browses, or else as a query selecting all of it. The query only passes
+    /// through the query input, which is left holding whatever was being written there.
+    async fn open_relation(&mut self, schema: &str, relation: &str) -> Result<()> {
+        if schema == "public" {
+            self.open_table(relation).await;
+            return Ok(());
+        }
+        let query = format!(
+            "SELECT * FROM {}.{}",
+            sql::quote_identifier(schema),
+            sql::quote_identifier(relation)
+        );
+        let input = std::mem::replace(&mut self.custom_query_input, query);
+        self.custom_query_current_page = 0;
+        let result = self.start_custom_query().await;
+        self.custom_query_input = input;
+        result
     }
 
-    pub fn previous_table(&mut self) {
-        let i = match self.tables_list_state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    self.tables.len() - 1
-                } else {
-                    i - 1
-                }
+    /// The statement that fetched the rows being viewed, for the SQL panel toggled with
+    /// '`', or None when the view has no rows from the server.
+    pub fn page_sql(&self) -> Option<&str> {
+        match self.state {
+            AppState::TableData => self.table_page_sql.as_deref(),
+            AppState::CustomQuery | AppState::TransactionPending => {
+                self.custom_query_page_sql.as_deref()
             }
-            None => 0,
-        };
-        self.tables_list_state.select(Some(i));
+            _ => None,
+        }
     }
 
-    pub fn next_row(&mut self) {
-        let data_len = if matches!(self.state, AppState::CustomQuery) {
-            self.custom_query_result_data.len()
+    /// The view that lists tables to go back to from a table: the object tree if the
+    /// table was opened from there, otherwise the table list.
+    fn table_list_state(&self) -> AppState {
+        match self.object_tree {
+            Some(_) => AppState::ObjectTree,
+            None => AppState::TableList,
+        }
+    }
+
+    fn remember_recent_table(&mut self, table: &str) {
+        let connection = self.connection_name.clone().unwrap_or_default();
+        push_recent(self.recent_tables.entry(connection).or_default(), table);
+    }
+
+    /// The recently opened tables of the current connection other than the one being
+    /// viewed, most recent first.
+    pub fn switchable_tables(&self) -> Vec<String> {
+        self.recent_tables
+            .get(self.connection_name.as_deref().unwrap_or_default())
+            .into_iter()
+            .flatten()
+            .filter(|table| Some(*table) != self.current_table.as_ref())
+            .cloned()
+            .collect()
+    }
+
+    /// The queries saved for the current connection.
+    pub fn saved_queries(&self) -> &[SavedQuery] {
+        match &self.connection_name {
+            Some(name) => self.config.saved_queries(name),
+            None => &[],
+        }
+    }
+
+    /// Start asking for a name to save the query input under.
+    pub fn start_saving_query(&mut self) {
+        if self.connection_name.is_none() {
+            self.status_message =
+                Some("Queries can only be saved for saved connections".to_string());
+        } else if self.custom_query_input.trim().is_empty() {
+            self.status_message = Some("Nothing to save".to_string());
         } else {
-            self.table_data.len()
-        };
+            self.saved_query_name = Some(String::new());
+        }
+    }
 
-        if data_len == 0 {
+    /// Save the query input under the name typed for it, replacing any saved query of
+    /// that name.
+    pub fn save_query(&mut self) {
+        let name = self.saved_query_name.take().unwrap_or_default();
+        let name = name.trim();
+        let Some(connection) = &self.connection_name else {
+            return;
+        };
+        if name.is_empty() {
             return;
         }
+        let replaced = self
+            .config
+            .save_query(connection, name, self.custom_query_input.trim());
+        self.status_message = Some(match self.config.save() {
+            Ok(()) if replaced => format!("Query '{}' replaced", name),
+            Ok(()) => format!("Query '{}' saved", name),
+            Err(e) => format!("Failed to save query: {}", e),
+        });
+    }
 
-        let i = match self.table_data_state.selected() {
-            Some(i) => {
-                if i >= data_len - 1 {
-                    0
-                } else {
-                    i + 1
-                }
+    /// Insert the SQL of the file whose path was typed at the cursor, or keep why it
+    /// couldn't be read to show under the input.
+    pub fn load_query_file(&mut self) {
+        let path = self.query_file_path.take().unwrap_or_default();
+        match query_file::read(&path) {
+            Ok(sql) => {
+                self.paste(&sql);
+                self.status_message = Some(format!("Loaded {}", path.trim()));
             }
-            None => 0,
+            Err(e) => self.query_file_error = Some(e.to_string()),
+        }
+    }
+
+    pub fn open_saved_query_picker(&mut self) {
+        if self.saved_queries().is_empty() {
+            self.status_message =
+                Some("No saved queries for this connection; save one with Ctrl+S".to_string());
+            return;
+        }
+        self.saved_query_picker = Some(ListState::default().with_selected(Some(0)));
+    }
+
+    /// Put the saved query at `index` in the query input, replacing what was there.
+    pub fn load_saved_query(&mut self, index: usize) {
+        self.saved_query_picker = None;
+        let Some(query) = self.saved_queries().get(index).cloned() else {
+            return;
         };
-        self.table_data_state.select(Some(i));
+        self.custom_query_cursor_position = query.sql.chars().count();
+        self.custom_query_input = query.sql;
+        self.completion = None;
+        self.state = AppState::CustomQueryInput;
     }
 
-    pub fn previous_row(&mut self) {
-        let data_len = if matches!(self.state, AppState::CustomQuery) {
-            self.custom_query_result_data.len()
-        } else {
-            self.table_data.len()
+    /// Forget the saved query at `index`, closing the picker once there are none left.
+    pub fn remove_saved_query(&mut self, index: usize) {
+        let (Some(connection), Some(query)) = (
+            self.connection_name.clone(),
+            self.saved_queries().get(index).cloned(),
+        ) else {
+            return;
         };
+        self.config.remove_saved_query(&connection, &query.name);
+        if let Err(e) = self.config.save() {
+            self.status_message = Some(format!("Failed to remove query: {}", e));
+            return;
+        }
+        self.status_message = Some(format!("Query '{}' removed", query.name));
+        let len = self.saved_queries().len();
+        self.saved_query_picker =
+            (len > 0).then(|| ListState::default().with_selected(Some(index.min(len - 1))));
+    }
 
-        if data_len == 0 {
+    /// Open the recent tables switcher with the previously viewed table selected.
+    pub fn open_table_switcher(&mut self) {
+        if self.switchable_tables().is_empty() {
+            self.status_message = Some("No other tables opened yet".to_string());
             return;
         }
+        let mut state = ListState::default();
+        state.select(Some(0));
+        self.table_switcher = Some(state);
+    }
 
-        let i = match self.table_data_state.selected() {
-            Some(i) => {
-                if i == 0 {
-                    data_len - 1
-                } else {
-                    i - 1
+    /// Open the table at `index` in the switcher's list.
+    pub async fn switch_to_recent_table(&mut self, index: usize) {
+        self.table_switcher = None;
+        let Some(table) = self.switchable_tables().into_iter().nth(index) else {
+            return;
+        };
+        match self.tables.iter().position(|t| *t == table) {
+            Some(position) => {
+                self.tables_list_state.select(Some(position));
+                self.field_selection_state = None;
+                self.open_selected_table().await;
+            }
+            None => self.status_message = Some(format!("Table '{}' no longer exists", table)),
+        }
+    }
+
+    /// Select what was clicked in the table list or a data table, open it on a
+    /// double-click, and move the selection with the scroll wheel.
+    pub async fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let in_table = matches!(self.state, AppState::TableData | AppState::CustomQuery);
+        match (mouse.kind, &self.state) {
+            (MouseEventKind::Down(MouseButton::Left), AppState::TableList) => {
+                let offset = match self.list_filter {
+                    Some(_) => self.filtered_list_state.offset(),
+                    None => self.tables_list_state.offset(),
+                };
+                let visible = self.visible_tables();
+                if let Some(&index) = self
+                    .click_targets
+                    .list_item_at(mouse.column, mouse.row, offset)
+                    .and_then(|position| visible.get(position))
+                {
+                    self.tables_list_state.select(Some(index));
+                    if self.clicks.click(mouse.column, mouse.row) {
+                        self.open_selected_table().await;
+                    }
                 }
             }
-            None => 0,
+            (MouseEventKind::Down(MouseButton::Left), _) if in_table => {
+                let offset = self.table_data_state.offset();
+                let row_count = self.current_rows().len();
+                if let Some(row) = self
+                    .click_targets
+                    .table_row_at(mouse.column, mouse.row, offset)
+                    .filter(|&row| row < row_count)
+                {
+                    self.table_data_state.select(Some(row));
+                    self.field_selection_state = self.click_targets.column_at(mouse.column);
+                    if self.clicks.click(mouse.column, mouse.row)
+                        && self.field_selection_state.is_some()
+                    {
+                        self.enter_field_detail_view();
+                    }
+                }
+            }
+            (MouseEventKind::ScrollDown, AppState::TableList) => self.next_table(),
+            (MouseEventKind::ScrollUp, AppState::TableList) => self.previous_table(),
+            (MouseEventKind::ScrollDown, _) if in_table => self.scroll_rows(true).await,
+            (MouseEventKind::ScrollUp, _) if in_table => self.scroll_rows(false).await,
+            _ => {}
+        }
+    }
+
+    // Move the row selection for the scroll wheel, turning the page at either end
+    async fn scroll_rows(&mut self, down: bool) {
+        let custom = matches!(self.state, AppState::CustomQuery);
+        let selected = self.table_data_state.selected().unwrap_or(0);
+        self.field_selection_state = None;
+
+        let result = if down && selected + 1 < self.current_rows().len() {
+            self.table_data_state.select(Some(selected + 1));
+            Ok(())
+        } else if !down && selected > 0 {
+            self.table_data_state.select(Some(selected - 1));
+            Ok(())
+        } else if down && custom {
+            self.next_custom_query_page().await
+        } else if down {
+            self.next_page().await
+        } else {
+            let page = self.current_page_number();
+            let result = if custom {
+                self.previous_custom_query_page().await
+            } else {
+                self.previous_page().await
+            };
+            // Scrolling up into the previous page lands on its last row
+            if self.current_page_number() < page {
+                let last = self.current_rows().len().checked_sub(1);
+                self.table_data_state.select(last);
+            }
+            result
         };
-        self.table_data_state.select(Some(i));
+        if let Err(e) = result {
+            self.error_message = Some(format!("Error loading data: {:#}", e));
+            self.state = AppState::ConnectionError;
+        }
     }
 
-    pub fn next_page(&mut self) {
-        if self.current_page < self.max_page - 1 {
-            self.current_page += 1;
-            self.table_data.clear(); // Clear to reload on next render
+    // The rows of the table or query results being viewed
+    fn current_rows(&self) -> &[Vec<String>] {
+        match self.state {
+            AppState::CustomQuery => &self.custom_query_result_data,
+            _ => &self.table_data,
         }
     }
 
-    pub fn previous_page(&mut self) {
-        if self.current_page > 0 {
-            self.current_page -= 1;
-            self.table_data.clear(); // Clear to reload on next render
+    fn current_page_number(&self) -> u32 {
+        match self.state {
+            AppState::CustomQuery => self.custom_query_current_page,
+            _ => self.current_page,
         }
     }
 
-    pub fn next_field(&mut self) {
-        // Check if we're in table data view
+    /// Load the describe view for the table selected in the table list.
+    pub async fn describe_selected_table(&mut self) -> Result<()> {
+        let Some(table) = self
+            .tables_list_state
+            .selected()
+            .and_then(|index| self.tables.get(index))
+        else {
+            return Ok(());
+        };
+        if let Some(conn) = &self.connection {
+            let description = conn.describe_table(table).await?;
+            self.description_lines = description.to_lines();
+            self.description_scroll = 0;
+            self.state = AppState::TableDescription;
+        }
+        Ok(())
+    }
+
+    pub fn scroll_description(&mut self, lines: i32) {
+        let max_scroll = self.description_lines.len().saturating_sub(1) as i32;
+        self.description_scroll =
+            (self.description_scroll as i32 + lines).clamp(0, max_scroll) as u16;
+    }
+
+    /// Insert pasted text into the query input, newlines included, without running it.
+    /// A parameter value is a single line, so there line breaks become spaces.
+    pub fn paste(&mut self, text: &str) {
         if matches!(self.state, AppState::TableData)
-            && let Some(selected_row_idx) = self.table_data_state.selected()
-            && selected_row_idx < self.table_data.len()
+            && let Some(input) = self.insert_form.as_mut().and_then(InsertForm::input)
         {
-            let num_fields = self.table_data[selected_row_idx].len();
-            if num_fields > 0 {
-                let next_field_idx = match self.field_selection_state {
-                    Some(current_idx) => {
-                        if current_idx >= num_fields - 1 {
-                            0 // Wrap to first field
-                        } else {
-                            current_idx + 1
-                        }
-                    }
+            input.push_str(&text.lines().collect::<Vec<_>>().join(" "));
+            return;
+        }
+        if matches!(self.state, AppState::TableData)
+            && let Some(edit) = self.cell_edit.as_mut().filter(|edit| !edit.confirming)
+        {
+            edit.push_str(&text.lines().collect::<Vec<_>>().join(" "));
+            return;
+        }
+        if !matches!(self.state, AppState::CustomQueryInput) || self.destructive_confirm.is_some() {
+            return;
+        }
+        if let Some(name) = self.saved_query_name.as_mut() {
+            name.push_str(&text.lines().collect::<Vec<_>>().join(" "));
+            return;
+        }
+        if let Some(path) = self.query_file_path.as_mut() {
+            path.push_str(&text.lines().collect::<Vec<_>>().join(" "));
+            return;
+        }
+        if let Some(prompt) = self.param_prompt.as_mut() {
+            prompt
+                .input()
+                .push_str(&text.lines().collect::<Vec<_>>().join(" "));
+            return;
+        }
+        self.completion = None;
+        self.custom_query_cursor_position = editor::insert_str(
+            &mut self.custom_query_input,
+            self.custom_query_cursor_position,
+            text,
+        );
+    }
+
+    pub async fn open_activity(&mut self) -> Result<()> {
+        self.activity_state = TableState::default();
+        self.activity_confirm = None;
+        self.refresh_activity().await?;
+        self.state = AppState::Activity;
+        Ok(())
+    }
+
+    /// Reload the backends, keeping the same one selected while it's still there.
+    pub async fn refresh_activity(&mut self) -> Result<()> {
+        let Some(conn) = &self.connection else {
+            return Ok(());
+        };
+        let selected_pid = self.selected_backend().map(|b| b.pid);
+        let idle_in_transaction = self.activity_filter == ActivityFilter::IdleInTransaction;
+        let backends = conn.list_backends(idle_in_transaction).await?;
+        self.backends = match self.activity_filter {
+            ActivityFilter::LockWaits => match conn.blocking_pids().await {
+                Ok(blocked_by) => activity::lock_waits(backends, &blocked_by),
+                // Without lock information, fall back to listing every backend
+                Err(e) => {
+                    self.activity_filter = ActivityFilter::All;
+                    self.status_message = Some(format!("Lock waits aren't available: {}", e));
+                    backends
+                }
+            },
+            _ => backends,
+        };
+        let index = selected_pid
+            .and_then(|pid| self.backends.iter().position(|b| b.pid == pid))
+            .or((!self.backends.is_empty()).then_some(0));
+        self.activity_state.select(index);
+        Ok(())
+    }
+
+    /// Switch the activity view between all backends and those `filter` picks out.
+    pub async fn toggle_activity_filter(&mut self, filter: ActivityFilter) -> Result<()> {
+        self.activity_filter = if self.activity_filter == filter {
+            ActivityFilter::All
+        } else {
+            filter
+        };
+        self.refresh_activity().await
+    }
+
+    pub fn selected_backend(&self) -> Option<&activity::Backend> {
+        self.activity_state
+            .selected()
+            .and_then(|index| self.backends.get(index))
+    }
+
+    pub fn move_activity_selection(&mut self, forward: bool) {
+        let len = self.backends.len();
+        if len == 0 {
+            return;
+        }
+        let index = match self.activity_state.selected() {
+            Some(i) if forward => (i + 1) % len,
+            Some(i) => (i + len - 1) % len,
+            None => 0,
+        };
+        self.activity_state.select(Some(index));
+    }
+
+    pub async fn open_table_stats(&mut self) -> Result<()> {
+        self.table_stats_state = TableState::default();
+        self.refresh_table_stats().await?;
+        self.state = AppState::TableStats;
+        Ok(())
+    }
+
+    /// Reload the table stats, keeping the same table selected.
+    pub async fn refresh_table_stats(&mut self) -> Result<()> {
+        let Some(conn) = &self.connection else {
+            return Ok(());
+        };
+        self.table_stats = conn.table_stats().await?;
+        self.stats_reset = conn.stats_reset().await?;
+        self.sort_table_stats();
+        Ok(())
+    }
+
+    /// Sort by the column `step` places from the current one, or with 0 flip the order.
+    pub fn change_stats_sort(&mut self, step: isize) {
+        if step == 0 {
+            self.stats_sort_reversed = !self.stats_sort_reversed;
+        } else {
+            self.stats_sort = self.stats_sort.step(step);
+            self.stats_sort_reversed = false;
+        }
+        self.sort_table_stats();
+    }
+
+    // Re-sort, following the selected table to its new row
+    fn sort_table_stats(&mut self) {
+        let selected = self
+            .selected_table_stats()
+            .map(|stats| (stats.schema.clone(), stats.name.clone()));
+        table_stats::sort(
+            &mut self.table_stats,
+            self.stats_sort,
+            self.stats_sort_reversed,
+        );
+        let index = selected
+            .and_then(|(schema, name)| {
+                self.table_stats
+                    .iter()
+                    .position(|s| s.schema == schema && s.name == name)
+            })
+            .or((!self.table_stats.is_empty()).then_some(0));
+        self.table_stats_state.select(index);
+    }
+
+    pub fn selected_table_stats(&self) -> Option<&table_stats::TableStats> {
+        self.table_stats_state
+            .selected()
+            .and_then(|index| self.table_stats.get(index))
+    }
+
+    pub fn move_table_stats_selection(&mut self, forward: bool) {
+        let len = self.table_stats.len();
+        if len == 0 {
+            return;
+        }
+        let index = match self.table_stats_state.selected() {
+            Some(i) if forward => (i + 1) % len,
+            Some(i) => (i + len - 1) % len,
+            None => 0,
+        };
+        self.table_stats_state.select(Some(index));
+    }
+
+    /// Browse the rows of the table selected in the table stats view.
+    pub async fn open_selected_stats_table(&mut self) -> Result<()> {
+        let Some((schema, name)) = self
+            .selected_table_stats()
+            .map(|stats| (stats.schema.clone(), stats.name.clone()))
+        else {
+            return Ok(());
+        };
+        self.open_relation(&schema, &name).await
+    }
+
+    /// Show the selected backend's whole query in the field detail view.
+    pub fn view_backend_query(&mut self) {
+        if let Some(backend) = self.selected_backend() {
+            self.selected_field_value = Some(backend.query.clone());
+            self.field_detail_origin_state = Some(AppState::Activity);
+            self.field_detail_scroll = 0;
+            self.detail_search = None;
+            self.state = AppState::FieldDetail;
+        }
+    }
+
+    /// Cancel the selected backend's query, or with `terminate` end its session, once
+    /// the user confirms in `activity_confirm`.
+    pub async fn signal_backend(&mut self, pid: i32, terminate: bool) {
+        let Some(conn) = &self.connection else {
+            return;
+        };
+        let action = if terminate { "terminate" } else { "cancel" };
+        self.status_message = Some(match conn.signal_backend(pid, terminate).await {
+            Ok(true) if terminate => format!("Terminated backend {}", pid),
+            Ok(true) => format!("Sent a cancel request to backend {}", pid),
+            Ok(false) => format!("Backend {} is no longer running", pid),
+            Err(db::DbError::PermissionDenied(_)) => format!(
+                "Not allowed to {} backend {}: only your own sessions can be signalled without pg_signal_backend",
+                action, pid
+            ),
+            Err(e) => e.to_string(),
+        });
+        if let Err(e) = self.refresh_activity().await {
+            self.status_message = Some(format!("Failed to refresh activity: {}", e));
+        }
+    }
+
+    /// Ask to vacuum or analyze the selected table of the table list, or the table being
+    /// viewed.
+    pub fn confirm_maintenance(&mut self) {
+        let table = match self.state {
+            AppState::TableData => self.current_table.clone(),
+            _ => self
+                .tables_list_state
+                .selected()
+                .and_then(|index| self.tables.get(index).cloned()),
+        };
+        if self.connection.as_ref().is_some_and(|c| c.in_transaction()) {
+            self.status_message = Some(
+                "VACUUM can't run inside a transaction; commit or roll back first".to_string(),
+            );
+            return;
+        }
+        self.maintenance_confirm = table;
+    }
+
+    /// Run a maintenance command on `table` once the user confirmed it.
+    pub async fn run_maintenance(&mut self, table: &str, kind: db::Maintenance) {
+        let Some(conn) = &self.connection else {
+            return;
+        };
+        let started = Instant::now();
+        self.status_message = Some(match conn.maintenance(table, kind).await {
+            Ok(()) => {
+                // The planner's row estimate may have changed with it
+                self.page_cache.forget_count(table);
+                format!(
+                    "{} {} done in {:.1}s",
+                    kind.command(),
+                    table,
+                    started.elapsed().as_secs_f64()
+                )
+            }
+            Err(e) => e.to_string(),
+        });
+    }
+
+    /// Complete the word before the cursor with a table name, or a column of a table
+    /// mentioned in the query. Pressing Tab again replaces it with the next candidate.
+    pub async fn complete_word(&mut self) -> Result<()> {
+        if let Some(completion) = &mut self.completion {
+            let end = completion.start + completion.selected().chars().count();
+            completion.next();
+            let (input, cursor) = completion::replace_chars(
+                &self.custom_query_input,
+                completion.start,
+                end,
+                completion.selected(),
+            );
+            self.custom_query_input = input;
+            self.custom_query_cursor_position = cursor;
+            return Ok(());
+        }
+
+        let words = completion::identifiers(&self.custom_query_input);
+        let mentioned: Vec<String> = self
+            .tables
+            .iter()
+            .filter(|table| words.contains(&table.to_lowercase()))
+            .cloned()
+            .collect();
+        if let Some(conn) = &self.connection {
+            for table in &mentioned {
+                if !self.column_names.contains_key(table) {
+                    let columns = conn.list_columns(table).await?;
+                    self.column_names.insert(table.clone(), columns);
+                }
+            }
+        }
+
+        let names = self.tables.iter().chain(
+            mentioned
+                .iter()
+                .filter_map(|table| self.column_names.get(table))
+                .flatten(),
+        );
+        let Some(completion) = Completion::new(
+            &self.custom_query_input,
+            self.custom_query_cursor_position,
+            names.map(String::as_str),
+        ) else {
+            return Ok(());
+        };
+        let (input, cursor) = completion::replace_chars(
+            &self.custom_query_input,
+            completion.start,
+            self.custom_query_cursor_position,
+            completion.selected(),
+        );
+        self.custom_query_input = input;
+        self.custom_query_cursor_position = cursor;
+        self.completion = Some(completion);
+        Ok(())
+    }
+
+    pub async fn load_tables(&mut self) -> Result<()> {
+        if let Some(conn) = &self.connection {
+            self.column_names.clear();
+            self.user_types.clear();
+            self.page_cache.clear();
+            self.query_cache.clear();
+            self.object_tree = None;
+            self.tables = conn.list_tables().await?;
+            if !self.tables.is_empty() {
+                self.tables_list_state.select(Some(0));
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn load_table_data(&mut self) -> Result<()> {
+        // Reading a page is safe to repeat, so losing the connection partway through
+        // only costs a reconnect
+        match self.fetch_table_data().await {
+            Err(e) if db::is_connection_lost(&e) && self.reconnect().await => {
+                self.fetch_table_data().await?
+            }
+            result => result?,
+        }
+        // Without the types the values are still shown, just as the server sends them
+        if let Err(e) = self.load_user_types().await {
+            tracing::warn!("Failed to look up column types: {}", e);
+        }
+        Ok(())
+    }
+
+    // Find out which of the page's column types are enums or composites, asking the
+    // server only about types not seen before
+    async fn load_user_types(&mut self) -> Result<()> {
+        let Some(conn) = &self.connection else {
+            return Ok(());
+        };
+        let mut unknown: Vec<String> = self
+            .table_columns
+            .iter()
+            .map(|column| split_column_header(column).1)
+            .filter(|data_type| !data_type.is_empty() && !self.user_types.contains_key(data_type))
+            .collect();
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        unknown.sort();
+        unknown.dedup();
+        let mut found = conn.get_user_types(&unknown).await?;
+        for name in unknown {
+            let user_type = found.remove(&name);
+            self.user_types.insert(name, user_type);
+        }
+        Ok(())
+    }
+
+    /// The enum or composite type of a column of the table view, if it has one.
+    fn column_user_type(&self, column: usize) -> Option<&db::UserType> {
+        let header = self.table_columns.get(column)?;
+        self.user_types
+            .get(&split_column_header(header).1)?
+            .as_ref()
+    }
+
+    async fn fetch_table_data(&mut self) -> Result<()> {
+        if let (Some(table), Some(conn)) = (&self.current_table, &self.connection) {
+            let offset = (self.current_page * self.items_per_page) as i64;
+            let limit = self.items_per_page as i64;
+            let hidden = self
+                .config
+                .hidden_columns(self.connection_name.as_deref().unwrap_or_default(), table)
+                .to_vec();
+
+            let key = match self.keyset_columns.get(table) {
+                Some(key) => key.clone(),
+                None => {
+                    let key = conn.get_keyset_column(table).await?;
+                    self.keyset_columns.insert(table.clone(), key.clone());
+                    key
+                }
+            };
+            let mut timing = QueryTiming::default();
+            let (columns, data) = match self.page_cache.get(table, self.current_page) {
+                Some(page) => {
+                    self.table_page_sql = page.sql.clone();
+                    (page.columns.clone(), page.rows.clone())
+                }
+                None => {
+                    let started = Instant::now();
+                    let (columns, data) = match &key {
+                        Some(key) => {
+                            let cursor = page_cursor(
+                                self.page_bounds.as_ref(),
+                                table,
+                                self.current_page,
+                                offset,
+                            );
+                            conn.get_table_page(table, &hidden, Some(key), &cursor, limit)
+                                .await?
+                        }
+                        None => conn.get_table_data(table, &hidden, offset, limit).await?,
+                    };
+                    timing.execute = Some(started.elapsed());
+                    self.table_page_sql = conn.last_page_sql();
+                    self.page_cache.insert(
+                        table,
+                        self.current_page,
+                        CachedPage {
+                            columns: columns.clone(),
+                            rows: data.clone(),
+                            sql: self.table_page_sql.clone(),
+                        },
+                    );
+                    (columns, data)
+                }
+            };
+
+            self.table_columns = columns;
+            self.table_data = data;
+            self.marked_rows.clear();
+            self.mark_anchor = None;
+            let names: Vec<String> = self
+                .table_columns
+                .iter()
+                .map(|column| split_column_header(column).0)
+                .collect();
+            self.table_content_widths =
+                cells::content_widths(&names, &self.table_data, MAX_AUTO_COLUMN_WIDTH);
+            self.page_bounds = key.and_then(|key| {
+                let index = self
+                    .table_columns
+                    .iter()
+                    .position(|c| split_column_header(c).0 == key.name)?;
+                Some(PageBounds {
+                    table: table.clone(),
+                    page: self.current_page,
+                    first_key: self.table_data.first()?.get(index)?.clone(),
+                    last_key: self.table_data.last()?.get(index)?.clone(),
+                })
+            });
+
+            // Calculate max page based on table count
+            let mode = if self.exact_count_tables.contains(table) {
+                RowCountMode::Exact
+            } else {
+                self.config.settings.row_count
+            };
+            let (total_count, estimated) = match self.page_cache.count(table) {
+                Some(count) => count,
+                None => {
+                    let started = Instant::now();
+                    let count = count_table_rows(conn, table, mode).await?;
+                    timing.count = Some(started.elapsed());
+                    self.page_cache.set_count(table, count);
+                    count
+                }
+            };
+            self.table_timing = timing;
+            self.total_count = total_count;
+            self.total_count_estimated = estimated;
+            self.max_page = ((total_count as f64) / (self.items_per_page as f64)).ceil() as u32;
+            if estimated && !self.table_data.is_empty() {
+                // The estimate may be low; never claim fewer pages than we've seen
+                self.max_page = self.max_page.max(self.current_page + 1);
+            }
+
+            if !self.table_data.is_empty() {
+                self.table_data_state.select(Some(0));
+            }
+        }
+
+        if let (Some(name), Some(table)) = (&self.connection_name, &self.current_table) {
+            self.session.remember_view(name, table, self.current_page);
+            // Losing the remembered position is harmless, so don't fail the load over it
+            let _ = self.session.save();
+        }
+        Ok(())
+    }
+
+    // The four list movements do nothing when no entries are visible, whether the list
+    // is empty or the filter matches nothing
+
+    pub fn next_connection(&mut self) {
+        let visible = self.visible_connections();
+        if visible.is_empty() {
+            return;
+        }
+        let selected = self.connections_list_state.selected();
+        self.connections_list_state
+            .select(filter::step(&visible, selected, true));
+    }
+
+    pub fn previous_connection(&mut self) {
+        let visible = self.visible_connections();
+        if visible.is_empty() {
+            return;
+        }
+        let selected = self.connections_list_state.selected();
+        self.connections_list_state
+            .select(filter::step(&visible, selected, false));
+    }
+
+    pub fn next_table(&mut self) {
+        let visible = self.visible_tables();
+        if visible.is_empty() {
+            return;
+        }
+        let selected = self.tables_list_state.selected();
+        self.tables_list_state
+            .select(filter::step(&visible, selected, true));
+    }
+
+    pub fn previous_table(&mut self) {
+        let visible = self.visible_tables();
+        if visible.is_empty() {
+            return;
+        }
+        let selected = self.tables_list_state.selected();
+        self.tables_list_state
+            .select(filter::step(&visible, selected, false));
+    }
+
+    /// The saved connections in the order the connection list shows them.
+    pub fn connection_names(&self) -> Vec<String> {
+        let mut names = self.config.list_connections();
+        if self.config.settings.connection_order == ConnectionOrder::Recent {
+            self.session.sort_recent_first(&mut names);
+        }
+        names
+    }
+
+    /// Switch the connection list between most recently used first and by name, keeping
+    /// the same connection selected, and save the choice.
+    pub fn toggle_connection_order(&mut self) {
+        let names = self.connection_names();
+        let selected = self
+            .connections_list_state
+            .selected()
+            .and_then(|i| names.get(i).cloned());
+        let settings = &mut self.config.settings;
+        settings.connection_order = match settings.connection_order {
+            ConnectionOrder::Recent => ConnectionOrder::Name,
+            ConnectionOrder::Name => ConnectionOrder::Recent,
+        };
+        self.select_connection(selected.as_deref());
+        let order = connection_order_label(self.config.settings.connection_order);
+        self.status_message = Some(match self.config.save() {
+            Ok(()) => format!("Connections sorted {}", order),
+            Err(e) => format!(
+                "Connections sorted {}, but saving the setting failed: {}",
+                order, e
+            ),
+        });
+    }
+
+    // Select the named connection in the connection list, if it's there
+    fn select_connection(&mut self, name: Option<&str>) {
+        if let Some(index) =
+            name.and_then(|name| self.connection_names().iter().position(|conn| conn == name))
+        {
+            self.connections_list_state.select(Some(index));
+        }
+    }
+
+    /// Indices into the connection list of the connections passing the list filter,
+    /// best match first.
+    pub fn visible_connections(&self) -> Vec<usize> {
+        let connections = self.connection_names();
+        match &self.list_filter {
+            Some(pattern) => filter::filter_indices(&connections, pattern),
+            None => (0..connections.len()).collect(),
+        }
+    }
+
+    /// Indices into `tables` of the tables passing the list filter, best match first.
+    pub fn visible_tables(&self) -> Vec<usize> {
+        match &self.list_filter {
+            Some(pattern) => filter::filter_indices(&self.tables, pattern),
+            None => (0..self.tables.len()).collect(),
+        }
+    }
+
+    /// Select the best match after the list filter changed.
+    fn apply_list_filter(&mut self) {
+        if matches!(self.state, AppState::ConnectionSelection) {
+            let first = self.visible_connections().first().copied();
+            self.connections_list_state.select(first);
+        } else {
+            let first = self.visible_tables().first().copied();
+            self.tables_list_state.select(first);
+        }
+        self.filtered_list_state = ListState::default();
+    }
+
+    pub fn next_row(&mut self) {
+        let data_len = if matches!(self.state, AppState::CustomQuery) {
+            self.custom_query_result_data.len()
+        } else {
+            self.table_data.len()
+        };
+
+        if data_len == 0 {
+            return;
+        }
+
+        let i = match self.table_data_state.selected() {
+            Some(i) => {
+                if i >= data_len - 1 {
+                    0
+                } else {
+                    i + 1
+                }
+            }
+            None => 0,
+        };
+        self.table_data_state.select(Some(i));
+    }
+
+    pub fn previous_row(&mut self) {
+        let data_len = if matches!(self.state, AppState::CustomQuery) {
+            self.custom_query_result_data.len()
+        } else {
+            self.table_data.len()
+        };
+
+        if data_len == 0 {
+            return;
+        }
+
+        let i = match self.table_data_state.selected() {
+            Some(i) => {
+                if i == 0 {
+                    data_len - 1
+                } else {
+                    i - 1
+                }
+            }
+            None => 0,
+        };
+        self.table_data_state.select(Some(i));
+    }
+
+    /// The 1-based absolute position of the selected row across all pages of the current view.
+    pub fn selected_row_number(&self) -> Option<u64> {
+        let page = if matches!(self.state, AppState::CustomQuery) {
+            self.custom_query_current_page
+        } else {
+            self.current_page
+        };
+        let selected = self.table_data_state.selected()?;
+        Some(page as u64 * self.items_per_page as u64 + selected as u64 + 1)
+    }
+
+    // Title suffix describing the selected row's position, e.g. ", row 23 of 140" (or
+    // "of ~140" when the total is an estimate)
+    fn row_position_label(&self, total_count: Option<i64>, estimated: bool) -> String {
+        let approx = if estimated { "~" } else { "" };
+        match (self.selected_row_number(), total_count) {
+            (Some(row), Some(total)) => format!(", row {} of {}{}", row, approx, total),
+            (Some(row), None) => format!(", row {}", row),
+            (None, Some(total)) => format!(", {}{} rows", approx, total),
+            (None, None) => String::new(),
+        }
+    }
+
+    /// Reload the current page and the row count of the table being viewed, keeping the
+    /// selected row where it still exists.
+    pub async fn refresh_table_data(&mut self) -> Result<()> {
+        let Some(table) = self.current_table.clone() else {
+            return Ok(());
+        };
+        let selected = self.table_data_state.selected();
+        self.page_cache.invalidate_table(&table);
+        self.load_table_data().await?;
+        // Rows may have been deleted, leaving the current page past the end
+        if self.current_page > 0 && self.current_page >= self.max_page {
+            self.current_page = self.max_page.saturating_sub(1);
+            self.load_table_data().await?;
+        }
+        reselect_row(&mut self.table_data_state, selected, self.table_data.len());
+        self.status_message
+            .get_or_insert_with(|| "Refreshed".to_string());
+        Ok(())
+    }
+
+    /// Open the column picker for the table being viewed, listing all of its columns.
+    pub async fn open_column_picker(&mut self) -> Result<()> {
+        let (Some(table), Some(conn)) = (&self.current_table, &self.connection) else {
+            return Ok(());
+        };
+        let columns = conn.list_columns(table).await?;
+        let hidden = self
+            .config
+            .hidden_columns(self.connection_name.as_deref().unwrap_or_default(), table);
+        self.column_picker = Some(ColumnPicker::new(columns, hidden));
+        Ok(())
+    }
+
+    /// Show or hide the row of column types under the table view's column names, and
+    /// remember the choice in the config.
+    pub fn toggle_column_types(&mut self) {
+        let settings = &mut self.config.settings;
+        settings.show_column_types = !settings.show_column_types;
+        let shown = if settings.show_column_types {
+            "shown"
+        } else {
+            "hidden"
+        };
+        self.status_message = Some(match self.config.save() {
+            Ok(()) => format!("Column types {}", shown),
+            Err(e) => format!(
+                "Column types {}, but saving the setting failed: {}",
+                shown, e
+            ),
+        });
+    }
+
+    /// Show the commonest values of the selected column, first asking when the table is
+    /// big enough that grouping all its rows will take a while.
+    pub async fn request_histogram(&mut self) -> Result<()> {
+        let Some((table, _, column)) = self.selected_table_column() else {
+            self.status_message =
+                Some("Select a column with ←→ to see how its values are distributed".to_string());
+            return Ok(());
+        };
+        if self.total_count > HISTOGRAM_CONFIRM_ROWS {
+            self.histogram_confirm = Some((table, column));
+            return Ok(());
+        }
+        self.open_histogram(table, column).await
+    }
+
+    pub async fn open_histogram(&mut self, table: String, column: String) -> Result<()> {
+        let Some(conn) = &self.connection else {
+            return Ok(());
+        };
+        let counts = conn.value_counts(&table, &column, HISTOGRAM_VALUES).await?;
+        self.histogram = Some(Histogram {
+            table,
+            column,
+            counts,
+        });
+        Ok(())
+    }
+
+    /// Save the columns chosen in the picker and reload the page with just those.
+    pub async fn apply_column_picker(&mut self) -> Result<()> {
+        let (Some(picker), Some(table)) = (self.column_picker.take(), self.current_table.clone())
+        else {
+            return Ok(());
+        };
+        let connection = self.connection_name.clone().unwrap_or_default();
+        self.config
+            .set_hidden_columns(&connection, &table, picker.hidden());
+        if let Err(e) = self.config.save() {
+            self.status_message = Some(format!("Failed to save column selection: {}", e));
+        }
+
+        self.page_cache.forget_pages(&table);
+        self.field_selection_state = None;
+        let previous = self.table_data_state.selected();
+        self.load_table_data().await?;
+        reselect_row(&mut self.table_data_state, previous, self.table_data.len());
+        Ok(())
+    }
+
+    /// Open the form for inserting a row into the table being viewed, unless the session
+    /// is read-only.
+    pub async fn open_insert_form(&mut self) -> Result<()> {
+        let (Some(table), Some(conn)) = (&self.current_table, &self.connection) else {
+            return Ok(());
+        };
+        if conn.is_read_only().await? {
+            self.status_message =
+                Some("The connection is read-only, so rows can't be inserted".to_string());
+            return Ok(());
+        }
+        let columns = conn.describe_columns(table).await?;
+        self.insert_form = Some(InsertForm::new(table.clone(), columns));
+        Ok(())
+    }
+
+    /// Insert the row entered in the form and reload the page. If the row is incomplete or
+    /// the server rejects it, the form stays open with the reason in the status bar.
+    pub async fn submit_insert_form(&mut self) -> Result<()> {
+        let (Some(form), Some(conn)) = (&self.insert_form, &self.connection) else {
+            return Ok(());
+        };
+        let row = match form.row() {
+            Ok(row) => row,
+            Err(e) => {
+                self.status_message = Some(e);
+                return Ok(());
+            }
+        };
+        if let Err(e) = conn.insert_row(&form.table, &row).await {
+            self.status_message = Some(format!("Insert failed: {}", e));
+            return Ok(());
+        }
+        let table = form.table.clone();
+        self.insert_form = None;
+        self.query_cache.clear();
+        self.refresh_table_data().await?;
+        self.status_message = Some(format!("Inserted a row into {}", table));
+        Ok(())
+    }
+
+    /// Start editing the selected cell, if its row can be found again by a single-column
+    /// primary key and the session can write.
+    pub async fn start_cell_edit(&mut self) -> Result<()> {
+        let (Some(table), Some(conn)) = (&self.current_table, &self.connection) else {
+            return Ok(());
+        };
+        let (Some(row), Some(field)) = (
+            self.table_data_state
+                .selected()
+                .and_then(|i| self.table_data.get(i)),
+            self.field_selection_state,
+        ) else {
+            self.status_message = Some("Select a field with ←→ to edit it".to_string());
+            return Ok(());
+        };
+        let Some(key) = self.keyset_columns.get(table).cloned().flatten() else {
+            self.status_message = Some(format!(
+                "Only tables with a single-column primary key can be edited, which {} doesn't have",
+                table
+            ));
+            return Ok(());
+        };
+        let names: Vec<String> = self
+            .table_columns
+            .iter()
+            .map(|c| split_column_header(c).0)
+            .collect();
+        let Some(key_index) = names.iter().position(|name| *name == key.name) else {
+            self.status_message = Some(format!(
+                "The primary key {} is hidden; show it with 'C' to edit",
+                key.name
+            ));
+            return Ok(());
+        };
+        let (Some(column), Some(key_value)) = (names.get(field), row.get(key_index)) else {
+            return Ok(());
+        };
+        if conn.is_read_only().await? {
+            self.status_message =
+                Some("The connection is read-only, so cells can't be edited".to_string());
+            return Ok(());
+        }
+        // The grid shows NULL and text reading "NULL" alike, so the value is read again
+        let Some(value) = conn.cell_value(table, column, &key.name, key_value).await? else {
+            self.status_message = Some("The row is gone; it may have been deleted".to_string());
+            return Ok(());
+        };
+        self.cell_edit = Some(CellEdit::new(
+            table.clone(),
+            column.clone(),
+            key.name,
+            key_value.clone(),
+            value,
+        ));
+        Ok(())
+    }
+
+    /// Store the confirmed cell value and reload the page. If the server rejects it, the
+    /// editor stays open with the reason in the status bar.
+    pub async fn submit_cell_edit(&mut self) -> Result<()> {
+        let (Some(edit), Some(conn)) = (&self.cell_edit, &self.connection) else {
+            return Ok(());
+        };
+        let updated = conn
+            .update_cell(
+                &edit.table,
+                &edit.column,
+                edit.new_value(),
+                &edit.key_column,
+                &edit.key_value,
+            )
+            .await;
+        let column = edit.column.clone();
+        match updated {
+            Ok(rows) => {
+                self.cell_edit = None;
+                self.query_cache.clear();
+                self.refresh_table_data().await?;
+                self.status_message = Some(if rows == 0 {
+                    "No row was updated; it may have been deleted".to_string()
+                } else {
+                    format!("Updated {}", column)
+                });
+            }
+            Err(e) => {
+                if let Some(edit) = self.cell_edit.as_mut() {
+                    edit.confirming = false;
+                }
+                self.status_message = Some(format!("Update failed: {}", e));
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the page holding the 1-based row number typed at the row prompt and select
+    /// that row.
+    pub async fn jump_to_row(&mut self, input: &str) -> Result<()> {
+        let row = match input.parse::<u64>() {
+            Ok(row) if row > 0 => row,
+            _ => {
+                self.status_message = Some(format!("Not a row number: '{}'", input));
+                return Ok(());
+            }
+        };
+        // An estimated count may be low, so only an exact one rules rows out up front
+        if !self.total_count_estimated && row > self.total_count as u64 {
+            self.status_message = Some(format!(
+                "Row {} is out of range: the table has {} rows",
+                row, self.total_count
+            ));
+            return Ok(());
+        }
+
+        let previous_page = self.current_page;
+        let (page, index) = row_location(row, self.items_per_page);
+        self.current_page = page;
+        self.load_table_data().await?;
+        self.field_selection_state = None;
+        if index < self.table_data.len() {
+            self.table_data_state.select(Some(index));
+        } else {
+            self.status_message = Some(format!("Row {} is past the end of the table", row));
+            self.current_page = previous_page;
+            self.load_table_data().await?;
+        }
+        Ok(())
+    }
+
+    /// Move to the next page and load it.
+    pub async fn next_page(&mut self) -> Result<()> {
+        // An estimated row count can be too low, so keep going while pages are full
+        let more_past_estimate =
+            self.total_count_estimated && self.table_data.len() == self.items_per_page as usize;
+        // Compared as `page + 1 < max` so an empty table's max_page of 0 can't underflow
+        if self.current_page + 1 < self.max_page || more_past_estimate {
+            self.current_page += 1;
+            self.load_table_data().await?;
+        }
+        Ok(())
+    }
+
+    /// Move to the previous page and load it.
+    pub async fn previous_page(&mut self) -> Result<()> {
+        if self.current_page > 0 {
+            self.current_page -= 1;
+            self.load_table_data().await?;
+        }
+        Ok(())
+    }
+
+    pub fn next_field(&mut self) {
+        // Check if we're in table data view
+        if matches!(self.state, AppState::TableData)
+            && let Some(selected_row_idx) = self.table_data_state.selected()
+            && selected_row_idx < self.table_data.len()
+        {
+            let num_fields = self.table_data[selected_row_idx].len();
+            if num_fields > 0 {
+                let next_field_idx = match self.field_selection_state {
+                    Some(current_idx) => {
+                        if current_idx >= num_fields - 1 {
+                            0 // Wrap to first field
+                        } else {
+                            current_idx + 1
+                        }
+                    }
+                    None => 0, // Start with first field if none selected yet
+                };
+                self.field_selection_state = Some(next_field_idx);
+            }
+        }
+        // Check if we're in custom query view
+        else if matches!(self.state, AppState::CustomQuery)
+            && let Some(selected_row_idx) = self.table_data_state.selected()
+            && selected_row_idx < self.custom_query_result_data.len()
+        {
+            let num_fields = self.custom_query_result_data[selected_row_idx].len();
+            if num_fields > 0 {
+                let next_field_idx = match self.field_selection_state {
+                    Some(current_idx) => {
+                        if current_idx >= num_fields - 1 {
+                            0 // Wrap to first field
+                        } else {
+                            current_idx + 1
+                        }
+                    }
                     None => 0, // Start with first field if none selected yet
                 };
                 self.field_selection_state = Some(next_field_idx);
             }
         }
-        // Check if we're in custom query view
-        else if matches!(self.state, AppState::CustomQuery)
-            && let Some(selected_row_idx) = self.table_data_state.selected()
-            && selected_row_idx < self.custom_query_result_data.len()
-        {
-            let num_fields = self.custom_query_result_data[selected_row_idx].len();
-            if num_fields > 0 {
-                let next_field_idx = match self.field_selection_state {
-                    Some(current_idx) => {
-                        if current_idx >= num_fields - 1 {
-                            0 // Wrap to first field
-                        } else {
-                            current_idx + 1
-                        }
+    }
+
+    pub fn previous_field(&mut self) {
+        // Check if we're in table data view
+        if matches!(self.state, AppState::TableData)
+            && let Some(selected_row_idx) = self.table_data_state.selected()
+            && selected_row_idx < self.table_data.len()
+        {
+            let num_fields = self.table_data[selected_row_idx].len();
+            if num_fields > 0 {
+                let prev_field_idx = match self.field_selection_state {
+                    Some(current_idx) => {
+                        if current_idx == 0 {
+                            num_fields - 1 // Wrap to last field
+                        } else {
+                            current_idx - 1
+                        }
+                    }
+                    None => 0, // Start with first field if none selected yet
+                };
+                self.field_selection_state = Some(prev_field_idx);
+            }
+        }
+        // Check if we're in custom query view
+        else if matches!(self.state, AppState::CustomQuery)
+            && let Some(selected_row_idx) = self.table_data_state.selected()
+            && selected_row_idx < self.custom_query_result_data.len()
+        {
+            let num_fields = self.custom_query_result_data[selected_row_idx].len();
+            if num_fields > 0 {
+                let prev_field_idx = match self.field_selection_state {
+                    Some(current_idx) => {
+                        if current_idx == 0 {
+                            num_fields - 1 // Wrap to last field
+                        } else {
+                            current_idx - 1
+                        }
+                    }
+                    None => 0, // Start with first field if none selected yet
+                };
+                self.field_selection_state = Some(prev_field_idx);
+            }
+        }
+    }
+
+    // A table cell as shown in the field detail view: arrays one element per line,
+    // anything else as it is
+    fn table_field_detail(&self, row: usize, column: usize) -> String {
+        let value = &self.table_data[row][column];
+        let is_array = self
+            .table_columns
+            .get(column)
+            .is_some_and(|header| arrays::is_array_type(&split_column_header(header).1));
+        if let Some(user_type) = self.column_user_type(column)
+            && value != "NULL"
+        {
+            composites::detail_text(value, user_type).unwrap_or_else(|| value.clone())
+        } else if is_array {
+            arrays::detail_text(value).unwrap_or_else(|| value.clone())
+        } else {
+            value.clone()
+        }
+    }
+
+    pub fn enter_field_detail_view(&mut self) {
+        // Check if we're in table data view
+        if matches!(self.state, AppState::TableData)
+            && let Some(selected_row_idx) = self.table_data_state.selected()
+            && selected_row_idx < self.table_data.len()
+        {
+            if let Some(selected_field_idx) = self.field_selection_state {
+                if selected_field_idx < self.table_data[selected_row_idx].len() {
+                    // Store the selected field value for detailed view
+                    self.selected_field_value =
+                        Some(self.table_field_detail(selected_row_idx, selected_field_idx));
+                    // Store the original state for returning later
+                    self.field_detail_origin_state = Some(AppState::TableData);
+                    // Switch to field detail view
+                    self.state = AppState::FieldDetail;
+                    self.field_detail_scroll = 0; // Reset scroll to top
+                    self.detail_search = None;
+                }
+            } else if !self.table_data[selected_row_idx].is_empty() {
+                // If no field is selected yet, select the first field
+                self.field_selection_state = Some(0);
+                self.selected_field_value = Some(self.table_field_detail(selected_row_idx, 0));
+                // Store the original state for returning later
+                self.field_detail_origin_state = Some(AppState::TableData);
+                self.state = AppState::FieldDetail;
+                self.field_detail_scroll = 0; // Reset scroll to top
+                self.detail_search = None;
+            }
+        }
+        // Check if we're in custom query view
+        else if matches!(self.state, AppState::CustomQuery)
+            && let Some(selected_row_idx) = self.table_data_state.selected()
+            && selected_row_idx < self.custom_query_result_data.len()
+        {
+            if let Some(selected_field_idx) = self.field_selection_state {
+                if selected_field_idx < self.custom_query_result_data[selected_row_idx].len() {
+                    // Store the selected field value for detailed view
+                    self.selected_field_value = Some(
+                        self.custom_query_result_data[selected_row_idx][selected_field_idx].clone(),
+                    );
+                    // Store the original state for returning later
+                    self.field_detail_origin_state = Some(AppState::CustomQuery);
+                    // Switch to field detail view
+                    self.state = AppState::FieldDetail;
+                    self.field_detail_scroll = 0; // Reset scroll to top
+                    self.detail_search = None;
+                }
+            } else if !self.custom_query_result_data[selected_row_idx].is_empty() {
+                // If no field is selected yet, select the first field
+                self.field_selection_state = Some(0);
+                self.selected_field_value =
+                    Some(self.custom_query_result_data[selected_row_idx][0].clone());
+                // Store the original state for returning later
+                self.field_detail_origin_state = Some(AppState::CustomQuery);
+                self.state = AppState::FieldDetail;
+                self.field_detail_scroll = 0; // Reset scroll to top
+                self.detail_search = None;
+            }
+        }
+    }
+
+    /// Mark the selected row for copying, or unmark it if it was marked.
+    pub fn toggle_row_mark(&mut self) {
+        let Some(i) = self.table_data_state.selected() else {
+            return;
+        };
+        if !self.marked_rows.remove(&i) {
+            self.marked_rows.insert(i);
+        }
+        self.mark_anchor = Some(i);
+    }
+
+    /// Mark every row from the last one marked or unmarked to the selected one.
+    pub fn mark_row_range(&mut self) {
+        let Some(i) = self.table_data_state.selected() else {
+            return;
+        };
+        let anchor = self.mark_anchor.unwrap_or(i);
+        self.marked_rows.extend(anchor.min(i)..=anchor.max(i));
+        self.mark_anchor = Some(i);
+    }
+
+    pub fn clear_marked_rows(&mut self) {
+        self.marked_rows.clear();
+        self.mark_anchor = None;
+    }
+
+    /// Copy the marked rows, or the selected row if none are marked. The rows are fetched
+    /// again with their types, by primary key if the table has one and else as the same
+    /// page, so NULL is told apart from text reading "NULL" and JSON keeps numbers,
+    /// booleans and NULLs as such rather than as the grid's text.
+    pub async fn copy_marked_rows(&mut self, format: CopyFormat) -> Result<()> {
+        let (Some(table), Some(conn)) = (&self.current_table, &self.connection) else {
+            return Ok(());
+        };
+        let mut indexes: Vec<usize> = self.marked_rows.iter().copied().collect();
+        if indexes.is_empty() {
+            indexes.extend(self.table_data_state.selected());
+        }
+        indexes.sort_unstable();
+        indexes.retain(|&i| i < self.table_data.len());
+        if indexes.is_empty() {
+            return Ok(());
+        }
+
+        let names: Vec<String> = self
+            .table_columns
+            .iter()
+            .map(|c| split_column_header(c).0)
+            .collect();
+        let select = format!(
+            "SELECT {} FROM {}",
+            names
+                .iter()
+                .map(|name| sql::quote_identifier(name))
+                .collect::<Vec<_>>()
+                .join(", "),
+            sql::quote_identifier(table)
+        );
+        let key = self.keyset_columns.get(table).cloned().flatten();
+        let key_index = key
+            .as_ref()
+            .and_then(|key| names.iter().position(|name| *name == key.name));
+        let rows = match (key, key_index) {
+            (Some(key), Some(key_index)) => {
+                let values: Vec<Option<String>> = indexes
+                    .iter()
+                    .map(|&i| Some(self.table_data[i][key_index].clone()))
+                    .collect();
+                // The parameters are cast to the key's type rather than the key to text,
+                // so they're compared as the key's values and its index can be used
+                let placeholders: Vec<String> = (1..=values.len())
+                    .map(|n| format!("${}::{}", n, key.data_type))
+                    .collect();
+                let column = sql::quote_identifier(&key.name);
+                let query = format!(
+                    "{} WHERE {} IN ({}) ORDER BY {}",
+                    select,
+                    column,
+                    placeholders.join(", "),
+                    column
+                );
+                conn.typed_rows(&query, &values).await?
+            }
+            _ => {
+                let query = format!(
+                    "{} LIMIT {} OFFSET {}",
+                    select,
+                    self.items_per_page,
+                    self.current_page * self.items_per_page
+                );
+                let mut page = conn.typed_rows(&query, &[]).await?;
+                page.rows = indexes
+                    .iter()
+                    .filter_map(|&i| page.rows.get(i).cloned())
+                    .collect();
+                page
+            }
+        };
+        self.status_message = Some(copy_rows(format, &rows));
+        Ok(())
+    }
+
+    /// Copy the page of query results being shown, with its column names first. A query
+    /// that can be paged is fetched again for the page with its types, like table rows
+    /// are; the rows of any other statement are copied from the grid, since running it
+    /// again could repeat what it did, so a text value reading "NULL" is copied as NULL.
+    pub async fn copy_query_results(&mut self, format: CopyFormat) -> Result<()> {
+        if self.custom_query_result_data.is_empty() {
+            self.status_message = Some("No rows to copy".to_string());
+            return Ok(());
+        }
+        let Some(conn) = &self.connection else {
+            return Ok(());
+        };
+        let rows = if sql::is_wrappable(&self.custom_query_statement) {
+            let query = sql::paged_query(
+                &self.custom_query_statement,
+                self.items_per_page as i64,
+                (self.custom_query_current_page * self.items_per_page) as i64,
+            );
+            conn.typed_rows(&query, &self.custom_query_params).await?
+        } else {
+            TypedRows {
+                columns: self
+                    .custom_query_result_columns
+                    .iter()
+                    .map(|name| (name.clone(), ValueKind::Text))
+                    .collect(),
+                rows: self
+                    .custom_query_result_data
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .map(|value| Some(value.clone()).filter(|value| value != "NULL"))
+                            .collect()
+                    })
+                    .collect(),
+            }
+        };
+        self.status_message = Some(copy_rows(format, &rows));
+        Ok(())
+    }
+
+    pub fn copy_selected_row_as_insert(&mut self) {
+        let Some(table) = &self.current_table else {
+            return;
+        };
+        let Some(row) = self
+            .table_data_state
+            .selected()
+            .and_then(|i| self.table_data.get(i))
+        else {
+            return;
+        };
+
+        let columns: Vec<(String, String)> = self
+            .table_columns
+            .iter()
+            .map(|c| split_column_header(c))
+            .collect();
+        let statement = crate::db::sql::insert_statement(table, &columns, row);
+
+        self.status_message = Some(match clipboard::copy_to_clipboard(&statement) {
+            Ok(()) => "Copied INSERT statement to clipboard".to_string(),
+            Err(e) => format!("Failed to copy to clipboard: {}", e),
+        });
+    }
+
+    /// Copy the selected connection's connection string, without its password.
+    pub fn copy_connection_string(&mut self) {
+        let connections = self.connection_names();
+        let Some(conn_info) = self
+            .connections_list_state
+            .selected()
+            .and_then(|i| connections.get(i))
+            .and_then(|name| self.config.get_connection(name))
+        else {
+            return;
+        };
+        let uri = conn_info.to_connection_string(false);
+        self.status_message = Some(match clipboard::copy_to_clipboard(&uri) {
+            Ok(()) => format!("Copied {} to clipboard", uri),
+            Err(e) => format!("Failed to copy to clipboard: {}", e),
+        });
+    }
+
+    /// Show the selected row one column per line.
+    pub fn enter_record_view(&mut self) {
+        let has_row = self
+            .table_data_state
+            .selected()
+            .is_some_and(|row| row < self.current_rows().len());
+        if has_row {
+            self.record_origin_state = Some(self.state.clone());
+            self.state = AppState::RecordView;
+            self.record_scroll = 0;
+        }
+    }
+
+    /// The columns and row shown in the record view.
+    fn record(&self) -> (&[String], &[String]) {
+        let (columns, rows) = match self.record_origin_state {
+            Some(AppState::CustomQuery) => (
+                &self.custom_query_result_columns,
+                &self.custom_query_result_data,
+            ),
+            _ => (&self.table_columns, &self.table_data),
+        };
+        let row = self
+            .table_data_state
+            .selected()
+            .and_then(|i| rows.get(i))
+            .map_or(&[][..], |row| row.as_slice());
+        (columns, row)
+    }
+
+    pub fn scroll_record(&mut self, lines: i32) {
+        let (columns, row) = self.record();
+        let max_scroll = record_lines(columns, row).len().saturating_sub(1) as i32;
+        self.record_scroll = (self.record_scroll as i32 + lines).clamp(0, max_scroll) as u16;
+    }
+
+    pub fn scroll_field_detail_up(&mut self) {
+        if self.field_detail_scroll > 0 {
+            self.field_detail_scroll -= 1;
+        }
+    }
+
+    pub fn scroll_field_detail_down(&mut self) {
+        if self.field_detail_scroll < self.field_detail_max_scroll {
+            self.field_detail_scroll += 1;
+        }
+    }
+
+    /// Change the query of the field detail search and find its matches again.
+    fn edit_detail_search(&mut self, edit: impl FnOnce(&mut String)) {
+        if let Some(search) = self.detail_search.as_mut() {
+            edit(&mut search.query);
+            search.update(self.selected_field_value.as_deref().unwrap_or_default());
+        }
+    }
+
+    /// Narrow or widen the selected column of the table view by `steps` steps, keeping the
+    /// new width in the config for the next time the table is opened.
+    pub fn resize_selected_column(&mut self, steps: i32) {
+        let Some((table, index, column)) = self.selected_table_column() else {
+            self.status_message = Some("Select a column with ←→ to resize it".to_string());
+            return;
+        };
+        let connection = self.connection_name.clone().unwrap_or_default();
+        // An automatically sized column starts from the width it was last drawn with
+        let current = self
+            .config
+            .column_width(&connection, &table, &column)
+            .or_else(|| {
+                self.click_targets
+                    .columns
+                    .get(index)
+                    .map(|&(_, width)| width)
+            })
+            .unwrap_or(0);
+        let width = (current as i32 + steps * COLUMN_RESIZE_STEP)
+            .clamp(MIN_COLUMN_WIDTH, MAX_COLUMN_WIDTH) as u16;
+        self.config
+            .set_column_width(&connection, &table, &column, Some(width));
+        self.status_message = Some(match self.config.save() {
+            Ok(()) => format!("{} is {} wide, '=' to size it automatically", column, width),
+            Err(e) => format!("Failed to save column width: {}", e),
+        });
+    }
+
+    /// Go back to sizing the selected column of the table view automatically.
+    pub fn reset_selected_column_width(&mut self) {
+        let Some((table, _, column)) = self.selected_table_column() else {
+            return;
+        };
+        let connection = self.connection_name.clone().unwrap_or_default();
+        self.config
+            .set_column_width(&connection, &table, &column, None);
+        self.status_message = Some(match self.config.save() {
+            Ok(()) => format!("{} is sized automatically", column),
+            Err(e) => format!("Failed to save column width: {}", e),
+        });
+    }
+
+    // The current table and the index and name of its selected column
+    fn selected_table_column(&self) -> Option<(String, usize, String)> {
+        let table = self.current_table.clone()?;
+        let index = self.field_selection_state?;
+        let column = split_column_header(self.table_columns.get(index)?).0;
+        Some((table, index, column))
+    }
+
+    // Widths for the columns of the table view within `available` columns: those sized by
+    // hand keep their width and the others are sized to their content in what's left
+    fn table_column_widths(&self, available: u16) -> Vec<Constraint> {
+        let connection = self.connection_name.as_deref().unwrap_or_default();
+        let table = self.current_table.as_deref().unwrap_or_default();
+        let fixed: Vec<Option<u16>> = self
+            .table_columns
+            .iter()
+            .map(|column| {
+                let name = split_column_header(column).0;
+                self.config.column_width(connection, table, &name)
+            })
+            .collect();
+        column_widths(&fixed, &self.table_content_widths, available)
+    }
+
+    /// Run the query input, first opening `param_prompt` to ask for the values of its
+    /// `$n` placeholders if it has any.
+    pub async fn start_custom_query(&mut self) -> Result<()> {
+        let statements = sql::split_statements(&self.custom_query_input);
+        if self.config.settings.confirm_destructive
+            && statements.iter().any(|s| sql::is_destructive(s))
+        {
+            self.destructive_confirm = Some(String::new());
+            return Ok(());
+        }
+        self.start_confirmed_query().await
+    }
+
+    /// Carry on running the query input once any destructive statements were confirmed.
+    pub async fn start_confirmed_query(&mut self) -> Result<()> {
+        self.custom_query_params.clear();
+        let statements = sql::split_statements(&self.custom_query_input);
+        if statements.iter().any(|s| sql::parameter_count(s) > 0) {
+            if statements.len() > 1 {
+                return Err(anyhow::anyhow!(
+                    "Parameters ($1, $2, ...) can only be used when running a single statement"
+                ));
+            }
+            let types = match &self.connection {
+                Some(conn) => conn.parameter_types(&statements[0]).await?,
+                None => Vec::new(),
+            };
+            if !types.is_empty() {
+                self.param_prompt = Some(ParamPrompt::new(types));
+                return Ok(());
+            }
+        }
+        self.state = AppState::CustomQuery;
+        self.run_custom_query().await
+    }
+
+    /// Run the query input with the values entered in `param_prompt`.
+    pub async fn submit_params(&mut self) -> Result<()> {
+        if let Some(prompt) = self.param_prompt.take() {
+            self.custom_query_params = prompt.params();
+        }
+        self.state = AppState::CustomQuery;
+        self.run_custom_query().await
+    }
+
+    /// Run everything in the query input.
+    ///
+    /// A single statement that returns rows is paged through; any other statement runs
+    /// once and its affected-row count is kept in `custom_query_rows_affected`. When the input holds several
+    /// statements, each runs once in order and its outcome is recorded in
+    /// `custom_query_batch`; a final SELECT is then shown in the results table.
+    ///
+    /// In transactional mode, input containing a mutating statement runs inside a
+    /// transaction and leaves the app in `TransactionPending` until it is committed or
+    /// rolled back. A failure rolls the transaction back straight away.
+    pub async fn run_custom_query(&mut self) -> Result<()> {
+        // The query may change any table, so don't show cached pages afterwards
+        self.page_cache.clear();
+        let statements = sql::split_statements(&self.custom_query_input);
+        let changes_state = statements.iter().any(|s| !sql::is_wrappable(s));
+        if changes_state {
+            self.query_cache.clear();
+        }
+        let use_transaction =
+            self.transactional && statements.iter().any(|s| !sql::returns_rows(s));
+
+        if use_transaction && let Some(conn) = self.connection.as_mut() {
+            conn.begin().await?;
+        }
+
+        let result = self.run_statements(statements).await;
+
+        // The statements may have opened or ended a transaction themselves, with BEGIN,
+        // COMMIT or ROLLBACK. If the server can't be asked, what it was thought to be stays
+        if changes_state && let Some(conn) = self.connection.as_mut() {
+            let _ = conn.sync_transaction_state().await;
+        }
+
+        if self.connection.as_ref().is_some_and(|c| c.in_transaction()) {
+            let failed = result.is_err()
+                || self
+                    .custom_query_batch
+                    .iter()
+                    .any(|s| matches!(s.outcome, StatementOutcome::Error(_)));
+            if failed {
+                // The connection may be gone too, in which case the server has already
+                // discarded the transaction
+                let _ = self.finish_transaction(false).await;
+                self.status_message = Some("Transaction rolled back after an error".to_string());
+            } else {
+                self.state = AppState::TransactionPending;
+            }
+        }
+
+        result
+    }
+
+    /// Commit (or roll back) the transaction opened by a transactional custom query.
+    pub async fn finish_transaction(&mut self, commit: bool) -> Result<()> {
+        self.state = AppState::CustomQuery;
+        self.page_cache.clear();
+        self.query_cache.clear();
+        if let Some(conn) = self.connection.as_mut() {
+            if commit {
+                conn.commit().await?;
+            } else {
+                conn.rollback().await?;
+            }
+        }
+        self.status_message = Some(
+            if commit {
+                "Transaction committed"
+            } else {
+                "Transaction rolled back"
+            }
+            .to_string(),
+        );
+        Ok(())
+    }
+
+    async fn run_statements(&mut self, mut statements: Vec<String>) -> Result<()> {
+        self.custom_query_batch.clear();
+        self.custom_query_result_columns.clear();
+        self.custom_query_result_data.clear();
+        self.custom_query_total_count = Some(0);
+        self.custom_query_count_capped = false;
+        self.custom_query_max_page = 0;
+        self.custom_query_rows_affected = None;
+        self.custom_query_timing = QueryTiming::default();
+        self.custom_query_page_sql = None;
+
+        if statements.len() <= 1 {
+            self.custom_query_statement = statements
+                .pop()
+                .unwrap_or_else(|| self.custom_query_input.clone());
+            if sql::returns_rows(&self.custom_query_statement) {
+                return self.execute_custom_query().await;
+            }
+            if let Some(conn) = &self.connection {
+                let started = Instant::now();
+                let rows = conn
+                    .execute_statement(&self.custom_query_statement, &self.custom_query_params)
+                    .await?;
+                self.custom_query_timing.execute = Some(started.elapsed());
+                self.custom_query_page_sql = Some(self.custom_query_statement.clone());
+                self.custom_query_rows_affected = Some(rows);
+            }
+            return Ok(());
+        }
+
+        let Some(conn) = &self.connection else {
+            return Ok(());
+        };
+
+        // Only a trailing SELECT is paged; re-running the rest on every page change
+        // would repeat their side effects
+        let final_select = if statements.last().is_some_and(|s| sql::is_select(s)) {
+            statements.pop()
+        } else {
+            None
+        };
+
+        let mut failed = false;
+        for statement in statements {
+            let outcome = if failed {
+                StatementOutcome::NotRun
+            } else {
+                match conn.execute_statement(&statement, &[]).await {
+                    Ok(rows) => StatementOutcome::Rows(rows),
+                    Err(e) => {
+                        failed = true;
+                        StatementOutcome::Error(e.to_string())
                     }
-                    None => 0, // Start with first field if none selected yet
-                };
-                self.field_selection_state = Some(next_field_idx);
+                }
+            };
+            self.custom_query_batch
+                .push(StatementSummary { statement, outcome });
+        }
+
+        if let Some(statement) = final_select {
+            let outcome = if failed {
+                StatementOutcome::NotRun
+            } else {
+                self.custom_query_statement = statement.clone();
+                match self.execute_custom_query().await {
+                    Ok(()) => StatementOutcome::Results,
+                    Err(e) => StatementOutcome::Error(e.to_string()),
+                }
+            };
+            self.custom_query_batch
+                .push(StatementSummary { statement, outcome });
+        }
+
+        Ok(())
+    }
+
+    /// Fetch the current page of `custom_query_statement`.
+    pub async fn execute_custom_query(&mut self) -> Result<()> {
+        if let Some(conn) = &self.connection {
+            let offset = (self.custom_query_current_page * self.items_per_page) as i64;
+            let limit = self.items_per_page as i64;
+
+            // Read-only queries outside a transaction may be answered from the cache
+            let cacheable =
+                sql::is_wrappable(&self.custom_query_statement) && !conn.in_transaction();
+            let cached = self
+                .query_cache
+                .get(
+                    &self.custom_query_statement,
+                    &self.custom_query_params,
+                    self.custom_query_current_page,
+                )
+                .filter(|_| cacheable)
+                .map(|(age, cached)| (age, cached.clone()));
+            let (page, total_count) = match cached {
+                Some((age, cached)) => {
+                    self.status_message = Some(format!(
+                        "Cached results from {}s ago; 'r' to refresh",
+                        age.as_secs()
+                    ));
+                    self.custom_query_timing = QueryTiming::default();
+                    self.custom_query_page_sql = cached.sql;
+                    (cached.page, Ok(cached.total_count))
+                }
+                None => {
+                    let (page, total_count, timing) = fetch_query_page(
+                        conn,
+                        &self.custom_query_statement,
+                        &self.custom_query_params,
+                        offset,
+                        limit,
+                        self.max_count_rows,
+                    )
+                    .await?;
+                    self.custom_query_timing = timing;
+                    self.custom_query_page_sql = conn.last_page_sql();
+                    if cacheable && let Ok(total) = &total_count {
+                        self.query_cache.insert(
+                            &self.custom_query_statement,
+                            &self.custom_query_params,
+                            self.custom_query_current_page,
+                            CachedResult {
+                                page: page.clone(),
+                                total_count: *total,
+                                sql: self.custom_query_page_sql.clone(),
+                            },
+                        );
+                    }
+                    (page, total_count)
+                }
+            };
+
+            self.custom_query_result_columns = page.columns;
+            self.custom_query_result_data = page.rows;
+            self.custom_query_content_widths = cells::content_widths(
+                &self.custom_query_result_columns,
+                &self.custom_query_result_data,
+                MAX_AUTO_COLUMN_WIDTH,
+            );
+
+            // Calculate max page based on query count
+            self.custom_query_count_capped = false;
+            let total_count = match total_count {
+                Ok(total) => match self.max_count_rows {
+                    Some(max) if total > max as i64 => {
+                        self.custom_query_count_capped = true;
+                        self.status_message = Some(format!(
+                            "Over {} rows, so not counted (max_count_rows)",
+                            max
+                        ));
+                        None
+                    }
+                    _ => Some(total),
+                },
+                Err(e) => {
+                    // The rows are still worth showing without a page count
+                    self.status_message = Some(format!("Row count unavailable: {}", e));
+                    None
+                }
+            };
+            self.custom_query_total_count = total_count;
+            self.custom_query_max_page = match total_count {
+                Some(total) => ((total as f64) / (self.items_per_page as f64)).ceil() as u32,
+                None => self.custom_query_current_page + 1,
+            };
+
+            if !self.custom_query_result_data.is_empty() {
+                self.table_data_state.select(Some(0));
             }
         }
+        Ok(())
     }
 
-    pub fn previous_field(&mut self) {
-        // Check if we're in table data view
-        if matches!(self.state, AppState::TableData)
-            && let Some(selected_row_idx) = self.table_data_state.selected()
-            && selected_row_idx < self.table_data.len()
+    /// Run the query behind the current results page again. Results of statements that
+    /// don't return rows are left alone, since running those again would repeat them.
+    pub async fn refresh_custom_query(&mut self) -> Result<()> {
+        if self.custom_query_rows_affected.is_some()
+            || !sql::returns_rows(&self.custom_query_statement)
         {
-            let num_fields = self.table_data[selected_row_idx].len();
-            if num_fields > 0 {
-                let prev_field_idx = match self.field_selection_state {
-                    Some(current_idx) => {
-                        if current_idx == 0 {
-                            num_fields - 1 // Wrap to last field
-                        } else {
-                            current_idx - 1
+            self.status_message = Some("Only query results can be refreshed".to_string());
+            return Ok(());
+        }
+        let selected = self.table_data_state.selected();
+        self.query_cache.forget(&self.custom_query_statement);
+        self.execute_custom_query().await?;
+        if self.custom_query_current_page > 0
+            && self.custom_query_current_page >= self.custom_query_max_page
+        {
+            self.custom_query_current_page = self.custom_query_max_page.saturating_sub(1);
+            self.execute_custom_query().await?;
+        }
+        reselect_row(
+            &mut self.table_data_state,
+            selected,
+            self.custom_query_result_data.len(),
+        );
+        self.status_message
+            .get_or_insert_with(|| "Refreshed".to_string());
+        Ok(())
+    }
+
+    /// Move to the next page of query results and load it.
+    pub async fn next_custom_query_page(&mut self) -> Result<()> {
+        // Without a row count, keep going while pages are full
+        let more_uncounted = self.custom_query_total_count.is_none()
+            && self.custom_query_result_data.len() == self.items_per_page as usize;
+        if self.custom_query_current_page + 1 < self.custom_query_max_page || more_uncounted {
+            self.custom_query_current_page += 1;
+            self.execute_custom_query().await?;
+        }
+        Ok(())
+    }
+
+    /// Move to the previous page of query results and load it.
+    pub async fn previous_custom_query_page(&mut self) -> Result<()> {
+        if self.custom_query_current_page > 0 {
+            self.custom_query_current_page -= 1;
+            self.execute_custom_query().await?;
+        }
+        Ok(())
+    }
+}
+
+pub async fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    mut app: App,
+    connection_name: String,
+) -> io::Result<()> {
+    // Automatically connect to the specified connection if we're in the Connecting state
+    if matches!(app.state, AppState::Connecting)
+        && let Err(e) = app.connect_to_saved_connection(&connection_name).await
+    {
+        app.error_message = Some(format!("{:#}", e));
+        app.state = AppState::ConnectionError;
+    }
+
+    let mut last_activity = Instant::now();
+    let mut logged_error = None;
+    loop {
+        // Every error the UI shows goes to the log and errors.log too, once
+        if app.error_message != logged_error {
+            if let Some(error) = &app.error_message {
+                tracing::error!("{}", error);
+                let path = crate::config::Config::error_log_path();
+                if let Err(e) =
+                    error_log::append(&path, app.last_connection_attempt.as_deref(), error)
+                {
+                    tracing::warn!("Cannot write to {}: {}", path.display(), e);
+                }
+            }
+            logged_error = app.error_message.clone();
+        }
+        terminal.draw(|f| ui(f, &mut app))?;
+
+        let timeout = match app.state {
+            AppState::CustomQueryInput => CURSOR_BLINK_INTERVAL,
+            AppState::Activity => ACTIVITY_REFRESH_INTERVAL,
+            _ => CONNECTION_CHECK_INTERVAL,
+        };
+        if !event::poll(timeout)? {
+            // Hold still while a cancel or terminate is being confirmed
+            if matches!(app.state, AppState::Activity)
+                && app.activity_confirm.is_none()
+                && let Err(e) = app.refresh_activity().await
+            {
+                app.status_message = Some(format!("Failed to refresh activity: {}", e));
+            }
+            // Notice a connection the server dropped without waiting for the next key
+            if !app.check_connection().await {
+                continue;
+            }
+            if last_activity.elapsed() >= KEEPALIVE_INTERVAL {
+                app.keepalive().await;
+                last_activity = Instant::now();
+            }
+            continue;
+        }
+        last_activity = Instant::now();
+
+        let event = event::read()?;
+        if let Event::Mouse(mouse) = event {
+            // Pointer movement is reported too, but only clicks and scrolling do anything
+            if !matches!(mouse.kind, MouseEventKind::Moved | MouseEventKind::Drag(_))
+                && app.check_connection().await
+            {
+                app.status_message = None;
+                app.handle_mouse(mouse).await;
+            }
+            continue;
+        }
+        if let Event::Paste(text) = event {
+            app.paste(&text);
+            continue;
+        }
+        if let Event::Key(key) = event {
+            app.status_message = None;
+            if key.code != KeyCode::Tab {
+                app.completion = None;
+            }
+            if !app.check_connection().await {
+                continue;
+            }
+            if matches!(app.state, AppState::TableData)
+                && let Some(prompt) = app.row_prompt.as_mut()
+            {
+                match key.code {
+                    KeyCode::Char(c) if c.is_ascii_digit() => prompt.push(c),
+                    KeyCode::Backspace => {
+                        prompt.pop();
+                    }
+                    KeyCode::Enter => {
+                        let input = app.row_prompt.take().unwrap_or_default();
+                        if let Err(e) = app.jump_to_row(&input).await {
+                            app.error_message = Some(format!("Error loading table data: {:#}", e));
+                            app.state = AppState::ConnectionError;
                         }
                     }
-                    None => 0, // Start with first field if none selected yet
+                    KeyCode::Esc => app.row_prompt = None,
+                    _ => {}
+                }
+                continue;
+            }
+            if matches!(app.state, AppState::TableData)
+                && let Some(selected) = app.table_switcher.as_ref().map(ListState::selected)
+            {
+                let len = app.switchable_tables().len();
+                let selected = selected.unwrap_or(0);
+                match key.code {
+                    KeyCode::Tab | KeyCode::Down => {
+                        app.table_switcher =
+                            Some(ListState::default().with_selected(Some((selected + 1) % len)));
+                    }
+                    KeyCode::BackTab | KeyCode::Up => {
+                        app.table_switcher = Some(
+                            ListState::default().with_selected(Some((selected + len - 1) % len)),
+                        );
+                    }
+                    KeyCode::Enter => app.switch_to_recent_table(selected).await,
+                    KeyCode::Char(c @ '1'..='9') => {
+                        app.switch_to_recent_table(c as usize - '1' as usize).await;
+                    }
+                    KeyCode::Esc => app.table_switcher = None,
+                    _ => {}
+                }
+                continue;
+            }
+            if let Some(table) = app.maintenance_confirm.take() {
+                let kind = match key.code {
+                    KeyCode::Char('y') => Some(db::Maintenance::VacuumAnalyze),
+                    KeyCode::Char('a') => Some(db::Maintenance::Analyze),
+                    _ => None,
                 };
-                self.field_selection_state = Some(prev_field_idx);
+                if let Some(kind) = kind {
+                    // Shown while the command runs, which can take a while on a big table
+                    app.status_message = Some(format!("Running {} {}…", kind.command(), table));
+                    terminal.draw(|f| ui(f, &mut app))?;
+                    app.run_maintenance(&table, kind).await;
+                }
+                continue;
             }
-        }
-        // Check if we're in custom query view
-        else if matches!(self.state, AppState::CustomQuery)
-            && let Some(selected_row_idx) = self.table_data_state.selected()
-            && selected_row_idx < self.custom_query_result_data.len()
-        {
-            let num_fields = self.custom_query_result_data[selected_row_idx].len();
-            if num_fields > 0 {
-                let prev_field_idx = match self.field_selection_state {
-                    Some(current_idx) => {
-                        if current_idx == 0 {
-                            num_fields - 1 // Wrap to last field
-                        } else {
-                            current_idx - 1
+            if matches!(app.state, AppState::Activity)
+                && let Some((pid, terminate)) = app.activity_confirm.take()
+            {
+                if key.code == KeyCode::Char('y') {
+                    app.signal_backend(pid, terminate).await;
+                }
+                continue;
+            }
+            if matches!(app.state, AppState::CustomQueryInput)
+                && let Some(typed) = app.destructive_confirm.as_mut()
+            {
+                match key.code {
+                    KeyCode::Char(c) => typed.push(c),
+                    KeyCode::Backspace => {
+                        typed.pop();
+                    }
+                    KeyCode::Enter => {
+                        let confirmed = app.destructive_confirm.take().as_deref() == Some("YES");
+                        if !confirmed {
+                            app.status_message = Some("Query not run".to_string());
+                        } else if let Err(e) = app.start_confirmed_query().await {
+                            app.error_message = Some(format!("Error executing query: {:#}", e));
+                            app.state = AppState::ConnectionError;
+                        }
+                    }
+                    KeyCode::Esc => {
+                        app.destructive_confirm = None;
+                        app.status_message = Some("Query not run".to_string());
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if let Some(name) = app.saved_query_name.as_mut() {
+                match key.code {
+                    KeyCode::Char(c) => name.push(c),
+                    KeyCode::Backspace => {
+                        name.pop();
+                    }
+                    KeyCode::Enter => app.save_query(),
+                    KeyCode::Esc => app.saved_query_name = None,
+                    _ => {}
+                }
+                continue;
+            }
+            if let Some(path) = app.query_file_path.as_mut() {
+                match key.code {
+                    KeyCode::Char(c) => path.push(c),
+                    KeyCode::Backspace => {
+                        path.pop();
+                    }
+                    KeyCode::Enter => app.load_query_file(),
+                    KeyCode::Esc => app.query_file_path = None,
+                    _ => {}
+                }
+                continue;
+            }
+            // A file that couldn't be loaded is reported until the next key
+            if matches!(app.state, AppState::CustomQueryInput) {
+                app.query_file_error = None;
+            }
+            if let Some(selected) = app.saved_query_picker.as_ref().map(ListState::selected) {
+                let len = app.saved_queries().len();
+                let selected = selected.unwrap_or(0);
+                match key.code {
+                    KeyCode::Down => {
+                        app.saved_query_picker =
+                            Some(ListState::default().with_selected(Some((selected + 1) % len)));
+                    }
+                    KeyCode::Up => {
+                        app.saved_query_picker = Some(
+                            ListState::default().with_selected(Some((selected + len - 1) % len)),
+                        );
+                    }
+                    KeyCode::Enter => app.load_saved_query(selected),
+                    KeyCode::Delete => app.remove_saved_query(selected),
+                    KeyCode::Esc => app.saved_query_picker = None,
+                    _ => {}
+                }
+                continue;
+            }
+            if matches!(app.state, AppState::CustomQueryInput)
+                && let Some(prompt) = app.param_prompt.as_mut()
+            {
+                match key.code {
+                    KeyCode::Char(c) => prompt.input().push(c),
+                    KeyCode::Backspace => {
+                        prompt.input().pop();
+                    }
+                    KeyCode::Tab | KeyCode::Down => {
+                        prompt.next();
+                    }
+                    KeyCode::BackTab | KeyCode::Up => prompt.previous(),
+                    KeyCode::Enter => {
+                        if !prompt.next()
+                            && let Err(e) = app.submit_params().await
+                        {
+                            app.error_message = Some(format!("Error executing query: {:#}", e));
+                            app.state = AppState::ConnectionError;
+                        }
+                    }
+                    KeyCode::Esc => app.param_prompt = None,
+                    _ => {}
+                }
+                continue;
+            }
+            if matches!(app.state, AppState::TableData)
+                && let Some(edit) = app.cell_edit.as_mut()
+            {
+                if edit.confirming {
+                    match key.code {
+                        KeyCode::Char('y') | KeyCode::Enter => {
+                            if let Err(e) = app.submit_cell_edit().await {
+                                app.error_message =
+                                    Some(format!("Error loading table data: {:#}", e));
+                                app.state = AppState::ConnectionError;
+                            }
+                        }
+                        KeyCode::Char('n') | KeyCode::Esc => edit.confirming = false,
+                        _ => {}
+                    }
+                } else {
+                    match key.code {
+                        KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                            edit.toggle_null()
+                        }
+                        KeyCode::Char(c) => edit.push_str(c.encode_utf8(&mut [0; 4])),
+                        KeyCode::Backspace => edit.backspace(),
+                        KeyCode::Enter => edit.confirming = true,
+                        KeyCode::Esc => app.cell_edit = None,
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            if matches!(app.state, AppState::TableData)
+                && let Some(form) = app.insert_form.as_mut()
+            {
+                match key.code {
+                    KeyCode::Char(c) => {
+                        if let Some(input) = form.input() {
+                            input.push(c);
+                        }
+                    }
+                    KeyCode::Backspace => {
+                        if let Some(input) = form.input() {
+                            input.pop();
+                        }
+                    }
+                    KeyCode::Tab | KeyCode::Down => {
+                        form.next();
+                    }
+                    KeyCode::BackTab | KeyCode::Up => form.previous(),
+                    // Enter moves through the fields and inserts from the last; F5 inserts
+                    // straight away
+                    KeyCode::Enter | KeyCode::F(5) => {
+                        if (key.code == KeyCode::F(5) || !form.next())
+                            && let Err(e) = app.submit_insert_form().await
+                        {
+                            app.error_message = Some(format!("Error loading table data: {:#}", e));
+                            app.state = AppState::ConnectionError;
+                        }
+                    }
+                    KeyCode::Esc => app.insert_form = None,
+                    _ => {}
+                }
+                continue;
+            }
+            if let Some((table, column)) = app.histogram_confirm.take() {
+                if key.code == KeyCode::Char('y') {
+                    // Shown while the rows are grouped, which can take a while
+                    app.status_message = Some(format!("Counting the values of {}…", column));
+                    terminal.draw(|f| ui(f, &mut app))?;
+                    app.status_message = None;
+                    if let Err(e) = app.open_histogram(table, column).await {
+                        app.status_message = Some(format!("Failed to count values: {}", e));
+                    }
+                }
+                continue;
+            }
+            if matches!(app.state, AppState::TableData) && app.histogram.is_some() {
+                if matches!(key.code, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('H')) {
+                    app.histogram = None;
+                }
+                continue;
+            }
+            if matches!(app.state, AppState::TableData)
+                && let Some(picker) = app.column_picker.as_mut()
+            {
+                match key.code {
+                    KeyCode::Up => picker.previous(),
+                    KeyCode::Down => picker.next(),
+                    KeyCode::Char(' ') => {
+                        app.status_message = (!picker.toggle())
+                            .then(|| "At least one column must stay visible".to_string());
+                    }
+                    KeyCode::Enter => {
+                        if let Err(e) = app.apply_column_picker().await {
+                            app.error_message = Some(format!("Error loading table data: {:#}", e));
+                            app.state = AppState::ConnectionError;
+                        }
+                    }
+                    KeyCode::Esc | KeyCode::Char('C') => app.column_picker = None,
+                    _ => {}
+                }
+                continue;
+            }
+            if matches!(
+                app.state,
+                AppState::ConnectionSelection | AppState::TableList
+            ) && let Some(pattern) = app.list_filter.as_mut()
+            {
+                match key.code {
+                    KeyCode::Char(c) => {
+                        pattern.push(c);
+                        app.apply_list_filter();
+                    }
+                    KeyCode::Backspace => {
+                        pattern.pop();
+                        app.apply_list_filter();
+                    }
+                    KeyCode::Down if matches!(app.state, AppState::ConnectionSelection) => {
+                        app.next_connection()
+                    }
+                    KeyCode::Up if matches!(app.state, AppState::ConnectionSelection) => {
+                        app.previous_connection()
+                    }
+                    KeyCode::Down => app.next_table(),
+                    KeyCode::Up => app.previous_table(),
+                    KeyCode::Enter if matches!(app.state, AppState::ConnectionSelection) => {
+                        app.list_filter = None;
+                        if app.connections_list_state.selected().is_some()
+                            && let Err(e) = app.connect_to_selected().await
+                        {
+                            app.error_message = Some(format!("{:#}", e));
+                            app.state = AppState::ConnectionError;
                         }
                     }
-                    None => 0, // Start with first field if none selected yet
-                };
-                self.field_selection_state = Some(prev_field_idx);
-            }
-        }
-    }
-
-    pub fn enter_field_detail_view(&mut self) {
-        // Check if we're in table data view
-        if matches!(self.state, AppState::TableData)
-            && let Some(selected_row_idx) = self.table_data_state.selected()
-            && selected_row_idx < self.table_data.len()
-        {
-            if let Some(selected_field_idx) = self.field_selection_state {
-                if selected_field_idx < self.table_data[selected_row_idx].len() {
-                    // Store the selected field value for detailed view
-                    self.selected_field_value =
-                        Some(self.table_data[selected_row_idx][selected_field_idx].clone());
-                    // Store the original state for returning later
-                    self.field_detail_origin_state = Some(AppState::TableData);
-                    // Switch to field detail view
-                    self.state = AppState::FieldDetail;
-                    self.field_detail_scroll = 0; // Reset scroll to top
+                    KeyCode::Enter => app.open_selected_table().await,
+                    KeyCode::Esc => app.list_filter = None,
+                    _ => {}
                 }
-            } else if !self.table_data[selected_row_idx].is_empty() {
-                // If no field is selected yet, select the first field
-                self.field_selection_state = Some(0);
-                self.selected_field_value = Some(self.table_data[selected_row_idx][0].clone());
-                // Store the original state for returning later
-                self.field_detail_origin_state = Some(AppState::TableData);
-                self.state = AppState::FieldDetail;
-                self.field_detail_scroll = 0; // Reset scroll to top
+                continue;
             }
-        }
-        // Check if we're in custom query view
-        else if matches!(self.state, AppState::CustomQuery)
-            && let Some(selected_row_idx) = self.table_data_state.selected()
-            && selected_row_idx < self.custom_query_result_data.len()
-        {
-            if let Some(selected_field_idx) = self.field_selection_state {
-                if selected_field_idx < self.custom_query_result_data[selected_row_idx].len() {
-                    // Store the selected field value for detailed view
-                    self.selected_field_value = Some(
-                        self.custom_query_result_data[selected_row_idx][selected_field_idx].clone(),
-                    );
-                    // Store the original state for returning later
-                    self.field_detail_origin_state = Some(AppState::CustomQuery);
-                    // Switch to field detail view
-                    self.state = AppState::FieldDetail;
-                    self.field_detail_scroll = 0; // Reset scroll to top
+            if matches!(app.state, AppState::FieldDetail)
+                && app
+                    .detail_search
+                    .as_ref()
+                    .is_some_and(|search| search.typing)
+            {
+                match key.code {
+                    KeyCode::Char(c) => app.edit_detail_search(|query| query.push(c)),
+                    KeyCode::Backspace => app.edit_detail_search(|query| {
+                        query.pop();
+                    }),
+                    KeyCode::Enter => {
+                        if let Some(search) = app.detail_search.as_mut() {
+                            search.typing = false;
+                        }
+                    }
+                    KeyCode::Esc => app.detail_search = None,
+                    _ => {}
                 }
-            } else if !self.custom_query_result_data[selected_row_idx].is_empty() {
-                // If no field is selected yet, select the first field
-                self.field_selection_state = Some(0);
-                self.selected_field_value =
-                    Some(self.custom_query_result_data[selected_row_idx][0].clone());
-                // Store the original state for returning later
-                self.field_detail_origin_state = Some(AppState::CustomQuery);
-                self.state = AppState::FieldDetail;
-                self.field_detail_scroll = 0; // Reset scroll to top
-            }
-        }
-    }
-
-    pub fn scroll_field_detail_up(&mut self) {
-        if self.field_detail_scroll > 0 {
-            self.field_detail_scroll -= 1;
-        }
-    }
-
-    pub fn scroll_field_detail_down(&mut self) {
-        // We'll update the scroll based on content when rendering, so just increment here
-        // We can't determine the total lines without knowing the terminal height here
-        self.field_detail_scroll += 1;
-    }
-
-    pub async fn execute_custom_query(&mut self) -> Result<()> {
-        if let Some(conn) = &self.connection {
-            let offset = (self.custom_query_current_page * self.items_per_page) as i64;
-            let limit = self.items_per_page as i64;
-
-            let (columns, data) = conn
-                .execute_custom_query(&self.custom_query_input, offset, limit)
-                .await?;
-
-            self.custom_query_result_columns = columns;
-            self.custom_query_result_data = data;
-
-            // Calculate max page based on query count
-            let total_count = conn.get_query_row_count(&self.custom_query_input).await?;
-            self.custom_query_max_page =
-                ((total_count as f64) / (self.items_per_page as f64)).ceil() as u32;
-
-            if !self.custom_query_result_data.is_empty() {
-                self.table_data_state.select(Some(0));
+                continue;
             }
-        }
-        Ok(())
-    }
-
-    pub fn next_custom_query_page(&mut self) {
-        if self.custom_query_current_page < self.custom_query_max_page - 1 {
-            self.custom_query_current_page += 1;
-            self.custom_query_result_data.clear(); // Clear to reload on next render
-        }
-    }
-
-    pub fn previous_custom_query_page(&mut self) {
-        if self.custom_query_current_page > 0 {
-            self.custom_query_current_page -= 1;
-            self.custom_query_result_data.clear(); // Clear to reload on next render
-        }
-    }
-}
-
-pub async fn run_app<B: Backend>(
-    terminal: &mut Terminal<B>,
-    mut app: App,
-    connection_name: String,
-) -> io::Result<()> {
-    // Automatically connect to the specified connection if we're in the Connecting state
-    if matches!(app.state, AppState::Connecting)
-        && let Err(e) = app.connect_to_saved_connection(&connection_name).await
-    {
-        app.error_message = Some(e.to_string());
-        app.state = AppState::ConnectionError;
-    }
-
-    loop {
-        terminal.draw(|f| ui(f, &mut app))?;
-
-        if let Event::Key(key) = event::read()? {
             match app.state {
                 AppState::ConnectionSelection => match key.code {
                     KeyCode::Char('q') => return Ok(()),
                     KeyCode::Esc => return Ok(()), // Keep ESC to quit from main menu
                     KeyCode::Down => app.next_connection(),
                     KeyCode::Up => app.previous_connection(),
+                    KeyCode::Char('u') => app.copy_connection_string(),
+                    KeyCode::Char('o') => app.toggle_connection_order(),
+                    KeyCode::Char('/') => {
+                        app.list_filter = Some(String::new());
+                        app.filtered_list_state = ListState::default();
+                    }
                     KeyCode::Enter => {
                         // Attempt to connect to the selected database
                         if let Err(e) = app.connect_to_selected().await {
-                            app.error_message = Some(e.to_string());
+                            app.error_message = Some(format!("{:#}", e));
                             app.state = AppState::ConnectionError;
                         }
                     }
@@ -597,44 +3239,105 @@ pub async fn run_app<B: Backend>(
                             app.state = AppState::ConnectionSelection;
                             app.error_message = None; // Clear error when going back
                         }
+                        KeyCode::Char('r') => {
+                            if let Some(name) = app.last_connection_attempt.clone() {
+                                retry_connection(terminal, &mut app, &name).await?;
+                            }
+                        }
                         _ => {}
                     }
                 }
                 AppState::TableList => match key.code {
                     KeyCode::Char('q') => return Ok(()),
                     KeyCode::Esc => app.state = AppState::ConnectionSelection,
+                    KeyCode::Char('V') => app.confirm_maintenance(),
+                    KeyCode::Char('L') => app.open_saved_query_picker(),
                     KeyCode::Down => app.next_table(),
                     KeyCode::Up => app.previous_table(),
-                    KeyCode::Enter => {
-                        // Load the selected table's data
-                        if let Some(index) = app.tables_list_state.selected()
-                            && index < app.tables.len()
-                        {
-                            app.current_table = Some(app.tables[index].clone());
-                            // Reset pagination when loading a new table
-                            app.current_page = 0;
-                            app.state = AppState::TableData;
-
-                            // Load data for the selected table
-                            if let Err(e) = app.load_table_data().await {
-                                app.error_message =
-                                    Some(format!("Error loading table data: {}", e));
-                                app.state = AppState::ConnectionError;
-                            }
-                        }
+                    KeyCode::Char('/') => {
+                        app.list_filter = Some(String::new());
+                        app.filtered_list_state = ListState::default();
                     }
+                    KeyCode::Enter => app.open_selected_table().await,
                     KeyCode::Char('c') => app.state = AppState::ConnectionSelection,
                     KeyCode::Char('s') => {
                         // Enter custom query mode
                         app.state = AppState::CustomQueryInput;
                         app.custom_query_input.clear();
+                        app.custom_query_cursor_position = 0;
+                    }
+                    KeyCode::Char('a') => {
+                        if let Err(e) = app.open_activity().await {
+                            app.status_message = Some(format!("Failed to load activity: {}", e));
+                        }
+                    }
+                    KeyCode::Char('t') => {
+                        if let Err(e) = app.open_object_tree().await {
+                            app.status_message = Some(format!("Failed to load schemas: {}", e));
+                        }
+                    }
+                    KeyCode::Char('S') => {
+                        if let Err(e) = app.open_table_stats().await {
+                            app.status_message =
+                                Some(format!("Failed to load table statistics: {}", e));
+                        }
+                    }
+                    KeyCode::Char('d') => {
+                        if let Err(e) = app.describe_selected_table().await {
+                            app.error_message = Some(format!("Error describing table: {:#}", e));
+                            app.state = AppState::ConnectionError;
+                        }
                     }
                     _ => {}
                 },
-                AppState::TableData => match key.code {
+                AppState::ObjectTree => match key.code {
                     KeyCode::Char('q') => return Ok(()),
                     KeyCode::Esc => {
+                        app.object_tree = None;
                         app.state = AppState::TableList;
+                    }
+                    KeyCode::Down => {
+                        if let Some(tree) = app.object_tree.as_mut() {
+                            tree.next();
+                        }
+                    }
+                    KeyCode::Up => {
+                        if let Some(tree) = app.object_tree.as_mut() {
+                            tree.previous();
+                        }
+                    }
+                    KeyCode::Right => {
+                        if let Err(e) = app.expand_tree_node().await {
+                            app.status_message = Some(format!("Failed to expand: {}", e));
+                        }
+                    }
+                    KeyCode::Left => {
+                        if let Some(tree) = app.object_tree.as_mut() {
+                            tree.collapse();
+                        }
+                    }
+                    KeyCode::Enter => {
+                        if let Err(e) = app.open_tree_relation().await {
+                            app.error_message = Some(format!("Error executing query: {:#}", e));
+                            app.state = AppState::ConnectionError;
+                        }
+                    }
+                    _ => {}
+                },
+                AppState::TableDescription => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Esc => app.state = AppState::TableList,
+                    KeyCode::Up => app.scroll_description(-1),
+                    KeyCode::Down => app.scroll_description(1),
+                    KeyCode::PageUp => app.scroll_description(-10),
+                    KeyCode::PageDown => app.scroll_description(10),
+                    _ => {}
+                },
+                AppState::TableData => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Esc if !app.marked_rows.is_empty() => app.clear_marked_rows(),
+                    KeyCode::Esc => {
+                        app.state = app.table_list_state();
                         app.current_table = None;
                         app.field_selection_state = None; // Reset field selection
                     }
@@ -649,26 +3352,92 @@ pub async fn run_app<B: Backend>(
                     KeyCode::Left => app.previous_field(), // Add left arrow for field navigation
                     KeyCode::Right => app.next_field(),    // Add right arrow for field navigation
                     KeyCode::Enter => app.enter_field_detail_view(), // Add enter to view field detail
+                    KeyCode::Char('x') | KeyCode::Char('\\') => app.enter_record_view(),
+                    KeyCode::Char('`') => app.show_sql = !app.show_sql,
+                    KeyCode::Char('I') => app.copy_selected_row_as_insert(),
+                    KeyCode::Char(' ') => app.toggle_row_mark(),
+                    KeyCode::Char('v') => app.mark_row_range(),
+                    KeyCode::Char(key @ ('y' | 'Y' | 'J' | 'M')) => {
+                        let format = match key {
+                            'y' => CopyFormat::Tsv,
+                            'Y' => CopyFormat::Csv,
+                            'J' => CopyFormat::Json,
+                            _ => CopyFormat::Markdown,
+                        };
+                        if let Err(e) = app.copy_marked_rows(format).await {
+                            app.status_message = Some(format!("Failed to copy rows: {}", e));
+                        }
+                    }
+                    KeyCode::Char('g') | KeyCode::Char(':') => app.row_prompt = Some(String::new()),
+                    KeyCode::Tab => app.open_table_switcher(),
+                    KeyCode::Char('T') => {
+                        app.timestamp_display = app.timestamp_display.next();
+                        app.status_message =
+                            Some(format!("Timestamps: {}", app.timestamp_display.label()));
+                    }
+                    KeyCode::Char('<') => app.resize_selected_column(-1),
+                    KeyCode::Char('V') => app.confirm_maintenance(),
+                    KeyCode::Char('>') => app.resize_selected_column(1),
+                    KeyCode::Char('=') => app.reset_selected_column_width(),
+                    KeyCode::Char('h') => app.toggle_column_types(),
+                    KeyCode::Char('b') => {
+                        app.bool_display = app.bool_display.next();
+                        app.status_message =
+                            Some(format!("Booleans: {}", app.bool_display.label()));
+                    }
+                    KeyCode::Char('H') => {
+                        if let Err(e) = app.request_histogram().await {
+                            app.status_message = Some(format!("Failed to count values: {}", e));
+                        }
+                    }
+                    KeyCode::Char('C') => {
+                        if let Err(e) = app.open_column_picker().await {
+                            app.status_message = Some(format!("Failed to list columns: {}", e));
+                        }
+                    }
+                    KeyCode::Char('e') => {
+                        if let Err(e) = app.start_cell_edit().await {
+                            app.status_message = Some(format!("Cannot edit the cell: {}", e));
+                        }
+                    }
+                    KeyCode::Char('a') => {
+                        if let Err(e) = app.open_insert_form().await {
+                            app.status_message = Some(format!("Failed to list columns: {}", e));
+                        }
+                    }
+                    KeyCode::Char('r') | KeyCode::F(5) => {
+                        if let Err(e) = app.refresh_table_data().await {
+                            app.error_message = Some(format!("Error loading table data: {:#}", e));
+                            app.state = AppState::ConnectionError;
+                        }
+                    }
+                    KeyCode::Char('E') if app.total_count_estimated => {
+                        // Replace the estimate with an exact count for this table
+                        if let Some(table) = app.current_table.clone() {
+                            app.page_cache.forget_count(&table);
+                            app.exact_count_tables.insert(table);
+                        }
+                        if let Err(e) = app.load_table_data().await {
+                            app.error_message = Some(format!("Error counting rows: {:#}", e));
+                            app.state = AppState::ConnectionError;
+                        }
+                    }
                     KeyCode::PageDown => {
-                        app.next_page();
                         app.field_selection_state = None; // Reset field selection when changing pages
-                        // Reload data for the new page
-                        if let Err(e) = app.load_table_data().await {
-                            app.error_message = Some(format!("Error loading table data: {}", e));
+                        if let Err(e) = app.next_page().await {
+                            app.error_message = Some(format!("Error loading table data: {:#}", e));
                             app.state = AppState::ConnectionError;
                         }
                     }
                     KeyCode::PageUp => {
-                        app.previous_page();
                         app.field_selection_state = None; // Reset field selection when changing pages
-                        // Reload data for the new page
-                        if let Err(e) = app.load_table_data().await {
-                            app.error_message = Some(format!("Error loading table data: {}", e));
+                        if let Err(e) = app.previous_page().await {
+                            app.error_message = Some(format!("Error loading table data: {:#}", e));
                             app.state = AppState::ConnectionError;
                         }
                     }
                     KeyCode::Char('t') => {
-                        app.state = AppState::TableList;
+                        app.state = app.table_list_state();
                         app.current_table = None;
                         app.field_selection_state = None; // Reset field selection
                     }
@@ -681,79 +3450,266 @@ pub async fn run_app<B: Backend>(
                         // Enter custom query mode
                         app.state = AppState::CustomQueryInput;
                         app.custom_query_input.clear();
+                        app.custom_query_cursor_position = 0;
                         app.field_selection_state = None; // Reset field selection
                     }
                     _ => {}
                 },
                 AppState::CustomQueryInput => match key.code {
-                    KeyCode::Esc => app.state = AppState::TableList,
-                    KeyCode::Enter => {
-                        // Execute the custom query
-                        if !app.custom_query_input.trim().is_empty() {
-                            // Reset pagination
-                            app.custom_query_current_page = 0;
-                            app.state = AppState::CustomQuery;
-
-                            // Execute the query
-                            if let Err(e) = app.execute_custom_query().await {
-                                app.error_message = Some(format!("Error executing query: {}", e));
-                                app.state = AppState::ConnectionError;
-                            }
+                    KeyCode::Esc => app.state = app.table_list_state(),
+                    // Execute the custom query. Ctrl+Enter is only reported by terminals
+                    // that support keyboard enhancements, so F5 does the same.
+                    KeyCode::F(5) | KeyCode::Enter
+                        if (key.code == KeyCode::F(5)
+                            || key.modifiers.contains(KeyModifiers::CONTROL))
+                            && !app.custom_query_input.trim().is_empty() =>
+                    {
+                        // Reset pagination
+                        app.custom_query_current_page = 0;
+
+                        // Execute the query, once any parameters have been filled in
+                        if let Err(e) = app.start_custom_query().await {
+                            app.error_message = Some(format!("Error executing query: {:#}", e));
+                            app.state = AppState::ConnectionError;
                         }
                     }
                     KeyCode::Backspace => {
-                        if app.custom_query_cursor_position > 0 {
-                            // Find the previous character boundary
-                            let mut chars: Vec<char> = app.custom_query_input.chars().collect();
-                            if app.custom_query_cursor_position <= chars.len() {
-                                chars.remove(app.custom_query_cursor_position - 1);
-                                app.custom_query_input = chars.into_iter().collect();
-                                app.custom_query_cursor_position -= 1;
+                        app.custom_query_cursor_position = editor::delete_before(
+                            &mut app.custom_query_input,
+                            app.custom_query_cursor_position,
+                        );
+                    }
+                    KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.transactional = !app.transactional;
+                    }
+                    KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.start_saving_query();
+                    }
+                    KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.open_saved_query_picker();
+                    }
+                    KeyCode::Char('o') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.query_file_path = Some(String::new());
+                    }
+                    KeyCode::Char('e') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        match external_editor::edit(&app.custom_query_input) {
+                            Ok(edited) => {
+                                app.custom_query_cursor_position = edited.chars().count();
+                                app.custom_query_input = edited;
                             }
+                            Err(e) => app.status_message = Some(format!("Query not edited: {}", e)),
+                        }
+                        // The editor drew over the screen ratatui thinks is still there
+                        terminal.clear()?;
+                    }
+                    KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let start = editor::word_left(
+                            &app.custom_query_input,
+                            app.custom_query_cursor_position,
+                        );
+                        app.custom_query_cursor_position = editor::delete_range(
+                            &mut app.custom_query_input,
+                            start,
+                            app.custom_query_cursor_position,
+                        );
+                    }
+                    KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        let start = editor::line_start(
+                            &app.custom_query_input,
+                            app.custom_query_cursor_position,
+                        );
+                        app.custom_query_cursor_position = editor::delete_range(
+                            &mut app.custom_query_input,
+                            start,
+                            app.custom_query_cursor_position,
+                        );
+                    }
+                    KeyCode::Tab => {
+                        if let Err(e) = app.complete_word().await {
+                            app.status_message = Some(format!("Completion failed: {}", e));
                         }
                     }
                     KeyCode::Char(c) => {
-                        // Convert to chars, insert at position, then convert back
-                        let mut chars: Vec<char> = app.custom_query_input.chars().collect();
-                        if app.custom_query_cursor_position <= chars.len() {
-                            chars.insert(app.custom_query_cursor_position, c);
-                            app.custom_query_input = chars.into_iter().collect();
-                            app.custom_query_cursor_position += 1;
+                        app.custom_query_cursor_position = editor::insert_char(
+                            &mut app.custom_query_input,
+                            app.custom_query_cursor_position,
+                            c,
+                        );
+                    }
+                    KeyCode::Enter => {
+                        app.custom_query_cursor_position = editor::insert_char(
+                            &mut app.custom_query_input,
+                            app.custom_query_cursor_position,
+                            '\n',
+                        );
+                    }
+                    KeyCode::Up => {
+                        app.custom_query_cursor_position = editor::move_up(
+                            &app.custom_query_input,
+                            app.custom_query_cursor_position,
+                        );
+                    }
+                    KeyCode::Down => {
+                        app.custom_query_cursor_position = editor::move_down(
+                            &app.custom_query_input,
+                            app.custom_query_cursor_position,
+                        );
+                    }
+                    KeyCode::Left if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.custom_query_cursor_position = editor::word_left(
+                            &app.custom_query_input,
+                            app.custom_query_cursor_position,
+                        );
+                    }
+                    KeyCode::Right if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        app.custom_query_cursor_position = editor::word_right(
+                            &app.custom_query_input,
+                            app.custom_query_cursor_position,
+                        );
+                    }
+                    KeyCode::Left if app.custom_query_cursor_position > 0 => {
+                        app.custom_query_cursor_position -= 1;
+                    }
+                    KeyCode::Right => {
+                        app.custom_query_cursor_position = editor::move_right(
+                            &app.custom_query_input,
+                            app.custom_query_cursor_position,
+                        );
+                    }
+                    KeyCode::Home => {
+                        app.custom_query_cursor_position = editor::line_start(
+                            &app.custom_query_input,
+                            app.custom_query_cursor_position,
+                        );
+                    }
+                    KeyCode::End => {
+                        app.custom_query_cursor_position = editor::line_end(
+                            &app.custom_query_input,
+                            app.custom_query_cursor_position,
+                        );
+                    }
+                    _ => {}
+                },
+                AppState::FieldDetail => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Esc => {
+                        // Return to the original state
+                        app.state = app
+                            .field_detail_origin_state
+                            .clone()
+                            .unwrap_or(AppState::TableData);
+                    }
+                    KeyCode::Up => app.scroll_field_detail_up(),
+                    KeyCode::Down => app.scroll_field_detail_down(),
+                    KeyCode::Char('/') => {
+                        app.detail_search = Some(TextSearch::new());
+                    }
+                    KeyCode::Char('n') => {
+                        if let Some(search) = app.detail_search.as_mut() {
+                            search.step(true);
                         }
                     }
-                    KeyCode::Left => {
-                        if app.custom_query_cursor_position > 0 {
-                            app.custom_query_cursor_position -= 1;
+                    KeyCode::Char('N') => {
+                        if let Some(search) = app.detail_search.as_mut() {
+                            search.step(false);
                         }
                     }
-                    KeyCode::Right => {
-                        if app.custom_query_cursor_position < app.custom_query_input.len() {
-                            app.custom_query_cursor_position += 1;
+                    _ => {}
+                },
+                AppState::Activity => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Esc => app.state = AppState::TableList,
+                    KeyCode::Down => app.move_activity_selection(true),
+                    KeyCode::Up => app.move_activity_selection(false),
+                    KeyCode::Enter => app.view_backend_query(),
+                    KeyCode::Char('r') | KeyCode::F(5) => {
+                        if let Err(e) = app.refresh_activity().await {
+                            app.status_message = Some(format!("Failed to refresh activity: {}", e));
                         }
                     }
-                    KeyCode::Home => {
-                        app.custom_query_cursor_position = 0;
+                    KeyCode::Char('i') | KeyCode::Char('l') => {
+                        let filter = if key.code == KeyCode::Char('i') {
+                            ActivityFilter::IdleInTransaction
+                        } else {
+                            ActivityFilter::LockWaits
+                        };
+                        if let Err(e) = app.toggle_activity_filter(filter).await {
+                            app.status_message = Some(format!("Failed to refresh activity: {}", e));
+                        }
+                    }
+                    KeyCode::Char('C') | KeyCode::Char('K') => {
+                        let terminate = key.code == KeyCode::Char('K');
+                        app.activity_confirm = app
+                            .selected_backend()
+                            .map(|backend| (backend.pid, terminate));
+                    }
+                    _ => {}
+                },
+                AppState::TableStats => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Esc => app.state = AppState::TableList,
+                    KeyCode::Down => app.move_table_stats_selection(true),
+                    KeyCode::Up => app.move_table_stats_selection(false),
+                    KeyCode::Right => app.change_stats_sort(1),
+                    KeyCode::Left => app.change_stats_sort(-1),
+                    KeyCode::Char('o') => app.change_stats_sort(0),
+                    KeyCode::Enter => {
+                        if let Err(e) = app.open_selected_stats_table().await {
+                            app.status_message = Some(e.to_string());
+                        }
                     }
-                    KeyCode::End => {
-                        app.custom_query_cursor_position = app.custom_query_input.len();
+                    KeyCode::Char('r') | KeyCode::F(5) => {
+                        if let Err(e) = app.refresh_table_stats().await {
+                            app.status_message =
+                                Some(format!("Failed to refresh table statistics: {}", e));
+                        }
                     }
                     _ => {}
                 },
-                AppState::FieldDetail => match key.code {
+                AppState::RecordView => match key.code {
                     KeyCode::Char('q') => return Ok(()),
-                    KeyCode::Esc => {
-                        // Return to the original state
+                    KeyCode::Esc | KeyCode::Char('x') | KeyCode::Char('\\') => {
                         app.state = app
-                            .field_detail_origin_state
-                            .clone()
+                            .record_origin_state
+                            .take()
                             .unwrap_or(AppState::TableData);
                     }
-                    KeyCode::Up => app.scroll_field_detail_up(),
-                    KeyCode::Down => app.scroll_field_detail_down(),
+                    KeyCode::Up => app.scroll_record(-1),
+                    KeyCode::Down => app.scroll_record(1),
+                    KeyCode::PageUp => app.scroll_record(-10),
+                    KeyCode::PageDown => app.scroll_record(10),
+                    _ => {}
+                },
+                AppState::TransactionPending => match key.code {
+                    KeyCode::Char('c') => {
+                        if let Err(e) = app.finish_transaction(true).await {
+                            app.error_message = Some(format!("{:#}", e));
+                            app.state = AppState::ConnectionError;
+                        }
+                    }
+                    KeyCode::Char('r') => {
+                        if let Err(e) = app.finish_transaction(false).await {
+                            app.error_message = Some(format!("{:#}", e));
+                            app.state = AppState::ConnectionError;
+                        }
+                    }
+                    KeyCode::Char('q') => {
+                        // Don't leave the decision to the server's disconnect handling
+                        let _ = app.finish_transaction(false).await;
+                        return Ok(());
+                    }
+                    KeyCode::Down => app.next_row(),
+                    KeyCode::Up => app.previous_row(),
                     _ => {}
                 },
                 AppState::CustomQuery => match key.code {
                     KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Char('r') | KeyCode::F(5) => {
+                        if let Err(e) = app.refresh_custom_query().await {
+                            app.error_message = Some(format!("Error loading query data: {:#}", e));
+                            app.state = AppState::ConnectionError;
+                        }
+                    }
                     KeyCode::Esc => {
                         app.state = AppState::CustomQueryInput;
                         app.field_selection_state = None; // Reset field selection
@@ -769,26 +3725,29 @@ pub async fn run_app<B: Backend>(
                     KeyCode::Left => app.previous_field(), // Add left arrow for field navigation
                     KeyCode::Right => app.next_field(),    // Add right arrow for field navigation
                     KeyCode::Enter => app.enter_field_detail_view(), // Add enter to view field detail
+                    KeyCode::Char('x') | KeyCode::Char('\\') => app.enter_record_view(),
+                    KeyCode::Char('`') => app.show_sql = !app.show_sql,
+                    KeyCode::Char('M') => {
+                        if let Err(e) = app.copy_query_results(CopyFormat::Markdown).await {
+                            app.status_message = Some(format!("Failed to copy rows: {}", e));
+                        }
+                    }
                     KeyCode::PageDown => {
-                        app.next_custom_query_page();
                         app.field_selection_state = None; // Reset field selection when changing pages
-                        // Reload data for the new page
-                        if let Err(e) = app.execute_custom_query().await {
-                            app.error_message = Some(format!("Error loading query data: {}", e));
+                        if let Err(e) = app.next_custom_query_page().await {
+                            app.error_message = Some(format!("Error loading query data: {:#}", e));
                             app.state = AppState::ConnectionError;
                         }
                     }
                     KeyCode::PageUp => {
-                        app.previous_custom_query_page();
                         app.field_selection_state = None; // Reset field selection when changing pages
-                        // Reload data for the new page
-                        if let Err(e) = app.execute_custom_query().await {
-                            app.error_message = Some(format!("Error loading query data: {}", e));
+                        if let Err(e) = app.previous_custom_query_page().await {
+                            app.error_message = Some(format!("Error loading query data: {:#}", e));
                             app.state = AppState::ConnectionError;
                         }
                     }
                     KeyCode::Char('t') => {
-                        app.state = AppState::TableList;
+                        app.state = app.table_list_state();
                         app.field_selection_state = None; // Reset field selection
                     }
                     KeyCode::Char('c') => {
@@ -807,16 +3766,83 @@ pub async fn run_app<B: Backend>(
     }
 }
 
+// Connect again to the connection that failed, drawing the connecting screen with its
+// spinner while waiting
+async fn retry_connection<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+    name: &str,
+) -> io::Result<()> {
+    app.error_message = None;
+    app.connection = None;
+    app.connection_status = Some(format!("Reconnecting to {}...", name));
+    app.state = AppState::Connecting;
+    let mut opening = match app.connection_opener(name) {
+        Ok(opener) => tokio::spawn(opener),
+        Err(e) => {
+            app.finish_connecting(name, Err(e)).await;
+            return Ok(());
+        }
+    };
+
+    let mut frames = tokio::time::interval(SPINNER_INTERVAL);
+    let result = loop {
+        tokio::select! {
+            result = &mut opening => break result,
+            _ = frames.tick() => {
+                terminal.draw(|f| ui(f, app))?;
+            }
+        }
+    };
+    let result = result.unwrap_or_else(|e| Err(anyhow::anyhow!("Connecting failed: {}", e)));
+    app.finish_connecting(name, result).await;
+    Ok(())
+}
+
 fn ui(f: &mut Frame, app: &mut App) {
     let size = f.area();
-
-    // If there's a connection status message, show it at the top
-    if let Some(ref status) = app.connection_status {
-        let status_paragraph = Paragraph::new(Text::styled(
-            status.as_str(),
-            Style::default().fg(Color::Green),
-        ))
-        .block(Block::default().borders(Borders::NONE));
+    app.click_targets = ClickTargets::default();
+    app.cell_hint = None;
+
+    // If there's a status message, show it at the top
+    if let Some(table) = &app.maintenance_confirm {
+        let prompt = format!(
+            "VACUUM (ANALYZE) {}? Press 'y' to run it, 'a' for ANALYZE only, any other key to cancel",
+            table
+        );
+        f.render_widget(
+            Paragraph::new(Text::styled(prompt, app.theme.accent)),
+            ratatui::layout::Rect {
+                x: 0,
+                y: 0,
+                width: size.width,
+                height: 1,
+            },
+        );
+    } else if let Some((table, column)) = &app.histogram_confirm {
+        let prompt = format!(
+            "Counting the values of {} reads all {}{} rows of {}. Press 'y' to go ahead, any other key to cancel",
+            column,
+            if app.total_count_estimated { "~" } else { "" },
+            app.total_count,
+            table
+        );
+        f.render_widget(
+            Paragraph::new(Text::styled(prompt, app.theme.accent)),
+            ratatui::layout::Rect {
+                x: 0,
+                y: 0,
+                width: size.width,
+                height: 1,
+            },
+        );
+    } else if let Some(status) = app
+        .status_message
+        .as_ref()
+        .or(app.connection_status.as_ref())
+    {
+        let status_paragraph = Paragraph::new(Text::styled(status.as_str(), app.theme.status))
+            .block(Block::default().borders(Borders::NONE));
         let status_area = ratatui::layout::Rect {
             x: 0,
             y: 0,
@@ -828,11 +3854,8 @@ fn ui(f: &mut Frame, app: &mut App) {
 
     // If there's an error message, show it at the top
     if let Some(ref error) = app.error_message {
-        let error_paragraph = Paragraph::new(Text::styled(
-            error.as_str(),
-            Style::default().fg(Color::Red),
-        ))
-        .block(Block::default().borders(Borders::NONE));
+        let error_paragraph = Paragraph::new(Text::styled(error.as_str(), app.theme.error))
+            .block(Block::default().borders(Borders::NONE));
         let error_area = ratatui::layout::Rect {
             x: 0,
             y: 0,
@@ -848,7 +3871,24 @@ fn ui(f: &mut Frame, app: &mut App) {
         .constraints([Constraint::Length(1), Constraint::Min(0)].as_ref()) // Status bar + main content
         .split(size);
 
-    let main_area = content_chunks[1];
+    let mut main_area = content_chunks[1];
+
+    // The statement behind the table or results, below them
+    if app.show_sql
+        && let Some(sql) = app.page_sql()
+    {
+        let lines = sql.lines().count() as u16;
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length((lines + 2).min(10))])
+            .split(main_area);
+        let panel = Paragraph::new(sql.to_string())
+            .block(Block::default().borders(Borders::ALL).title("SQL"))
+            .style(app.theme.help)
+            .wrap(Wrap { trim: false });
+        f.render_widget(panel, chunks[1]);
+        main_area = chunks[0];
+    }
 
     match app.state {
         AppState::ConnectionSelection => render_connection_selection(f, app, main_area),
@@ -857,32 +3897,176 @@ fn ui(f: &mut Frame, app: &mut App) {
         AppState::TableList => render_table_list(f, app, main_area),
         AppState::TableData => render_table_data(f, app, main_area),
         AppState::FieldDetail => render_field_detail(f, app, main_area),
+        AppState::TableDescription => render_table_description(f, app, main_area),
+        AppState::RecordView => render_record_view(f, app, main_area),
+        AppState::Activity => render_activity(f, app, main_area),
+        AppState::TableStats => render_table_stats(f, app, main_area),
+        AppState::ObjectTree => render_object_tree(f, app, main_area),
         AppState::CustomQueryInput => render_custom_query_input(f, app, main_area),
-        AppState::CustomQuery => render_custom_query_results(f, app, main_area),
+        AppState::CustomQuery | AppState::TransactionPending => {
+            render_custom_query_results(f, app, main_area)
+        }
+    }
+
+    if let Some(name) = &app.saved_query_name {
+        let title = " Save query: Enter to save, ESC to cancel ";
+        render_prompt(f, title, "Name: ", name, &app.theme, main_area);
+    }
+    if let Some(path) = &app.query_file_path {
+        let title = " Load SQL from a file: Enter to insert it, ESC to cancel ";
+        render_prompt(f, title, "File: ", path, &app.theme, main_area);
+    }
+    if app.saved_query_picker.is_some() {
+        render_saved_query_picker(f, app, main_area);
+    }
+
+    // Drawn over the right end of the status bar, unless there's a message to show
+    if app.status_message.is_none()
+        && let Some(hint) = &app.cell_hint
+    {
+        let hint = Paragraph::new(Text::styled(hint.as_str(), app.theme.status))
+            .alignment(ratatui::layout::Alignment::Right);
+        f.render_widget(hint, content_chunks[0]);
+    }
+}
+
+// Copy rows to the clipboard in `format`, returning the message saying how that went
+fn copy_rows(format: CopyFormat, rows: &TypedRows) -> String {
+    let text = format.render(rows);
+    let count = rows.rows.len();
+    let label = format.label();
+    match clipboard::copy_to_clipboard(&text) {
+        Ok(()) if count == 1 => format!("Copied 1 row as {} to clipboard", label),
+        Ok(()) => format!("Copied {} rows as {} to clipboard", count, label),
+        Err(e) => format!("Failed to copy to clipboard: {}", e),
+    }
+}
+
+// The page and the index within it of a 1-based row number
+fn row_location(row: u64, items_per_page: u32) -> (u32, usize) {
+    let index = row.saturating_sub(1);
+    (
+        (index / items_per_page as u64) as u32,
+        (index % items_per_page as u64) as usize,
+    )
+}
+
+// Move `table` to the front of a most-recent-first list, dropping the oldest past the limit
+fn push_recent(list: &mut Vec<String>, table: &str) {
+    list.retain(|t| t != table);
+    list.insert(0, table.to_string());
+    list.truncate(RECENT_TABLES_LIMIT);
+}
+
+// Select the row at `previous` again after a reload, or the last row if there are now fewer
+fn reselect_row(state: &mut TableState, previous: Option<usize>, len: usize) {
+    state.select(match len {
+        0 => None,
+        _ => Some(previous.unwrap_or(0).min(len - 1)),
+    });
+}
+
+// Where to start reading a page: next to the loaded page when stepping to a neighbour
+// (keyset pagination), otherwise by offset
+fn page_cursor(bounds: Option<&PageBounds>, table: &str, page: u32, offset: i64) -> PageCursor {
+    match bounds {
+        Some(b) if b.table == table && b.page + 1 == page => PageCursor::After(b.last_key.clone()),
+        Some(b) if b.table == table && page + 1 == b.page => {
+            PageCursor::Before(b.first_key.clone())
+        }
+        _ => PageCursor::Offset(offset),
+    }
+}
+
+// A page of a custom query's results and the query's total row count, which is counted
+// separately when fetching the page didn't establish it, with how long each took. Failing
+// to count doesn't fail the page.
+async fn fetch_query_page(
+    db: &impl Database,
+    statement: &str,
+    params: &[Option<String>],
+    offset: i64,
+    limit: i64,
+    max_count: Option<u64>,
+) -> Result<(QueryPage, Result<i64>, QueryTiming)> {
+    let started = Instant::now();
+    let page = db
+        .execute_custom_query(statement, params, offset, limit)
+        .await?;
+    let mut timing = QueryTiming {
+        execute: Some(started.elapsed()),
+        count: None,
+    };
+    let total_count = match page.total_rows {
+        Some(total) => Ok(total),
+        None => {
+            let started = Instant::now();
+            let count = db.get_query_row_count(statement, params, max_count).await;
+            timing.count = Some(started.elapsed());
+            count
+        }
+    };
+    Ok((page, total_count.map_err(anyhow::Error::from), timing))
+}
+
+// Count a table's rows as the mode asks, returning the count and whether it's an estimate
+async fn count_table_rows(
+    conn: &DatabaseConnection,
+    table: &str,
+    mode: RowCountMode,
+) -> Result<(i64, bool)> {
+    if mode != RowCountMode::Exact
+        && let Some(estimate) = conn.get_table_row_estimate(table).await?
+        && (mode == RowCountMode::Estimate || estimate >= EXACT_COUNT_LIMIT)
+    {
+        return Ok((estimate, true));
     }
+    Ok((conn.get_table_count(table).await?, false))
 }
 
 fn render_connection_selection(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
-    let connections = app.config.list_connections();
+    let connections = app.connection_names();
+    let visible = app.visible_connections();
 
-    let items: Vec<ListItem> = connections
+    let items: Vec<ListItem> = visible
         .iter()
-        .map(|name| ListItem::new(name.as_str()))
+        .map(|&i| ListItem::new(connections[i].as_str()))
         .collect();
 
     let list = List::new(items)
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title("Select Connection"),
-        )
-        .highlight_style(
-            Style::default()
-                .bg(Color::LightGreen)
-                .add_modifier(Modifier::BOLD),
-        );
+        .block(Block::default().borders(Borders::ALL).title(filtered_title(
+            &format!(
+                "Select Connection ({})",
+                connection_order_label(app.config.settings.connection_order)
+            ),
+            &app.list_filter,
+        )))
+        .highlight_style(app.theme.list_selection);
+
+    match app.list_filter {
+        Some(_) => {
+            let selected = app.connections_list_state.selected();
+            app.filtered_list_state
+                .select(selected.and_then(|s| visible.iter().position(|&v| v == s)));
+            f.render_stateful_widget(list, area, &mut app.filtered_list_state);
+        }
+        None => f.render_stateful_widget(list, area, &mut app.connections_list_state),
+    }
+}
+
+fn connection_order_label(order: ConnectionOrder) -> &'static str {
+    match order {
+        ConnectionOrder::Recent => "recent first",
+        ConnectionOrder::Name => "by name",
+    }
+}
 
-    f.render_stateful_widget(list, area, &mut app.connections_list_state);
+// A list's title, with the filter being typed when there is one
+fn filtered_title(title: &str, filter: &Option<String>) -> String {
+    match filter {
+        Some(pattern) => format!("{} (filter: {}|)", title, pattern),
+        None => title.to_string(),
+    }
 }
 
 fn render_connecting(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
@@ -891,16 +4075,23 @@ fn render_connecting(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect)
     } else {
         "Connecting..."
     };
-
-    let paragraph = Paragraph::new(Span::raw(text))
+    const SPINNER: [char; 10] = ['⠋', '⠙', '⠹', '⠸', '⠼', '⠴', '⠦', '⠧', '⠇', '⠏'];
+    let frame = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+        / SPINNER_INTERVAL.as_millis();
+    let spinner = SPINNER[frame as usize % SPINNER.len()];
+
+    let paragraph = Paragraph::new(Span::raw(format!("{} {}", spinner, text)))
         .block(Block::default().borders(Borders::ALL).title("Status"))
-        .style(Style::default().fg(Color::Yellow));
+        .style(app.theme.accent);
 
     f.render_widget(paragraph, area);
 
     let help_text = Paragraph::new(Span::raw("Press ESC to go back, 'q' to quit"))
         .block(Block::default().borders(Borders::NONE))
-        .style(Style::default().add_modifier(Modifier::ITALIC));
+        .style(app.theme.help);
 
     // Position help text at the bottom
     let help_area = ratatui::layout::Rect {
@@ -921,15 +4112,15 @@ fn render_connection_error(f: &mut Frame, app: &mut App, area: ratatui::layout::
 
     let paragraph = Paragraph::new(Span::raw(error_text))
         .block(Block::default().borders(Borders::ALL).title("Error"))
-        .style(Style::default().fg(Color::Red));
+        .style(app.theme.error);
 
     f.render_widget(paragraph, area);
 
     let help_text = Paragraph::new(Span::raw(
-        "Press 'c' or ESC to go back to connection selection, 'q' to quit",
+        "Press 'r' to retry the connection, 'c' or ESC to go back to connection selection, 'q' to quit",
     ))
     .block(Block::default().borders(Borders::NONE))
-    .style(Style::default().add_modifier(Modifier::ITALIC));
+    .style(app.theme.help);
 
     // Position help text at the bottom
     let help_area = ratatui::layout::Rect {
@@ -942,27 +4133,36 @@ fn render_connection_error(f: &mut Frame, app: &mut App, area: ratatui::layout::
 }
 
 fn render_table_list(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
-    let items: Vec<ListItem> = app
-        .tables
+    let visible = app.visible_tables();
+    let items: Vec<ListItem> = visible
         .iter()
-        .map(|name| ListItem::new(name.as_str()))
+        .map(|&i| ListItem::new(app.tables[i].as_str()))
         .collect();
 
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(filtered_title("Tables", &app.list_filter));
+    app.click_targets.list = Some(block.inner(area));
     let list = List::new(items)
-        .block(Block::default().borders(Borders::ALL).title("Tables"))
-        .highlight_style(
-            Style::default()
-                .bg(Color::LightGreen)
-                .add_modifier(Modifier::BOLD),
-        );
-
-    f.render_stateful_widget(list, area, &mut app.tables_list_state);
+        .block(block)
+        .highlight_style(app.theme.list_selection);
+
+    match app.list_filter {
+        Some(_) => {
+            let selected = app.tables_list_state.selected();
+            app.filtered_list_state
+                .select(selected.and_then(|s| visible.iter().position(|&v| v == s)));
+            f.render_stateful_widget(list, area, &mut app.filtered_list_state);
+        }
+        None => f.render_stateful_widget(list, area, &mut app.tables_list_state),
+    }
 
-    let help_text = Paragraph::new(Span::raw(
-        "Use ↑↓ to navigate, Enter to select, 's' for SQL query, 'c' for connections, ESC for back, 'q' to quit",
-    ))
+    let help_text = Paragraph::new(Span::raw(match app.list_filter {
+        Some(_) => "Type to filter, ↑↓ to navigate, Enter to select, ESC to clear the filter",
+        None => "Use ↑↓ to navigate, Enter to select, '/' to filter, 'd' to describe, 't' for the object tree, 'S' for table stats, 'V' to vacuum, 's' for SQL query, 'L' for saved queries, 'c' for connections, ESC for back, 'q' to quit",
+    }))
     .block(Block::default().borders(Borders::NONE))
-    .style(Style::default().add_modifier(Modifier::ITALIC));
+    .style(app.theme.help);
 
     // Position help text at the bottom
     let help_area = ratatui::layout::Rect {
@@ -980,16 +4180,9 @@ fn render_table_data(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect)
     let mut column_types: Vec<String> = Vec::new();
 
     for column in &app.table_columns {
-        if let Some(pos) = column.find(" (") {
-            let name = &column[..pos];
-            let type_part = &column[pos + 2..column.len() - 1]; // Remove the trailing ')'
-            column_names.push(name.to_string());
-            column_types.push(type_part.to_string());
-        } else {
-            // If no type information is present, just use the column name as is
-            column_names.push(column.to_string());
-            column_types.push("".to_string());
-        }
+        let (name, type_part) = split_column_header(column);
+        column_names.push(name);
+        column_types.push(type_part);
     }
 
     // Create headers for the table - column names
@@ -999,15 +4192,44 @@ fn render_table_data(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect)
     let header_types: Vec<Span> = column_types.iter().map(|t| Span::raw(t.as_str())).collect();
 
     // Create header rows
-    let header_row_names = Row::new(header_names)
-        .height(1)
-        .style(Style::default().add_modifier(Modifier::BOLD));
+    let header_row_names = Row::new(header_names).height(1).style(app.theme.header);
 
     let header_row_types = Row::new(header_types)
         .height(1)
-        .style(Style::default().add_modifier(Modifier::ITALIC));
+        .style(app.theme.header_types);
+
+    let timestamp_format = app
+        .config
+        .settings
+        .timestamp_format
+        .as_deref()
+        .unwrap_or(timestamps::DEFAULT_FORMAT);
+    let is_timestamp: Vec<bool> = column_types
+        .iter()
+        .map(|t| timestamps::is_timestamp_type(t))
+        .collect();
+    let is_array: Vec<bool> = column_types
+        .iter()
+        .map(|t| arrays::is_array_type(t))
+        .collect();
+    let is_boolean: Vec<bool> = column_types
+        .iter()
+        .map(|t| booleans::is_boolean_type(t))
+        .collect();
+    let is_composite: Vec<bool> = (0..column_types.len())
+        .map(|j| matches!(app.column_user_type(j), Some(db::UserType::Composite(_))))
+        .collect();
+
+    let show_types = app.config.settings.show_column_types;
+    let widths = app.table_column_widths(area.width.saturating_sub(2));
+    app.click_targets.set_table(
+        Block::default().borders(Borders::ALL).inner(area),
+        &widths,
+        if show_types { 2 } else { 1 },
+    );
 
     // Create rows for the table
+    let mut cell_hint = None;
     let rows: Vec<Row> = app
         .table_data
         .iter()
@@ -1016,7 +4238,26 @@ fn render_table_data(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect)
             let cells: Vec<Span> = row
                 .iter()
                 .enumerate()
-                .map(|(j, cell)| {
+                .map(|(j, value)| {
+                    let shown = if is_timestamp.get(j) == Some(&true) {
+                        timestamps::display(value, app.timestamp_display, timestamp_format)
+                    } else if is_array.get(j) == Some(&true) {
+                        arrays::grid_text(value)
+                    } else if is_boolean.get(j) == Some(&true) {
+                        booleans::display(value, app.bool_display)
+                    } else if is_composite.get(j) == Some(&true) {
+                        composites::grid_text(value)
+                    } else {
+                        None
+                    };
+                    let cell = shown.map_or(value.as_str().into(), std::borrow::Cow::Owned);
+                    let (cell, cut) = fit_cell(cell, app.click_targets.columns.get(j));
+                    if Some(i) == app.table_data_state.selected()
+                        && app.field_selection_state == Some(j)
+                        && cut
+                    {
+                        cell_hint = Some(cells::truncated_hint(value));
+                    }
                     // Check if this cell is selected
                     let mut cell_style = Style::default();
                     if Some(i) == app.table_data_state.selected()
@@ -1024,44 +4265,58 @@ fn render_table_data(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect)
                         && app.field_selection_state.unwrap() == j
                     {
                         // This is the currently selected field in the selected row
-                        cell_style = Style::default().bg(Color::Yellow).fg(Color::Black);
+                        cell_style = app.theme.field_selection;
                     } else if Some(i) == app.table_data_state.selected() {
                         // This is in the currently selected row
-                        cell_style = Style::default().bg(Color::LightBlue);
+                        cell_style = app.theme.row_selection;
+                    } else if app.marked_rows.contains(&i) {
+                        cell_style = app.theme.row_marked;
                     }
-                    Span::styled(cell.as_str(), cell_style)
+                    Span::styled(cell, cell_style)
                 })
                 .collect();
             Row::new(cells).height(1)
         })
         .collect();
 
+    app.cell_hint = cell_hint;
+
     // Combine headers and data rows into a single table
     let mut table_rows = Vec::new();
     table_rows.push(header_row_names);
-    table_rows.push(header_row_types);
+    if show_types {
+        table_rows.push(header_row_types);
+    }
     table_rows.extend(rows);
 
-    let widths: Vec<Constraint> = app
-        .table_columns
-        .iter()
-        .map(|_| Constraint::Percentage(100 / app.table_columns.len().max(1) as u16))
-        .collect();
-
     let table = Table::new(table_rows, widths).block(Block::default().borders(Borders::ALL).title(
         format!(
-            "Table: {} (Page {}/{})",
+            "Table: {} (Page {}/{}{}{}){}{}",
             app.current_table.as_ref().unwrap_or(&"Unknown".to_string()),
             app.current_page + 1,
-            app.max_page
+            if app.total_count_estimated { "~" } else { "" },
+            app.max_page.max(1), // An empty table still shows its one empty page
+            app.row_position_label(Some(app.total_count), app.total_count_estimated),
+            match app.marked_rows.len() {
+                0 => String::new(),
+                marked => format!(" [{} marked]", marked),
+            },
+            timing_suffix(&app.table_timing)
         ),
     ));
 
     f.render_stateful_widget(table, area, &mut app.table_data_state);
 
-    let help_text = Paragraph::new(Span::raw("Use ↑↓ to navigate rows, ←→ to navigate fields in row, Enter to view field detail, PageUp/PageDown to change pages, 't' for tables, ESC for back, 'c' for connections, 'q' to quit"))
-        .block(Block::default().borders(Borders::NONE))
-        .style(Style::default().add_modifier(Modifier::ITALIC));
+    let help_text = match &app.row_prompt {
+        Some(prompt) => Paragraph::new(format!(
+            "Go to row: {}|  (Enter to jump, ESC to cancel)",
+            prompt
+        ))
+        .style(app.theme.accent),
+        None => Paragraph::new(Span::raw("Use ↑↓ to navigate rows, ←→ to navigate fields in row, Enter to view field detail, '<'/'>' to resize the column ('=' to reset), 'x' for the whole record, 'g' to go to a row, Tab for recent tables, 'C' to choose columns, 'H' for the column's commonest values, 'h' to show or hide column types, 'T' to change how timestamps are shown, 'b' for booleans, 'I' to copy row as INSERT, 'a' to insert a row, 'e' to edit the cell, Space/'v' to mark rows and 'y'/'Y'/'J'/'M' to copy them as TSV/CSV/JSON/Markdown, 'E' for an exact row count, 'V' to vacuum, '`' to show the SQL, 'r' to refresh, PageUp/PageDown to change pages, 't' for tables, ESC for back, 'c' for connections, 'q' to quit"))
+            .block(Block::default().borders(Borders::NONE))
+            .style(app.theme.help),
+    };
 
     // Position help text at the bottom
     let help_area = ratatui::layout::Rect {
@@ -1070,84 +4325,1019 @@ fn render_table_data(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect)
         width: area.width,
         height: 2,
     };
-    f.render_widget(help_text, help_area);
+    f.render_widget(help_text, help_area);
+
+    if let Some(picker) = &mut app.column_picker {
+        render_column_picker(f, picker, &app.theme, area);
+    }
+    if let Some(histogram) = &app.histogram {
+        render_histogram(f, histogram, area);
+    }
+    if let Some(form) = &app.insert_form {
+        render_insert_form(f, form, &app.theme, area);
+    }
+    if let Some(edit) = &app.cell_edit {
+        render_cell_edit(f, edit, &app.theme, area);
+    }
+    if app.table_switcher.is_some() {
+        render_table_switcher(f, app, area);
+    }
+}
+
+// Widths for a grid's columns within `available` columns, including the space between
+// them. Columns with a `fixed` width keep it and the rest are sized to their `content`
+fn column_widths(fixed: &[Option<u16>], content: &[u16], available: u16) -> Vec<Constraint> {
+    let spacing = fixed.len().saturating_sub(1) as u16;
+    let taken = fixed
+        .iter()
+        .flatten()
+        .fold(spacing, |taken, &width| taken.saturating_add(width));
+    let wanted: Vec<u16> = (0..fixed.len())
+        .filter(|&i| fixed[i].is_none())
+        .map(|i| {
+            content
+                .get(i)
+                .copied()
+                .unwrap_or(MAX_AUTO_COLUMN_WIDTH as u16)
+        })
+        .collect();
+    let mut auto = cells::fit_widths(
+        &wanted,
+        available.saturating_sub(taken),
+        MIN_AUTO_COLUMN_WIDTH,
+    )
+    .into_iter();
+    fixed
+        .iter()
+        .map(|width| Constraint::Length(width.or_else(|| auto.next()).unwrap_or(0)))
+        .collect()
+}
+
+// A cell cut to the width of its column as last laid out, with `…` marking a cut, and
+// whether it was cut
+fn fit_cell<'a>(
+    cell: std::borrow::Cow<'a, str>,
+    column: Option<&(u16, u16)>,
+) -> (std::borrow::Cow<'a, str>, bool) {
+    let Some(&(_, width)) = column else {
+        return (cell, false);
+    };
+    match cells::ellipsize(&cell, width as usize) {
+        std::borrow::Cow::Borrowed(_) => (cell, false),
+        std::borrow::Cow::Owned(cut) => (std::borrow::Cow::Owned(cut), true),
+    }
+}
+
+fn render_table_switcher(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
+    let tables = app.switchable_tables();
+    let title = " Recent tables ";
+    let width = tables
+        .iter()
+        .map(|table| cells::display_width(table) + 2)
+        .chain([title.chars().count()])
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+    let width = width.min(area.width);
+    let height = (tables.len() as u16 + 2).min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let items: Vec<ListItem> = tables
+        .iter()
+        .enumerate()
+        .map(|(i, table)| ListItem::new(format!("{} {}", i + 1, table)))
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(app.theme.list_selection);
+
+    f.render_widget(Clear, popup);
+    if let Some(state) = &mut app.table_switcher {
+        f.render_stateful_widget(list, popup, state);
+    }
+}
+
+fn render_column_picker(
+    f: &mut Frame,
+    picker: &mut ColumnPicker,
+    theme: &Theme,
+    area: ratatui::layout::Rect,
+) {
+    let title = " Columns: Space to toggle, Enter to apply, ESC to cancel ";
+    let width = picker
+        .columns
+        .iter()
+        .map(|(name, _)| cells::display_width(name) + 4)
+        .chain([title.chars().count()])
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+    let width = width.min(area.width);
+    let height = (picker.columns.len() as u16 + 2).min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let items: Vec<ListItem> = picker
+        .columns
+        .iter()
+        .map(|(name, visible)| {
+            ListItem::new(format!("[{}] {}", if *visible { "x" } else { " " }, name))
+        })
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(theme.list_selection);
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut picker.state);
+}
+
+fn render_histogram(f: &mut Frame, histogram: &Histogram, area: ratatui::layout::Rect) {
+    let title = histogram.title();
+    let width = (area.width * 3 / 4)
+        .max(title.chars().count() as u16 + 2)
+        .min(area.width);
+    let lines = histogram.lines(width.saturating_sub(2) as usize);
+    let lines = if lines.is_empty() {
+        vec!["The table has no rows".to_string()]
+    } else {
+        lines
+    };
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let text: Vec<Line> = lines.into_iter().map(Line::from).collect();
+    let paragraph = Paragraph::new(text).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(title)
+            .title_bottom(" ESC to close "),
+    );
+
+    f.render_widget(Clear, popup);
+    f.render_widget(paragraph, popup);
+}
+
+// Split a "name (type)" column header into its name and type parts
+fn split_column_header(column: &str) -> (String, String) {
+    match column.find(" (") {
+        Some(pos) if column.ends_with(')') => (
+            column[..pos].to_string(),
+            column[pos + 2..column.len() - 1].to_string(), // Remove the trailing ')'
+        ),
+        // If no type information is present, just use the column name as is
+        _ => (column.to_string(), String::new()),
+    }
+}
+
+fn render_field_detail(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(90), Constraint::Percentage(10)].as_ref())
+        .split(area);
+
+    let value_to_display = app
+        .selected_field_value
+        .as_deref()
+        .unwrap_or("No value selected");
+
+    // The value is wrapped here rather than by the paragraph, so that the row of the
+    // current search match is known to scroll to
+    let width = chunks[0].width.saturating_sub(2) as usize;
+    let (lines, current_row) = field_detail_lines(
+        value_to_display,
+        width,
+        app.detail_search.as_ref(),
+        &app.theme,
+    );
+    if let Some(search) = app.detail_search.as_mut()
+        && search.scroll_pending
+    {
+        search.scroll_pending = false;
+        if let Some(row) = current_row {
+            app.field_detail_scroll = u16::try_from(row).unwrap_or(u16::MAX);
+        }
+    }
+    // Worked out on every render, so it follows the terminal being resized
+    let height = chunks[0].height.saturating_sub(2) as usize;
+    app.field_detail_max_scroll =
+        u16::try_from(lines.len().saturating_sub(height)).unwrap_or(u16::MAX);
+    app.field_detail_scroll = app.field_detail_scroll.min(app.field_detail_max_scroll);
+
+    // A table's field is titled with its column and type, which the grid may not show
+    let title = match app.field_detail_origin_state {
+        Some(AppState::TableData) => app
+            .field_selection_state
+            .and_then(|j| app.table_columns.get(j))
+            .map_or("Field Detail".to_string(), |column| {
+                format!("Field Detail: {}", column)
+            }),
+        _ => "Field Detail".to_string(),
+    };
+    let title = match &app.detail_search {
+        Some(search) => format!("{} {}", title, search.status()),
+        None => title,
+    };
+    let field_para = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .style(app.theme.text)
+        .scroll((app.field_detail_scroll, 0)); // Add vertical scrolling
+
+    f.render_widget(field_para, chunks[0]);
+
+    let help_text = Paragraph::new(Span::raw(
+        "Use ↑↓ to scroll, '/' to search, 'n'/'N' for the next/previous match, ESC to return to table view, 'q' to quit",
+    ))
+    .block(Block::default().borders(Borders::NONE))
+    .style(app.theme.help)
+    .wrap(Wrap { trim: true });
+
+    f.render_widget(help_text, chunks[1]);
+}
+
+// `value` wrapped to `width` columns with the search's matches highlighted, and the row
+// the current match starts on
+fn field_detail_lines<'a>(
+    value: &'a str,
+    width: usize,
+    search: Option<&TextSearch>,
+    theme: &Theme,
+) -> (Vec<Line<'a>>, Option<usize>) {
+    let matches = search.map_or(&[][..], |search| &search.matches[..]);
+    let current = search.and_then(|search| search.current_match());
+    let mut lines = Vec::new();
+    let mut current_row = None;
+    let mut line_start = 0;
+    for raw_line in value.split('\n') {
+        let line = raw_line.strip_suffix('\r').unwrap_or(raw_line);
+        for row in cells::wrap_ranges(line, width) {
+            let (start, end) = (line_start + row.start, line_start + row.end);
+            if current.is_some_and(|m| current_row.is_none() && m.start < end) {
+                current_row = Some(lines.len());
+            }
+            let mut spans = Vec::new();
+            let mut position = start;
+            for m in matches.iter().filter(|m| m.start < end && m.end > start) {
+                let (from, to) = (m.start.max(start), m.end.min(end));
+                if from > position {
+                    spans.push(Span::raw(&value[position..from]));
+                }
+                let style = if current == Some(m) {
+                    theme.field_selection
+                } else {
+                    theme.search_match
+                };
+                spans.push(Span::styled(&value[from..to], style));
+                position = to;
+            }
+            if position < end {
+                spans.push(Span::raw(&value[position..end]));
+            }
+            lines.push(Line::from(spans));
+        }
+        line_start += raw_line.len() + 1;
+    }
+    (lines, current_row)
+}
+
+// `column: value` lines for a record, with the names padded to line the values up.
+// Values spanning several lines continue under the first line of the value.
+fn record_lines(columns: &[String], row: &[String]) -> Vec<(String, String)> {
+    let names: Vec<String> = columns
+        .iter()
+        .map(|column| split_column_header(column).0)
+        .collect();
+    let width = names
+        .iter()
+        .map(|name| cells::display_width(name))
+        .max()
+        .unwrap_or(0);
+
+    let mut lines = Vec::new();
+    for (name, value) in names.iter().zip(row) {
+        for (i, part) in value.split('\n').enumerate() {
+            let label = if i == 0 {
+                format!("{}: ", cells::pad(name, width))
+            } else {
+                " ".repeat(width + 2)
+            };
+            lines.push((label, part.to_string()));
+        }
+    }
+    lines
+}
+
+fn render_record_view(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+        .split(area);
+
+    let (columns, row) = app.record();
+    let lines: Vec<Line> = record_lines(columns, row)
+        .into_iter()
+        .map(|(label, value)| {
+            Line::from(vec![
+                Span::styled(label, app.theme.header),
+                Span::styled(value, app.theme.text),
+            ])
+        })
+        .collect();
+    // The absolute row number, counting from the grid's current page
+    let page = match app.record_origin_state {
+        Some(AppState::CustomQuery) => app.custom_query_current_page,
+        _ => app.current_page,
+    };
+    let title = match app.table_data_state.selected() {
+        Some(index) => format!(
+            "Record {}",
+            page as u64 * app.items_per_page as u64 + index as u64 + 1
+        ),
+        None => "Record".to_string(),
+    };
+
+    let record = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .scroll((app.record_scroll, 0));
+    f.render_widget(record, chunks[0]);
+
+    let help_text = Paragraph::new(Span::raw(
+        "Use ↑↓ or PageUp/PageDown to scroll, ESC or 'x' to return to the grid, 'q' to quit",
+    ))
+    .block(Block::default().borders(Borders::NONE))
+    .style(app.theme.help);
+    f.render_widget(help_text, chunks[1]);
+}
+
+fn render_table_description(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+        .split(area);
+
+    let description = Paragraph::new(app.description_lines.join("\n"))
+        .block(Block::default().borders(Borders::ALL).title("Describe"))
+        .scroll((app.description_scroll, 0));
+    f.render_widget(description, chunks[0]);
+
+    let help_text = Paragraph::new(Span::raw(
+        "Use ↑↓ or PageUp/PageDown to scroll, ESC to return to table list, 'q' to quit",
+    ))
+    .block(Block::default().borders(Borders::NONE))
+    .style(app.theme.help);
+    f.render_widget(help_text, chunks[1]);
+}
+
+fn render_object_tree(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+        .split(area);
+
+    if let Some(tree) = &mut app.object_tree {
+        let items: Vec<ListItem> = tree
+            .rows()
+            .into_iter()
+            .map(|(path, depth)| {
+                ListItem::new(format!("{}{}", "  ".repeat(depth), tree.label(path)))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Objects"))
+            .highlight_style(app.theme.list_selection);
+        f.render_stateful_widget(list, chunks[0], &mut tree.state);
+    }
+
+    let help_text = Paragraph::new(Span::raw(
+        "Use ↑↓ to navigate, → to expand, ← to collapse, Enter to open a relation's rows, ESC for the table list, 'q' to quit",
+    ))
+    .block(Block::default().borders(Borders::NONE))
+    .style(app.theme.help);
+    f.render_widget(help_text, chunks[1]);
+}
+
+fn render_activity(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+        .split(area);
+
+    // Lock waits show who blocks whom in place of the application and transaction age
+    let lock_waits = app.activity_filter == ActivityFilter::LockWaits;
+    let header = Row::new(if lock_waits {
+        [
+            "PID",
+            "User",
+            "Blocked by",
+            "State",
+            "Duration",
+            "Blocking",
+            "Query",
+        ]
+    } else {
+        [
+            "PID",
+            "User",
+            "Application",
+            "State",
+            "Duration",
+            "Transaction",
+            "Query",
+        ]
+    })
+    .style(app.theme.header);
+    let rows: Vec<Row> = app
+        .backends
+        .iter()
+        .map(|backend| {
+            let elapsed = |duration: Option<Duration>| {
+                duration.map(activity::format_elapsed).unwrap_or_default()
+            };
+            let row = Row::new(if lock_waits {
+                [
+                    backend.pid.to_string(),
+                    backend.username.clone(),
+                    activity::format_pids(&backend.blocked_by),
+                    backend.state.clone(),
+                    elapsed(backend.elapsed),
+                    activity::format_pids(&backend.blocking),
+                    backend.query_line(),
+                ]
+            } else {
+                [
+                    backend.pid.to_string(),
+                    backend.username.clone(),
+                    backend.application_name.clone(),
+                    backend.state.clone(),
+                    elapsed(backend.elapsed),
+                    elapsed(backend.transaction_age),
+                    backend.query_line(),
+                ]
+            });
+            if backend.is_hidden() {
+                row.style(app.theme.muted)
+            } else {
+                row
+            }
+        })
+        .collect();
+    let widths = [
+        Constraint::Length(8),
+        Constraint::Length(12),
+        Constraint::Length(16),
+        Constraint::Length(20),
+        Constraint::Length(10),
+        Constraint::Length(11),
+        Constraint::Min(20),
+    ];
+
+    let hidden = app.backends.iter().filter(|b| b.is_hidden()).count();
+    let title = format!(
+        "{}: {} backend(s), refreshed every {}s{}",
+        match app.activity_filter {
+            ActivityFilter::All => "Activity",
+            ActivityFilter::IdleInTransaction => "Idle in transaction, oldest transaction first",
+            ActivityFilter::LockWaits => "Lock waits, blockers first",
+        },
+        app.backends.len(),
+        ACTIVITY_REFRESH_INTERVAL.as_secs(),
+        if hidden > 0 {
+            format!(
+                "; {} of other users not visible without pg_read_all_stats",
+                hidden
+            )
+        } else {
+            String::new()
+        }
+    );
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .row_highlight_style(app.theme.row_selection);
+    f.render_stateful_widget(table, chunks[0], &mut app.activity_state);
+
+    let help_text = match app.activity_confirm {
+        Some((pid, terminate)) => Paragraph::new(format!(
+            "{} backend {}? Press 'y' to confirm, any other key to cancel",
+            if terminate {
+                "Terminate the session of"
+            } else {
+                "Cancel the query of"
+            },
+            pid
+        ))
+        .style(app.theme.accent),
+        None => Paragraph::new(Span::raw(format!(
+            "Use ↑↓ to select a backend, Enter to view its query, 'C' to cancel its query, 'K' to terminate it, {}, 'r' to refresh, ESC for tables, 'q' to quit",
+            match app.activity_filter {
+                ActivityFilter::All =>
+                    "'i' to show only those idle in transaction, 'l' those waiting for locks",
+                ActivityFilter::IdleInTransaction =>
+                    "'i' to show all backends, 'l' those waiting for locks",
+                ActivityFilter::LockWaits =>
+                    "'l' to show all backends, 'i' those idle in transaction",
+            }
+        )))
+        .style(app.theme.help),
+    };
+    f.render_widget(help_text, chunks[1]);
 }
 
-fn render_field_detail(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
+fn render_table_stats(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Percentage(90), Constraint::Percentage(10)].as_ref())
+        .constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
         .split(area);
 
-    let value_to_display = app
-        .selected_field_value
-        .as_deref()
-        .unwrap_or("No value selected");
-
-    // Create a paragraph with the field value, potentially long text
-    let field_para = Paragraph::new(Text::from(value_to_display))
-        .block(Block::default().borders(Borders::ALL).title("Field Detail"))
-        .style(Style::default().fg(Color::White))
-        .wrap(ratatui::widgets::Wrap { trim: false })
-        .scroll((app.field_detail_scroll, 0)); // Add vertical scrolling
-
-    f.render_widget(field_para, chunks[0]);
+    // The sorted column is marked with the way it's sorted
+    let header = Row::new(StatsColumn::ALL.map(|column| {
+        let title = column.title();
+        if column != app.stats_sort {
+            return Span::raw(title);
+        }
+        let descending = (column != StatsColumn::Name) != app.stats_sort_reversed;
+        Span::styled(
+            format!("{} {}", title, if descending { "▼" } else { "▲" }),
+            app.theme.accent,
+        )
+    }))
+    .style(app.theme.header);
+    let rows: Vec<Row> = app
+        .table_stats
+        .iter()
+        .map(|stats| Row::new(stats.cells()))
+        .collect();
+    let widths = StatsColumn::ALL.map(|column| match column {
+        StatsColumn::Name => Constraint::Min(20),
+        StatsColumn::LastAutovacuum => Constraint::Length(17),
+        _ => Constraint::Length(13),
+    });
+
+    let title = format!(
+        "Table statistics: {} table(s){}",
+        app.table_stats.len(),
+        match &app.stats_reset {
+            Some(reset) => format!(", counted since the statistics were reset at {}", reset),
+            None => String::new(),
+        }
+    );
+    let table = Table::new(rows, widths)
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .row_highlight_style(app.theme.row_selection);
+    f.render_stateful_widget(table, chunks[0], &mut app.table_stats_state);
 
     let help_text = Paragraph::new(Span::raw(
-        "Use ↑↓ to scroll, ESC to return to table view, 'q' to quit",
+        "Use ↑↓ to select a table, ←→ to sort by another column, 'o' to reverse the order, Enter to view its rows, 'r' to refresh, ESC for tables, 'q' to quit",
     ))
-    .block(Block::default().borders(Borders::NONE))
-    .style(Style::default().add_modifier(Modifier::ITALIC));
-
+    .style(app.theme.help);
     f.render_widget(help_text, chunks[1]);
 }
 
 fn render_custom_query_input(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
+    // The input grows with the query, leaving room for the help text
+    let line_count = editor::line_count(&app.custom_query_input) as u16;
+    let input_height = (line_count + 2).clamp(3, area.height.saturating_sub(2).max(3));
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+        .constraints([Constraint::Length(input_height), Constraint::Min(0)].as_ref())
         .split(area);
 
     // Input area
     let input_block = Block::default()
         .borders(Borders::ALL)
-        .title("Enter SQL Query");
-
-    // Create input text with cursor at the correct position
-    let input_text = {
-        let mut chars: Vec<char> = app.custom_query_input.chars().collect();
-        if std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis()
-            % 1000
-            < 500
-        {
-            // Insert blinking cursor at the current cursor position
-            if app.custom_query_cursor_position <= chars.len() {
-                chars.insert(app.custom_query_cursor_position, '|');
-            }
-        }
-        chars.into_iter().collect::<String>()
-    };
+        .title(if app.transactional {
+            "Enter SQL Query (transaction mode)"
+        } else {
+            "Enter SQL Query"
+        });
+
+    // Highlight the input, with a blinking cursor at the current position
+    let cursor_visible = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+        % 1000
+        < 500;
+    let input_text = highlight::highlighted_lines(
+        &app.custom_query_input,
+        cursor_visible.then_some(app.custom_query_cursor_position),
+        &app.theme,
+    );
+
+    // Scroll so the cursor's line stays visible, and the cursor within it
+    let (cursor_row, cursor_col) =
+        editor::line_col(&app.custom_query_input, app.custom_query_cursor_position);
+    let visible_rows = chunks[0].height.saturating_sub(2);
+    let scroll = (cursor_row as u16).saturating_sub(visible_rows.saturating_sub(1));
+    let cursor_line = app
+        .custom_query_input
+        .split('\n')
+        .nth(cursor_row)
+        .unwrap_or_default();
+    let cursor_x = cells::display_width(&cursor_line.chars().take(cursor_col).collect::<String>());
+    app.custom_query_hscroll = editor::horizontal_scroll(
+        app.custom_query_hscroll,
+        cursor_x,
+        chunks[0].width.saturating_sub(2) as usize,
+    );
 
     let input_paragraph = Paragraph::new(input_text)
         .block(input_block)
-        .style(Style::default().fg(Color::Yellow));
+        .scroll((scroll, app.custom_query_hscroll as u16));
 
     f.render_widget(input_paragraph, chunks[0]);
 
-    // Help text
-    let help_text = Paragraph::new(Span::raw(
-        "Type your SQL query and press Enter to execute. Press ESC to go back to table list.",
-    ))
+    // Help text, or why the file to load couldn't be read
+    let help_text = match &app.query_file_error {
+        Some(error) => Paragraph::new(Span::raw(error.as_str())).style(app.theme.error),
+        None => Paragraph::new(Span::raw(
+            "Type your SQL query and press F5 (or Ctrl+Enter) to execute; Enter starts a new line. Tab completes table and column names. Ctrl+T toggles transaction mode. Ctrl+S saves the query under a name and Ctrl+L loads a saved one. Ctrl+O inserts the SQL from a file. Ctrl+E opens it in $EDITOR. Press ESC to go back to table list.",
+        ))
+        .style(app.theme.help),
+    }
     .block(Block::default().borders(Borders::NONE))
-    .style(Style::default().add_modifier(Modifier::ITALIC));
+    .wrap(Wrap { trim: true });
 
     f.render_widget(help_text, chunks[1]);
+
+    if let Some(completion) = &app.completion {
+        let (row, col) = editor::line_col(&app.custom_query_input, completion.start);
+        // Columns on screen, which differ from chars for wide characters
+        let line = app
+            .custom_query_input
+            .split('\n')
+            .nth(row)
+            .unwrap_or_default();
+        let before: String = line.chars().take(col).collect();
+        let x = chunks[0].x
+            + 1
+            + (cells::display_width(&before) as u16)
+                .saturating_sub(app.custom_query_hscroll as u16);
+        let y = chunks[0].y + 2 + (row as u16).saturating_sub(scroll);
+        render_completion_popup(f, completion, &app.theme, x, y, area);
+    }
+    if let Some(prompt) = &app.param_prompt {
+        render_param_prompt(f, prompt, &app.theme, area);
+    }
+    if let Some(typed) = &app.destructive_confirm {
+        render_destructive_confirm(f, app, typed, area);
+    }
+}
+
+fn render_destructive_confirm(f: &mut Frame, app: &App, typed: &str, area: ratatui::layout::Rect) {
+    let title = " Confirm: Enter to continue, ESC to cancel ";
+    let mut lines: Vec<Line> = sql::split_statements(&app.custom_query_input)
+        .iter()
+        .filter(|statement| sql::is_destructive(statement))
+        .map(|statement| {
+            // On one line, and cut short so the popup stays narrow
+            let mut line = statement.split_whitespace().collect::<Vec<_>>().join(" ");
+            if line.chars().count() > 60 {
+                line = line.chars().take(57).chain("...".chars()).collect();
+            }
+            Line::styled(line, app.theme.accent)
+        })
+        .collect();
+    lines.extend([
+        Line::from(""),
+        Line::from("This will affect the whole table \u{2014} type YES to continue"),
+        Line::from(format!("> {}|", typed)),
+    ]);
+
+    let width = lines
+        .iter()
+        .map(|line| line.width() + 1)
+        .chain([title.chars().count()])
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+    let width = width.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let form = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(Clear, popup);
+    f.render_widget(form, popup);
+}
+
+// A one-line popup for typing a name or path after `label`
+fn render_prompt(
+    f: &mut Frame,
+    title: &str,
+    label: &str,
+    text: &str,
+    theme: &Theme,
+    area: ratatui::layout::Rect,
+) {
+    let line = Line::from(vec![
+        Span::styled(label, theme.accent),
+        Span::raw(format!("{}|", text)),
+    ]);
+    let width = (line.width() + 1).max(title.chars().count()) as u16 + 2;
+    let width = width.min(area.width);
+    let height = 3.min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let form = Paragraph::new(line).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(Clear, popup);
+    f.render_widget(form, popup);
+}
+
+fn render_saved_query_picker(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
+    let title = " Saved queries: Enter to load, Delete to remove, ESC to cancel ";
+    // Each query on one line after its name, cut short so the popup stays narrow
+    let lines: Vec<Line> = app
+        .saved_queries()
+        .iter()
+        .map(|query| {
+            let sql = query.sql.split_whitespace().collect::<Vec<_>>().join(" ");
+            Line::from(vec![
+                Span::styled(query.name.clone(), app.theme.accent),
+                Span::raw("  "),
+                Span::styled(cells::ellipsize(&sql, 60).into_owned(), app.theme.help),
+            ])
+        })
+        .collect();
+    let width = lines
+        .iter()
+        .map(|line| line.width() + 2)
+        .chain([title.chars().count()])
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+    let width = width.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let list = List::new(lines.into_iter().map(ListItem::new).collect::<Vec<_>>())
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(app.theme.list_selection);
+
+    f.render_widget(Clear, popup);
+    if let Some(state) = &mut app.saved_query_picker {
+        f.render_stateful_widget(list, popup, state);
+    }
+}
+
+fn render_param_prompt(
+    f: &mut Frame,
+    prompt: &ParamPrompt,
+    theme: &Theme,
+    area: ratatui::layout::Rect,
+) {
+    let title = " Parameters: Enter for the next or to run, ESC to cancel ";
+    let lines: Vec<Line> = prompt
+        .types
+        .iter()
+        .zip(&prompt.values)
+        .enumerate()
+        .map(|(i, (data_type, value))| {
+            let label = format!("${} ({}): ", i + 1, data_type);
+            if i == prompt.selected {
+                Line::from(vec![
+                    Span::styled(label, theme.accent),
+                    Span::raw(format!("{}|", value)),
+                ])
+            } else {
+                Line::from(format!("{}{}", label, value))
+            }
+        })
+        .chain([
+            Line::from(""),
+            Line::styled("Type NULL for a null value", theme.help),
+        ])
+        .collect();
+
+    let width = lines
+        .iter()
+        .map(|line| line.width() + 1)
+        .chain([title.chars().count()])
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+    let width = width.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let form = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(Clear, popup);
+    f.render_widget(form, popup);
+}
+
+fn render_cell_edit(f: &mut Frame, edit: &CellEdit, theme: &Theme, area: ratatui::layout::Rect) {
+    let (title, line) = if edit.confirming {
+        (
+            " Confirm: y or Enter to update, n or ESC to go back ".to_string(),
+            Line::styled(edit.confirmation(), theme.accent),
+        )
+    } else {
+        (
+            format!(
+                " Edit {} where {} = {}: Enter to save, ESC to cancel ",
+                edit.column, edit.key_column, edit.key_value
+            ),
+            Line::from(vec![
+                Span::styled(format!("{}: ", edit.column), theme.accent),
+                match edit.null {
+                    true => Span::styled("NULL|", theme.help),
+                    false => Span::raw(format!("{}|", edit.value)),
+                },
+            ]),
+        )
+    };
+    let mut lines = vec![line];
+    if !edit.confirming {
+        lines.push(Line::from(""));
+        lines.push(Line::styled("Ctrl+N switches to and from NULL", theme.help));
+    }
+    let width = lines
+        .iter()
+        .map(|line| line.width() + 1)
+        .chain([title.chars().count()])
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+    let width = width.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let form = Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(Clear, popup);
+    f.render_widget(form, popup);
+}
+
+fn render_insert_form(
+    f: &mut Frame,
+    form: &InsertForm,
+    theme: &Theme,
+    area: ratatui::layout::Rect,
+) {
+    let title = format!(
+        " Insert into {}: Enter for the next field or to insert, F5 to insert, ESC to cancel ",
+        form.table
+    );
+    let lines: Vec<Line> = form
+        .columns
+        .iter()
+        .zip(&form.values)
+        .enumerate()
+        .map(|(i, (column, value))| {
+            let label = format!("{} ({}, {}): ", column.name, column.data_type, form.hint(i));
+            if form.is_auto(i) {
+                Line::styled(label.trim_end().to_string(), theme.help)
+            } else if i == form.selected {
+                Line::from(vec![
+                    Span::styled(label, theme.accent),
+                    Span::raw(format!("{}|", value)),
+                ])
+            } else {
+                Line::from(format!("{}{}", label, value))
+            }
+        })
+        .chain([
+            Line::from(""),
+            Line::styled(
+                "Leave a field empty for its default; type NULL for a null value",
+                theme.help,
+            ),
+        ])
+        .collect();
+
+    let width = lines
+        .iter()
+        .map(|line| line.width() + 1)
+        .chain([title.chars().count()])
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+    let width = width.min(area.width);
+    let height = (lines.len() as u16 + 2).min(area.height);
+    let popup = ratatui::layout::Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    );
+
+    let form = Paragraph::new(lines).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(Clear, popup);
+    f.render_widget(form, popup);
+}
+
+/// List the completion candidates starting at `x`, `y`, just below the word being
+/// completed.
+fn render_completion_popup(
+    f: &mut Frame,
+    completion: &Completion,
+    theme: &Theme,
+    x: u16,
+    y: u16,
+    area: ratatui::layout::Rect,
+) {
+    let width = completion
+        .candidates
+        .iter()
+        .map(|c| cells::display_width(c) as u16)
+        .max()
+        .unwrap_or(0)
+        .saturating_add(4)
+        .min(area.width);
+    let y = y.min(area.bottom());
+    let height = (completion.candidates.len() as u16 + 2).min(area.bottom() - y);
+    let x = x.min(area.right() - width);
+    let popup = ratatui::layout::Rect::new(x, y, width, height);
+
+    let items: Vec<ListItem> = completion
+        .candidates
+        .iter()
+        .map(|c| ListItem::new(c.as_str()))
+        .collect();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL))
+        .highlight_style(theme.field_selection);
+    let mut state = ListState::default();
+    state.select(Some(completion.selected));
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut state);
 }
 
 fn render_custom_query_results(f: &mut Frame, app: &mut App, area: ratatui::layout::Rect) {
+    let pending = matches!(app.state, AppState::TransactionPending);
+
+    if let Some(rows) = app.custom_query_rows_affected {
+        let message = Paragraph::new(format!(
+            "{}\n\n{}",
+            rows_affected_message(rows),
+            if pending {
+                TRANSACTION_PENDING_HELP
+            } else {
+                "Press 's' to edit the query, 't' for tables, 'c' for connections, 'q' to quit"
+            }
+        ))
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "Query Result{}",
+            timing_suffix(&app.custom_query_timing)
+        )));
+        f.render_widget(message, area);
+        return;
+    }
+
+    // When a batch was run, list each statement's outcome above the results
+    let area = if app.custom_query_batch.is_empty() {
+        area
+    } else {
+        let summary_height = (app.custom_query_batch.len() as u16 + 2).min(area.height / 3);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(summary_height), Constraint::Min(0)])
+            .split(area);
+        render_batch_summary(f, app, chunks[0]);
+        chunks[1]
+    };
+
     // Create headers for the table
     let header_names: Vec<Span> = app
         .custom_query_result_columns
@@ -1156,11 +5346,21 @@ fn render_custom_query_results(f: &mut Frame, app: &mut App, area: ratatui::layo
         .collect();
 
     // Create header rows
-    let header_row_names = Row::new(header_names)
-        .height(1)
-        .style(Style::default().add_modifier(Modifier::BOLD));
+    let header_row_names = Row::new(header_names).height(1).style(app.theme.header);
+
+    let widths = column_widths(
+        &vec![None; app.custom_query_result_columns.len()],
+        &app.custom_query_content_widths,
+        area.width.saturating_sub(2),
+    );
+    app.click_targets.set_table(
+        Block::default().borders(Borders::ALL).inner(area),
+        &widths,
+        1,
+    );
 
     // Create rows for the table
+    let mut cell_hint = None;
     let rows: Vec<Row> = app
         .custom_query_result_data
         .iter()
@@ -1169,7 +5369,15 @@ fn render_custom_query_results(f: &mut Frame, app: &mut App, area: ratatui::layo
             let cells: Vec<Span> = row
                 .iter()
                 .enumerate()
-                .map(|(j, cell)| {
+                .map(|(j, value)| {
+                    let (cell, cut) =
+                        fit_cell(value.as_str().into(), app.click_targets.columns.get(j));
+                    if Some(i) == app.table_data_state.selected()
+                        && app.field_selection_state == Some(j)
+                        && cut
+                    {
+                        cell_hint = Some(cells::truncated_hint(value));
+                    }
                     // Check if this cell is selected
                     let mut cell_style = Style::default();
                     if Some(i) == app.table_data_state.selected()
@@ -1177,44 +5385,47 @@ fn render_custom_query_results(f: &mut Frame, app: &mut App, area: ratatui::layo
                         && app.field_selection_state.unwrap() == j
                     {
                         // This is the currently selected field in the selected row
-                        cell_style = Style::default().bg(Color::Yellow).fg(Color::Black);
+                        cell_style = app.theme.field_selection;
                     } else if Some(i) == app.table_data_state.selected() {
                         // This is in the currently selected row
-                        cell_style = Style::default().bg(Color::LightBlue);
+                        cell_style = app.theme.row_selection;
                     }
-                    Span::styled(cell.as_str(), cell_style)
+                    Span::styled(cell, cell_style)
                 })
                 .collect();
             Row::new(cells).height(1)
         })
         .collect();
+    app.cell_hint = cell_hint;
 
     // Combine headers and data rows into a single table
     let mut table_rows = Vec::new();
     table_rows.push(header_row_names);
     table_rows.extend(rows);
 
-    let widths: Vec<Constraint> = app
-        .custom_query_result_columns
-        .iter()
-        .map(|_| Constraint::Percentage(100 / app.custom_query_result_columns.len().max(1) as u16))
-        .collect();
-
     let table = Table::new(table_rows, widths).block(Block::default().borders(Borders::ALL).title(
         format!(
-            "Query Results (Page {}/{})",
+            "Query Results (Page {}/{}{}){}",
             app.custom_query_current_page + 1,
-            app.custom_query_max_page
+            match app.custom_query_total_count {
+                Some(_) => app.custom_query_max_page.max(1).to_string(),
+                None if app.custom_query_count_capped => "many".to_string(),
+                None => "?".to_string(),
+            },
+            app.row_position_label(app.custom_query_total_count, false),
+            timing_suffix(&app.custom_query_timing)
         ),
     ));
 
     f.render_stateful_widget(table, area, &mut app.table_data_state);
 
-    let help_text = Paragraph::new(Span::raw(
-        "Use ↑↓ to navigate rows, ←→ to navigate fields in row, Enter to view field detail, PageUp/PageDown to change pages, 's' for query input, 't' for tables, 'c' for connections, ESC for back, 'q' to quit",
-    ))
+    let help_text = Paragraph::new(Span::raw(if pending {
+        TRANSACTION_PENDING_HELP
+    } else {
+        "Use ↑↓ to navigate rows, ←→ to navigate fields in row, Enter to view field detail, 'x' for the whole record, 'M' to copy the page as Markdown, '`' to show the SQL, 'r' to refresh, PageUp/PageDown to change pages, 's' for query input, 't' for tables, 'c' for connections, ESC for back, 'q' to quit"
+    }))
     .block(Block::default().borders(Borders::NONE))
-    .style(Style::default().add_modifier(Modifier::ITALIC));
+    .style(app.theme.help);
 
     // Position help text at the bottom
     let help_area = ratatui::layout::Rect {
@@ -1226,6 +5437,77 @@ fn render_custom_query_results(f: &mut Frame, app: &mut App, area: ratatui::layo
     f.render_widget(help_text, help_area);
 }
 
+const TRANSACTION_PENDING_HELP: &str =
+    "Transaction open: press 'c' to commit or 'r' to roll back ('q' rolls back and quits)";
+
+fn render_batch_summary(f: &mut Frame, app: &App, area: ratatui::layout::Rect) {
+    let items: Vec<ListItem> = app
+        .custom_query_batch
+        .iter()
+        .enumerate()
+        .map(|(i, summary)| {
+            let style = match summary.outcome {
+                StatementOutcome::Error(_) => app.theme.error,
+                StatementOutcome::NotRun => app.theme.muted,
+                _ => Style::default(),
+            };
+            ListItem::new(format!("{}. {}", i + 1, summary_line(summary))).style(style)
+        })
+        .collect();
+
+    let failed = app
+        .custom_query_batch
+        .iter()
+        .any(|s| matches!(s.outcome, StatementOutcome::Error(_)));
+    let title = format!(
+        "Batch: {} statement(s){}",
+        app.custom_query_batch.len(),
+        if failed { ", stopped on error" } else { "" }
+    );
+    let list = List::new(items).block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(list, area);
+}
+
+// How long the rows took, to follow a results block's title
+fn timing_suffix(timing: &QueryTiming) -> String {
+    match timing.label() {
+        Some(label) => format!(" · {}", label),
+        None => String::new(),
+    }
+}
+
+fn rows_affected_message(rows: u64) -> String {
+    if rows == 1 {
+        "1 row affected".to_string()
+    } else {
+        format!("{} rows affected", rows)
+    }
+}
+
+// One line describing a batch statement: its SQL collapsed onto a single line, then its outcome
+fn summary_line(summary: &StatementSummary) -> String {
+    const MAX_STATEMENT_CHARS: usize = 60;
+    let statement = summary
+        .statement
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    let statement = if statement.chars().count() > MAX_STATEMENT_CHARS {
+        let truncated: String = statement.chars().take(MAX_STATEMENT_CHARS - 3).collect();
+        format!("{}...", truncated)
+    } else {
+        statement
+    };
+    let outcome = match &summary.outcome {
+        StatementOutcome::Rows(1) => "1 row".to_string(),
+        StatementOutcome::Rows(n) => format!("{} rows", n),
+        StatementOutcome::Error(e) => format!("error: {}", e),
+        StatementOutcome::NotRun => "not run".to_string(),
+        StatementOutcome::Results => "results below".to_string(),
+    };
+    format!("{} — {}", statement, outcome)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1233,11 +5515,7 @@ mod tests {
     #[test]
     fn test_initial_state() {
         let temp_dir = tempfile::TempDir::new().unwrap();
-        unsafe {
-            std::env::set_var("HOME", temp_dir.path().to_str().unwrap());
-        }
-
-        let app = App::new().unwrap();
+        let app = App::new_in(temp_dir.path()).unwrap();
         assert_eq!(app.state, AppState::ConnectionSelection);
         assert!(app.connection.is_none());
         assert!(app.tables.is_empty());
@@ -1247,11 +5525,9 @@ mod tests {
     #[test]
     fn test_new_with_connection() {
         let temp_dir = tempfile::TempDir::new().unwrap();
-        unsafe {
-            std::env::set_var("HOME", temp_dir.path().to_str().unwrap());
-        }
-
-        let app = App::new_with_connection("test_conn".to_string()).unwrap();
+        let app = App::new_in(temp_dir.path())
+            .unwrap()
+            .connecting_to("test_conn".to_string());
         assert_eq!(app.state, AppState::Connecting);
         assert!(app.connection_status.is_some());
         assert!(
@@ -1262,13 +5538,23 @@ mod tests {
     }
 
     #[test]
-    fn test_navigation_between_connections() {
+    fn test_page_sql_follows_the_view() {
         let temp_dir = tempfile::TempDir::new().unwrap();
-        unsafe {
-            std::env::set_var("HOME", temp_dir.path().to_str().unwrap());
-        }
+        let mut app = App::new_in(temp_dir.path()).unwrap();
+        app.table_page_sql = Some("SELECT 1".to_string());
+        app.custom_query_page_sql = Some("SELECT 2".to_string());
+        app.state = AppState::TableData;
+        assert_eq!(app.page_sql(), Some("SELECT 1"));
+        app.state = AppState::CustomQuery;
+        assert_eq!(app.page_sql(), Some("SELECT 2"));
+        app.state = AppState::TableList;
+        assert_eq!(app.page_sql(), None);
+    }
 
-        let mut app = App::new().unwrap();
+    #[test]
+    fn test_navigation_between_connections() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut app = App::new_in(temp_dir.path()).unwrap();
 
         // Manually add some connections to the config for testing
         let conn1 = crate::config::ConnectionInfo {
@@ -1278,6 +5564,11 @@ mod tests {
             username: "user1".to_string(),
             password: "pass1".to_string(),
             name: "conn1".to_string(),
+            options: Default::default(),
+            on_connect: Vec::new(),
+            max_count_rows: None,
+            max_export_rows: None,
+            password_command: None,
         };
 
         let conn2 = crate::config::ConnectionInfo {
@@ -1287,10 +5578,16 @@ mod tests {
             username: "user2".to_string(),
             password: "pass2".to_string(),
             name: "conn2".to_string(),
+            options: Default::default(),
+            on_connect: Vec::new(),
+            max_count_rows: None,
+            max_export_rows: None,
+            password_command: None,
         };
 
-        app.config.add_connection(conn1).unwrap();
         app.config.add_connection(conn2).unwrap();
+        app.config.add_connection(conn1).unwrap();
+        assert_eq!(app.connection_names(), ["conn1", "conn2"]);
 
         // Test initial state
         assert_eq!(app.connections_list_state.selected(), None);
@@ -1314,7 +5611,8 @@ mod tests {
 
     #[test]
     fn test_navigation_between_tables() {
-        let mut app = App::new().unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut app = App::new_in(temp_dir.path()).unwrap();
 
         // Add some mock tables for testing
         app.tables = vec![
@@ -1338,9 +5636,30 @@ mod tests {
         assert_eq!(app.tables_list_state.selected(), Some(2));
     }
 
+    #[test]
+    fn test_navigation_in_empty_lists() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut app = App::new_in(temp_dir.path()).unwrap();
+        app.next_connection();
+        app.previous_connection();
+        assert_eq!(app.connections_list_state.selected(), None);
+        app.next_table();
+        app.previous_table();
+        assert_eq!(app.tables_list_state.selected(), None);
+
+        // A filter matching nothing leaves the selection alone too
+        app.tables = vec!["users".to_string()];
+        app.tables_list_state.select(Some(0));
+        app.list_filter = Some("xyz".to_string());
+        app.next_table();
+        app.previous_table();
+        assert_eq!(app.tables_list_state.selected(), Some(0));
+    }
+
     #[test]
     fn test_navigation_between_rows() {
-        let mut app = App::new().unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut app = App::new_in(temp_dir.path()).unwrap();
 
         // Add some mock table data for testing
         app.table_data = vec![
@@ -1366,7 +5685,8 @@ mod tests {
 
     #[test]
     fn test_navigation_in_custom_query_results() {
-        let mut app = App::new().unwrap();
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut app = App::new_in(temp_dir.path()).unwrap();
 
         // Set state to CustomQuery and add mock custom query data
         app.state = AppState::CustomQuery;
@@ -1394,40 +5714,276 @@ mod tests {
     }
 
     #[test]
-    fn test_page_navigation() {
-        let mut app = App::new().unwrap();
+    fn test_record_lines() {
+        let columns = vec!["id (int4)".to_string(), "description (text)".to_string()];
+        let row = vec!["7".to_string(), "first\nsecond".to_string()];
+        assert_eq!(
+            record_lines(&columns, &row),
+            vec![
+                ("id         : ".to_string(), "7".to_string()),
+                ("description: ".to_string(), "first".to_string()),
+                ("             ".to_string(), "second".to_string()),
+            ]
+        );
+        assert!(record_lines(&columns, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_field_detail_lines() {
+        let value = "first line\r\nsecond needle and a needle";
+        let mut search = TextSearch::new();
+        search.query = "needle".to_string();
+        search.update(value);
+        search.step(true);
+        let (lines, current_row) = field_detail_lines(value, 12, Some(&search), &Theme::default());
+        let rows: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        assert_eq!(rows, ["first line", "second ", "needle and a ", "needle"]);
+        assert_eq!(current_row, Some(3));
+    }
+
+    #[test]
+    fn test_field_detail_scroll_stops_at_the_end() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut app = App::new_in(temp_dir.path()).unwrap();
+        app.field_detail_max_scroll = 2;
+        for _ in 0..5 {
+            app.scroll_field_detail_down();
+        }
+        assert_eq!(app.field_detail_scroll, 2);
+        app.scroll_field_detail_up();
+        assert_eq!(app.field_detail_scroll, 1);
+    }
+
+    #[test]
+    fn test_mark_rows() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut app = App::new_in(temp_dir.path()).unwrap();
+        app.table_data = vec![vec!["1".to_string()]; 6];
+        app.table_data_state.select(Some(1));
+        app.toggle_row_mark();
+        app.table_data_state.select(Some(4));
+        app.mark_row_range();
+        assert_eq!(app.marked_rows, HashSet::from([1, 2, 3, 4]));
+
+        // Space unmarks a marked row and makes it the start of the next range
+        app.table_data_state.select(Some(2));
+        app.toggle_row_mark();
+        assert_eq!(app.marked_rows, HashSet::from([1, 3, 4]));
+        app.table_data_state.select(Some(0));
+        app.mark_row_range();
+        assert_eq!(app.marked_rows, HashSet::from([0, 1, 2, 3, 4]));
+
+        app.clear_marked_rows();
+        assert!(app.marked_rows.is_empty());
+        assert_eq!(app.mark_anchor, None);
+    }
+
+    #[test]
+    fn test_record_lines_align_wide_names() {
+        let columns = vec!["名前 (text)".to_string(), "id (int4)".to_string()];
+        let row = vec!["太郎".to_string(), "1".to_string()];
+        let lines = record_lines(&columns, &row);
+        assert_eq!(lines[0].0, "名前: ");
+        assert_eq!(lines[1].0, "id  : ");
+    }
+
+    #[test]
+    fn test_push_recent() {
+        let mut recent = Vec::new();
+        for table in ["a", "b", "a"] {
+            push_recent(&mut recent, table);
+        }
+        assert_eq!(recent, ["a", "b"]);
+
+        for i in 0..RECENT_TABLES_LIMIT {
+            push_recent(&mut recent, &format!("t{}", i));
+        }
+        assert_eq!(recent.len(), RECENT_TABLES_LIMIT);
+        assert_eq!(recent.first().map(String::as_str), Some("t8"));
+        assert!(!recent.contains(&"b".to_string()));
+    }
+
+    // Serves canned results, counting the rows of any query as `count`
+    struct FakeDatabase {
+        page: QueryPage,
+        count: Option<i64>,
+    }
+
+    impl Database for FakeDatabase {
+        async fn list_tables(&self) -> db::Result<Vec<String>> {
+            Ok(vec!["users".to_string()])
+        }
+
+        async fn get_table_data(
+            &self,
+            _table_name: &str,
+            _hidden_columns: &[String],
+            _offset: i64,
+            _limit: i64,
+        ) -> db::Result<(Vec<String>, Vec<Vec<String>>)> {
+            Ok((self.page.columns.clone(), self.page.rows.clone()))
+        }
+
+        async fn get_table_count(&self, _table_name: &str) -> db::Result<i64> {
+            self.get_query_row_count("", &[], None).await
+        }
+
+        async fn execute_custom_query(
+            &self,
+            _query: &str,
+            _params: &[Option<String>],
+            _offset: i64,
+            _limit: i64,
+        ) -> db::Result<QueryPage> {
+            Ok(self.page.clone())
+        }
+
+        async fn get_query_row_count(
+            &self,
+            _query: &str,
+            _params: &[Option<String>],
+            _max: Option<u64>,
+        ) -> db::Result<i64> {
+            self.count
+                .ok_or_else(|| db::DbError::PermissionDenied("permission denied".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fetch_query_page_counts_when_needed() {
+        let page = QueryPage {
+            columns: vec!["id".to_string()],
+            rows: vec![vec!["1".to_string()]],
+            total_rows: None,
+        };
+        let db = FakeDatabase {
+            page: page.clone(),
+            count: Some(42),
+        };
+        let (fetched, total, timing) = fetch_query_page(&db, "SELECT 1", &[], 0, 20, None)
+            .await
+            .unwrap();
+        assert_eq!(fetched, page);
+        assert_eq!(total.unwrap(), 42);
+        assert!(timing.execute.is_some() && timing.count.is_some());
+
+        // A page that already knows its total isn't counted again
+        let db = FakeDatabase {
+            page: QueryPage {
+                total_rows: Some(7),
+                ..page.clone()
+            },
+            count: None,
+        };
+        let (_, total, timing) = fetch_query_page(&db, "SHOW ALL", &[], 0, 20, None)
+            .await
+            .unwrap();
+        assert_eq!(total.unwrap(), 7);
+        assert_eq!(timing.count, None);
+
+        // Failing to count still returns the page
+        let db = FakeDatabase { page, count: None };
+        let (fetched, total, _) = fetch_query_page(&db, "SELECT 1", &[], 0, 20, None)
+            .await
+            .unwrap();
+        assert_eq!(fetched.rows.len(), 1);
+        assert!(total.is_err());
+    }
+
+    #[test]
+    fn test_row_location() {
+        assert_eq!(row_location(1, 20), (0, 0));
+        assert_eq!(row_location(20, 20), (0, 19));
+        assert_eq!(row_location(21, 20), (1, 0));
+        assert_eq!(row_location(95, 20), (4, 14));
+    }
+
+    #[test]
+    fn test_reselect_row() {
+        let mut state = TableState::default();
+        reselect_row(&mut state, Some(5), 10);
+        assert_eq!(state.selected(), Some(5));
+        reselect_row(&mut state, Some(5), 3);
+        assert_eq!(state.selected(), Some(2));
+        reselect_row(&mut state, None, 3);
+        assert_eq!(state.selected(), Some(0));
+        reselect_row(&mut state, Some(1), 0);
+        assert_eq!(state.selected(), None);
+    }
+
+    #[tokio::test]
+    async fn test_page_navigation() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut app = App::new_in(temp_dir.path()).unwrap();
 
         // Set up pagination
         app.current_page = 2;
         app.max_page = 5;
 
         // Test next_page
-        app.next_page();
+        app.next_page().await.unwrap();
         assert_eq!(app.current_page, 3);
 
         // Test previous_page
-        app.previous_page();
+        app.previous_page().await.unwrap();
         assert_eq!(app.current_page, 2);
 
         // Test boundary conditions
         app.current_page = 0;
-        app.previous_page();
+        app.previous_page().await.unwrap();
         assert_eq!(app.current_page, 0); // Should not go below 0
 
         app.current_page = 4;
         app.max_page = 5; // So valid pages are 0-4
-        app.next_page();
+        app.next_page().await.unwrap();
         assert_eq!(app.current_page, 4); // Should not exceed max_page - 1
     }
 
+    #[tokio::test]
+    async fn test_page_navigation_with_empty_results() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut app = App::new_in(temp_dir.path()).unwrap();
+        assert_eq!(app.max_page, 0);
+        app.next_page().await.unwrap();
+        app.previous_page().await.unwrap();
+        assert_eq!(app.current_page, 0);
+
+        app.custom_query_total_count = Some(0);
+        app.next_custom_query_page().await.unwrap();
+        app.previous_custom_query_page().await.unwrap();
+        assert_eq!(app.custom_query_current_page, 0);
+
+        // A single page has nowhere to go either
+        app.max_page = 1;
+        app.custom_query_max_page = 1;
+        app.next_page().await.unwrap();
+        app.next_custom_query_page().await.unwrap();
+        assert_eq!((app.current_page, app.custom_query_current_page), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_next_page_past_estimated_count() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut app = App::new_in(temp_dir.path()).unwrap();
+        app.current_page = 4;
+        app.max_page = 5;
+        app.total_count_estimated = true;
+
+        // A full last page means the estimate was low
+        app.table_data = vec![vec!["x".to_string()]; app.items_per_page as usize];
+        app.next_page().await.unwrap();
+        assert_eq!(app.current_page, 5);
+
+        // A partial page is the real end of the table
+        app.table_data = vec![vec!["x".to_string()]];
+        app.next_page().await.unwrap();
+        assert_eq!(app.current_page, 5);
+    }
+
     #[test]
     fn test_app_state_transitions() {
         let temp_dir = tempfile::TempDir::new().unwrap();
-        unsafe {
-            std::env::set_var("HOME", temp_dir.path().to_str().unwrap());
-        }
-
-        let mut app = App::new().unwrap();
+        let mut app = App::new_in(temp_dir.path()).unwrap();
 
         // Start in ConnectionSelection
         assert_eq!(app.state, AppState::ConnectionSelection);
@@ -1448,4 +6004,80 @@ mod tests {
         app.state = AppState::ConnectionError;
         assert_eq!(app.state, AppState::ConnectionError);
     }
+
+    #[test]
+    fn test_selected_row_number() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let mut app = App::new_in(temp_dir.path()).unwrap();
+        app.items_per_page = 20;
+        assert_eq!(app.selected_row_number(), None);
+
+        app.current_page = 2;
+        app.table_data_state.select(Some(4));
+        assert_eq!(app.selected_row_number(), Some(45));
+
+        // Custom query results track their own page
+        app.state = AppState::CustomQuery;
+        app.custom_query_current_page = 0;
+        assert_eq!(app.selected_row_number(), Some(5));
+    }
+
+    #[test]
+    fn test_summary_line() {
+        let summary = StatementSummary {
+            statement: "UPDATE projects\n   SET name = 'x'".to_string(),
+            outcome: StatementOutcome::Rows(3),
+        };
+        assert_eq!(
+            summary_line(&summary),
+            "UPDATE projects SET name = 'x' — 3 rows"
+        );
+
+        let summary = StatementSummary {
+            statement: format!("SELECT {}", "a, ".repeat(40)),
+            outcome: StatementOutcome::NotRun,
+        };
+        let line = summary_line(&summary);
+        assert!(line.ends_with("... — not run"));
+        assert_eq!(line.split(" — ").next().unwrap().chars().count(), 60);
+    }
+
+    #[test]
+    fn test_rows_affected_message() {
+        assert_eq!(rows_affected_message(1), "1 row affected");
+        assert_eq!(rows_affected_message(0), "0 rows affected");
+        assert_eq!(rows_affected_message(12), "12 rows affected");
+    }
+
+    #[test]
+    fn test_page_cursor() {
+        let bounds = PageBounds {
+            table: "users".to_string(),
+            page: 3,
+            first_key: "61".to_string(),
+            last_key: "80".to_string(),
+        };
+        assert_eq!(
+            page_cursor(Some(&bounds), "users", 4, 80),
+            PageCursor::After("80".to_string())
+        );
+        assert_eq!(
+            page_cursor(Some(&bounds), "users", 2, 40),
+            PageCursor::Before("61".to_string())
+        );
+        // Jumps, reloads and other tables fall back to offsets
+        assert_eq!(
+            page_cursor(Some(&bounds), "users", 7, 140),
+            PageCursor::Offset(140)
+        );
+        assert_eq!(
+            page_cursor(Some(&bounds), "users", 3, 60),
+            PageCursor::Offset(60)
+        );
+        assert_eq!(
+            page_cursor(Some(&bounds), "orders", 4, 80),
+            PageCursor::Offset(80)
+        );
+        assert_eq!(page_cursor(None, "users", 4, 80), PageCursor::Offset(80));
+    }
 }
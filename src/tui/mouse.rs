@@ -0,0 +1,122 @@
+//! Working out what a mouse click landed on.
+
+use ratatui::layout::{Constraint, Flex, Layout, Rect};
+use std::time::{Duration, Instant};
+
+// Two clicks on the same spot within this time make a double-click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Where the rows and cells of the current view were last drawn. Each render function
+/// fills in what it shows, so clicks only land on what's on screen.
+#[derive(Debug, Default, Clone)]
+pub struct ClickTargets {
+    pub list: Option<Rect>,       // Inside the border of the table list
+    pub table: Option<Rect>,      // Inside the border of the data table
+    pub header_rows: usize,       // Rows of the data table above the data
+    pub columns: Vec<(u16, u16)>, // x and width of each column of the data table
+}
+
+impl ClickTargets {
+    /// Record a data table drawn inside `inner` with the given column widths, laid out
+    /// the way `Table` lays them out (no selection column, one cell between columns).
+    pub fn set_table(&mut self, inner: Rect, widths: &[Constraint], header_rows: usize) {
+        self.table = Some(inner);
+        self.header_rows = header_rows;
+        self.columns = Layout::horizontal(widths.iter().copied())
+            .flex(Flex::Start)
+            .spacing(1)
+            .split(inner)
+            .iter()
+            .map(|column| (column.x, column.width))
+            .collect();
+    }
+
+    /// The index of the list item at `x`, `y`, given how far the list is scrolled.
+    pub fn list_item_at(&self, x: u16, y: u16, offset: usize) -> Option<usize> {
+        let area = self.list?;
+        contains(area, x, y).then(|| offset + (y - area.y) as usize)
+    }
+
+    /// The index of the data row at `x`, `y`, given how far the table is scrolled.
+    /// Clicks on the header rows are ignored.
+    pub fn table_row_at(&self, x: u16, y: u16, offset: usize) -> Option<usize> {
+        let area = self.table?;
+        if !contains(area, x, y) {
+            return None;
+        }
+        (offset + (y - area.y) as usize).checked_sub(self.header_rows)
+    }
+
+    pub fn column_at(&self, x: u16) -> Option<usize> {
+        self.columns
+            .iter()
+            .position(|&(start, width)| x >= start && x < start + width)
+    }
+}
+
+fn contains(area: Rect, x: u16, y: u16) -> bool {
+    x >= area.x && x < area.right() && y >= area.y && y < area.bottom()
+}
+
+/// Tells double-clicks from single ones.
+#[derive(Debug, Default)]
+pub struct ClickTracker {
+    last: Option<(Instant, u16, u16)>,
+}
+
+impl ClickTracker {
+    /// Record a click at `x`, `y` and return whether it completes a double-click.
+    pub fn click(&mut self, x: u16, y: u16) -> bool {
+        let now = Instant::now();
+        let double = self.last.is_some_and(|(time, last_x, last_y)| {
+            (last_x, last_y) == (x, y) && now.duration_since(time) < DOUBLE_CLICK_INTERVAL
+        });
+        // A third click starts over rather than making another double-click
+        self.last = if double { None } else { Some((now, x, y)) };
+        double
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_hit_testing() {
+        let mut targets = ClickTargets::default();
+        let widths = [Constraint::Percentage(50), Constraint::Percentage(50)];
+        targets.set_table(Rect::new(1, 2, 20, 10), &widths, 2);
+
+        // The first two rows are headers
+        assert_eq!(targets.table_row_at(5, 3, 0), None);
+        assert_eq!(targets.table_row_at(5, 4, 0), Some(0));
+        assert_eq!(targets.table_row_at(5, 6, 3), Some(5));
+        assert_eq!(targets.table_row_at(25, 6, 0), None);
+
+        assert_eq!(targets.columns, vec![(1, 10), (12, 9)]);
+        assert_eq!(targets.column_at(1), Some(0));
+        assert_eq!(targets.column_at(11), None); // The gap between columns
+        assert_eq!(targets.column_at(12), Some(1));
+    }
+
+    #[test]
+    fn test_list_hit_testing() {
+        let targets = ClickTargets {
+            list: Some(Rect::new(1, 1, 10, 5)),
+            ..Default::default()
+        };
+        assert_eq!(targets.list_item_at(2, 1, 0), Some(0));
+        assert_eq!(targets.list_item_at(2, 3, 4), Some(6));
+        assert_eq!(targets.list_item_at(2, 6, 0), None);
+        assert_eq!(targets.list_item_at(0, 1, 0), None);
+    }
+
+    #[test]
+    fn test_double_click() {
+        let mut clicks = ClickTracker::default();
+        assert!(!clicks.click(3, 4));
+        assert!(clicks.click(3, 4));
+        assert!(!clicks.click(3, 4));
+        assert!(!clicks.click(3, 5));
+    }
+}
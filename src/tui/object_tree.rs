@@ -0,0 +1,293 @@
+//! The object tree: schemas that expand to their relations, which expand to their
+//! columns, opened with 't' from the table list.
+//!
+//! Children are loaded the first time a node is expanded and kept after that, so
+//! collapsing and expanding again doesn't query the server. The tree is shown as a list
+//! of its visible rows, indented by depth.
+
+use crate::db::objects::{Relation, RelationColumn};
+use ratatui::widgets::ListState;
+
+#[derive(Debug, Clone)]
+pub struct SchemaNode {
+    pub name: String,
+    pub expanded: bool,
+    pub relations: Option<Vec<RelationNode>>, // None until first expanded
+}
+
+#[derive(Debug, Clone)]
+pub struct RelationNode {
+    pub relation: Relation,
+    pub expanded: bool,
+    pub columns: Option<Vec<RelationColumn>>, // None until first expanded
+}
+
+/// Where a visible row is in the tree, by index at each level.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TreePath {
+    Schema(usize),
+    Relation(usize, usize),
+    Column(usize, usize, usize),
+}
+
+/// Children that have to be loaded before a node can be expanded.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Load {
+    Relations(String),       // Schema
+    Columns(String, String), // Schema and relation
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectTree {
+    pub schemas: Vec<SchemaNode>,
+    pub state: ListState, // Selection among the visible rows
+}
+
+impl ObjectTree {
+    pub fn new(schemas: Vec<String>) -> ObjectTree {
+        let mut state = ListState::default();
+        if !schemas.is_empty() {
+            state.select(Some(0));
+        }
+        let schemas = schemas
+            .into_iter()
+            .map(|name| SchemaNode {
+                name,
+                expanded: false,
+                relations: None,
+            })
+            .collect();
+        ObjectTree { schemas, state }
+    }
+
+    /// The visible rows in display order, with their depth.
+    pub fn rows(&self) -> Vec<(TreePath, usize)> {
+        let mut rows = Vec::new();
+        for (s, schema) in self.schemas.iter().enumerate() {
+            rows.push((TreePath::Schema(s), 0));
+            let Some(relations) = schema.relations.as_ref().filter(|_| schema.expanded) else {
+                continue;
+            };
+            for (r, relation) in relations.iter().enumerate() {
+                rows.push((TreePath::Relation(s, r), 1));
+                let Some(columns) = relation.columns.as_ref().filter(|_| relation.expanded) else {
+                    continue;
+                };
+                rows.extend((0..columns.len()).map(|c| (TreePath::Column(s, r, c), 2)));
+            }
+        }
+        rows
+    }
+
+    pub fn selected(&self) -> Option<TreePath> {
+        let rows = self.rows();
+        self.state
+            .selected()
+            .and_then(|i| rows.get(i))
+            .map(|(path, _)| *path)
+    }
+
+    /// The schema and relation of the selected row, if it's a relation or one of its
+    /// columns.
+    pub fn selected_relation(&self) -> Option<(&str, &Relation)> {
+        let (s, r) = match self.selected()? {
+            TreePath::Schema(_) => return None,
+            TreePath::Relation(s, r) | TreePath::Column(s, r, _) => (s, r),
+        };
+        let schema = &self.schemas[s];
+        let relation = &schema.relations.as_ref()?[r].relation;
+        Some((&schema.name, relation))
+    }
+
+    pub fn next(&mut self) {
+        let len = self.rows().len();
+        if let Some(i) = self.state.selected()
+            && i + 1 < len
+        {
+            self.state.select(Some(i + 1));
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if let Some(i) = self.state.selected() {
+            self.state.select(Some(i.saturating_sub(1)));
+        }
+    }
+
+    /// Expand the selected node, or step into its first child if it's already expanded.
+    /// Returns what to load first when its children haven't been loaded yet; once they
+    /// are, pass them to `set_relations` or `set_columns` and call this again.
+    pub fn expand(&mut self) -> Option<Load> {
+        match self.selected()? {
+            TreePath::Schema(s) => {
+                let schema = &mut self.schemas[s];
+                let Some(relations) = &schema.relations else {
+                    return Some(Load::Relations(schema.name.clone()));
+                };
+                if schema.expanded {
+                    if !relations.is_empty() {
+                        self.next();
+                    }
+                } else {
+                    schema.expanded = true;
+                }
+            }
+            TreePath::Relation(s, r) => {
+                let schema = &mut self.schemas[s];
+                let relation = &mut schema.relations.as_mut()?[r];
+                let Some(columns) = &relation.columns else {
+                    return Some(Load::Columns(
+                        schema.name.clone(),
+                        relation.relation.name.clone(),
+                    ));
+                };
+                if relation.expanded {
+                    if !columns.is_empty() {
+                        self.next();
+                    }
+                } else {
+                    relation.expanded = true;
+                }
+            }
+            TreePath::Column(..) => {}
+        }
+        None
+    }
+
+    /// Collapse the selected node, or move to its parent if it's a column or collapsed.
+    pub fn collapse(&mut self) {
+        let parent = match self.selected() {
+            Some(TreePath::Schema(s)) => {
+                self.schemas[s].expanded = false;
+                None
+            }
+            Some(TreePath::Relation(s, r)) => {
+                let relation = &mut self.schemas[s].relations.as_mut().unwrap()[r];
+                if relation.expanded {
+                    relation.expanded = false;
+                    None
+                } else {
+                    Some(TreePath::Schema(s))
+                }
+            }
+            Some(TreePath::Column(s, r, _)) => Some(TreePath::Relation(s, r)),
+            None => None,
+        };
+        if let Some(parent) = parent {
+            self.select(parent);
+        }
+    }
+
+    pub fn set_relations(&mut self, schema: &str, relations: Vec<Relation>) {
+        if let Some(node) = self.schemas.iter_mut().find(|s| s.name == schema) {
+            node.relations = Some(
+                relations
+                    .into_iter()
+                    .map(|relation| RelationNode {
+                        relation,
+                        expanded: false,
+                        columns: None,
+                    })
+                    .collect(),
+            );
+        }
+    }
+
+    pub fn set_columns(&mut self, schema: &str, relation: &str, columns: Vec<RelationColumn>) {
+        let node = self
+            .schemas
+            .iter_mut()
+            .find(|s| s.name == schema)
+            .and_then(|s| s.relations.as_mut())
+            .and_then(|relations| relations.iter_mut().find(|r| r.relation.name == relation));
+        if let Some(node) = node {
+            node.columns = Some(columns);
+        }
+    }
+
+    /// The text of a visible row: an arrow for nodes that expand, then the name and what
+    /// kind of object it is.
+    pub fn label(&self, path: TreePath) -> String {
+        let arrow = |expanded: bool| if expanded { "▾" } else { "▸" };
+        match path {
+            TreePath::Schema(s) => {
+                let schema = &self.schemas[s];
+                format!("{} {}", arrow(schema.expanded), schema.name)
+            }
+            TreePath::Relation(s, r) => {
+                let node = &self.schemas[s].relations.as_ref().unwrap()[r];
+                format!(
+                    "{} {} ({})",
+                    arrow(node.expanded),
+                    node.relation.name,
+                    node.relation.kind.label()
+                )
+            }
+            TreePath::Column(s, r, c) => {
+                let node = &self.schemas[s].relations.as_ref().unwrap()[r];
+                let column = &node.columns.as_ref().unwrap()[c];
+                format!("  {} {}", column.name, column.data_type)
+            }
+        }
+    }
+
+    fn select(&mut self, path: TreePath) {
+        if let Some(i) = self.rows().iter().position(|(p, _)| *p == path) {
+            self.state.select(Some(i));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::objects::RelationKind;
+
+    #[test]
+    fn test_expand_and_collapse() {
+        let mut tree = ObjectTree::new(vec!["public".to_string(), "audit".to_string()]);
+        assert_eq!(tree.expand(), Some(Load::Relations("public".to_string())));
+        tree.set_relations(
+            "public",
+            vec![Relation {
+                name: "users".to_string(),
+                kind: RelationKind::Table,
+            }],
+        );
+        assert_eq!(tree.expand(), None);
+        assert_eq!(tree.label(TreePath::Schema(0)), "▾ public");
+        assert_eq!(tree.rows().len(), 3);
+
+        // Expanding again steps into the children
+        tree.expand();
+        assert_eq!(tree.selected(), Some(TreePath::Relation(0, 0)));
+        assert_eq!(tree.selected_relation().unwrap().0, "public");
+        assert_eq!(
+            tree.expand(),
+            Some(Load::Columns("public".to_string(), "users".to_string()))
+        );
+        tree.set_columns(
+            "public",
+            "users",
+            vec![RelationColumn {
+                name: "id".to_string(),
+                data_type: "integer".to_string(),
+            }],
+        );
+        tree.expand();
+        tree.expand();
+        assert_eq!(tree.selected(), Some(TreePath::Column(0, 0, 0)));
+        assert_eq!(tree.label(TreePath::Column(0, 0, 0)), "  id integer");
+
+        // Collapsing walks back up to the schema, which keeps its loaded children
+        tree.collapse();
+        assert_eq!(tree.selected(), Some(TreePath::Relation(0, 0)));
+        tree.collapse();
+        tree.collapse();
+        assert_eq!(tree.selected(), Some(TreePath::Schema(0)));
+        tree.collapse();
+        assert_eq!(tree.rows().len(), 2);
+        assert_eq!(tree.expand(), None);
+        assert_eq!(tree.rows().len(), 3);
+    }
+}
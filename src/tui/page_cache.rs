@@ -0,0 +1,148 @@
+//! Recently viewed table pages, so paging back and forth doesn't query the server again.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedPage {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+    pub sql: Option<String>, // The statement that fetched the page
+}
+
+/// A least-recently-used cache of pages keyed by table and page number, along with each
+/// table's row count as (count, estimated). Each remembers when it was fetched.
+#[derive(Debug, Default)]
+pub struct PageCache {
+    pages: VecDeque<((String, u32), Instant, CachedPage)>, // Most recently used last
+    counts: HashMap<String, (Instant, (i64, bool))>,
+}
+
+// Pages kept across all tables
+const CAPACITY: usize = 32;
+
+impl PageCache {
+    pub fn get(&mut self, table: &str, page: u32) -> Option<&CachedPage> {
+        let index = self
+            .pages
+            .iter()
+            .position(|((t, p), _, _)| t == table && *p == page)?;
+        let entry = self.pages.remove(index)?;
+        self.pages.push_back(entry);
+        self.pages.back().map(|(_, _, cached)| cached)
+    }
+
+    pub fn insert(&mut self, table: &str, page: u32, cached: CachedPage) {
+        self.pages
+            .retain(|((t, p), _, _)| !(t == table && *p == page));
+        if self.pages.len() >= CAPACITY {
+            self.pages.pop_front();
+        }
+        self.pages
+            .push_back(((table.to_string(), page), Instant::now(), cached));
+    }
+
+    pub fn count(&self, table: &str) -> Option<(i64, bool)> {
+        self.counts.get(table).map(|(_, count)| *count)
+    }
+
+    pub fn set_count(&mut self, table: &str, count: (i64, bool)) {
+        self.counts
+            .insert(table.to_string(), (Instant::now(), count));
+    }
+
+    /// Forget a table's count, e.g. when it should be counted another way.
+    pub fn forget_count(&mut self, table: &str) {
+        self.counts.remove(table);
+    }
+
+    /// Forget a table's pages but keep its count, e.g. when other columns are shown.
+    pub fn forget_pages(&mut self, table: &str) {
+        self.pages.retain(|((t, _), _, _)| t != table);
+    }
+
+    /// Forget everything cached for a table.
+    pub fn invalidate_table(&mut self, table: &str) {
+        self.pages.retain(|((t, _), _, _)| t != table);
+        self.counts.remove(table);
+    }
+
+    /// Forget what was cached for a table longer than `max_age` ago.
+    pub fn expire_table(&mut self, table: &str, max_age: Duration) {
+        self.pages
+            .retain(|((t, _), fetched, _)| t != table || fetched.elapsed() <= max_age);
+        if self
+            .counts
+            .get(table)
+            .is_some_and(|(fetched, _)| fetched.elapsed() > max_age)
+        {
+            self.counts.remove(table);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.pages.clear();
+        self.counts.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn page(value: &str) -> CachedPage {
+        CachedPage {
+            columns: vec!["id".to_string()],
+            rows: vec![vec![value.to_string()]],
+            sql: None,
+        }
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = PageCache::default();
+        for page_number in 0..CAPACITY as u32 {
+            cache.insert("users", page_number, page(&page_number.to_string()));
+        }
+        // Using page 0 makes page 1 the oldest
+        assert!(cache.get("users", 0).is_some());
+        cache.insert("projects", 0, page("p"));
+
+        assert_eq!(cache.get("users", 0), Some(&page("0")));
+        assert!(cache.get("users", 1).is_none());
+        assert_eq!(cache.get("projects", 0), Some(&page("p")));
+    }
+
+    #[test]
+    fn test_invalidate_table() {
+        let mut cache = PageCache::default();
+        cache.insert("users", 0, page("u"));
+        cache.insert("projects", 0, page("p"));
+        cache.set_count("users", (10, false));
+        cache.insert("users", 0, page("u2"));
+        assert_eq!(cache.get("users", 0), Some(&page("u2")));
+
+        cache.invalidate_table("users");
+        assert!(cache.get("users", 0).is_none());
+        assert_eq!(cache.count("users"), None);
+        assert!(cache.get("projects", 0).is_some());
+    }
+
+    #[test]
+    fn test_expire_table() {
+        let mut cache = PageCache::default();
+        cache.insert("users", 0, page("u"));
+        cache.set_count("users", (1, false));
+        cache.insert("projects", 0, page("p"));
+
+        cache.expire_table("users", Duration::from_secs(60));
+        assert!(cache.get("users", 0).is_some());
+        assert_eq!(cache.count("users"), Some((1, false)));
+
+        std::thread::sleep(Duration::from_millis(5));
+        cache.expire_table("users", Duration::from_millis(1));
+        assert!(cache.get("users", 0).is_none());
+        assert_eq!(cache.count("users"), None);
+        assert!(cache.get("projects", 0).is_some());
+    }
+}
@@ -0,0 +1,62 @@
+//! The form asking for the values of a custom query's `$n` parameters before it runs.
+
+#[derive(Debug, Clone)]
+pub struct ParamPrompt {
+    pub types: Vec<String>,  // The type the server expects for each parameter
+    pub values: Vec<String>, // As typed; `NULL` stands for SQL NULL
+    pub selected: usize,
+}
+
+impl ParamPrompt {
+    pub fn new(types: Vec<String>) -> ParamPrompt {
+        let values = vec![String::new(); types.len()];
+        ParamPrompt {
+            types,
+            values,
+            selected: 0,
+        }
+    }
+
+    pub fn input(&mut self) -> &mut String {
+        &mut self.values[self.selected]
+    }
+
+    /// Move to the next parameter, returning false if this was the last.
+    pub fn next(&mut self) -> bool {
+        if self.selected + 1 < self.values.len() {
+            self.selected += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// The values to bind, with `NULL` typed as a value standing for SQL NULL.
+    pub fn params(&self) -> Vec<Option<String>> {
+        self.values
+            .iter()
+            .map(|value| (value != "NULL").then(|| value.clone()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_values_in_order_with_null() {
+        let mut prompt = ParamPrompt::new(vec!["int4".to_string(), "text".to_string()]);
+        prompt.input().push_str("42");
+        assert!(prompt.next());
+        prompt.input().push_str("NULL");
+        assert!(!prompt.next());
+        prompt.previous();
+        assert_eq!(prompt.selected, 0);
+        assert_eq!(prompt.params(), [Some("42".to_string()), None]);
+    }
+}
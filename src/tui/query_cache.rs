@@ -0,0 +1,140 @@
+//! Pages of custom query results kept for a while, so running the same query again, or
+//! paging back to a page already seen, doesn't go to the server.
+//!
+//! Off unless `query_cache_ttl` is set, since a cached page can be out of date. Only
+//! read-only queries are cached, and the cache is emptied whenever something else runs.
+
+use crate::db::QueryPage;
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+/// A cached page with the query's total row count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResult {
+    pub page: QueryPage,
+    pub total_count: i64,
+    pub sql: Option<String>, // The statement that fetched the page
+}
+
+// The exact SQL, its parameter values and the page number
+type Key = (String, Vec<Option<String>>, u32);
+
+#[derive(Debug, Default)]
+pub struct QueryCache {
+    ttl: Option<Duration>,
+    entries: VecDeque<(Key, Instant, CachedResult)>, // Oldest first
+}
+
+// Pages kept across all queries
+const CAPACITY: usize = 32;
+
+impl QueryCache {
+    /// A cache whose entries last `ttl`, or one that caches nothing when it's None.
+    pub fn new(ttl: Option<Duration>) -> Self {
+        QueryCache {
+            ttl,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// How long entries last, or None when caching is off.
+    pub fn ttl(&self) -> Option<Duration> {
+        self.ttl
+    }
+
+    /// A cached page and how long ago it was fetched.
+    pub fn get(
+        &mut self,
+        sql: &str,
+        params: &[Option<String>],
+        page: u32,
+    ) -> Option<(Duration, &CachedResult)> {
+        let ttl = self.ttl?;
+        self.entries
+            .retain(|(_, cached_at, _)| cached_at.elapsed() <= ttl);
+        self.entries
+            .iter()
+            .find(|((s, p, n), _, _)| s == sql && p == params && *n == page)
+            .map(|(_, cached_at, cached)| (cached_at.elapsed(), cached))
+    }
+
+    pub fn insert(
+        &mut self,
+        sql: &str,
+        params: &[Option<String>],
+        page: u32,
+        result: CachedResult,
+    ) {
+        if self.ttl.is_none() {
+            return;
+        }
+        self.entries
+            .retain(|((s, p, n), _, _)| !(s == sql && p == params && *n == page));
+        if self.entries.len() >= CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back((
+            (sql.to_string(), params.to_vec(), page),
+            Instant::now(),
+            result,
+        ));
+    }
+
+    /// Forget every page of a query, e.g. when it's refreshed.
+    pub fn forget(&mut self, sql: &str) {
+        self.entries.retain(|((s, _, _), _, _)| s != sql);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(value: &str) -> CachedResult {
+        CachedResult {
+            page: QueryPage {
+                columns: vec!["v".to_string()],
+                rows: vec![vec![value.to_string()]],
+                total_rows: None,
+            },
+            total_count: 1,
+            sql: None,
+        }
+    }
+
+    #[test]
+    fn test_query_cache() {
+        let mut cache = QueryCache::new(Some(Duration::from_secs(60)));
+        cache.insert("select 1", &[], 0, result("1"));
+        cache.insert("select $1", &[Some("a".to_string())], 0, result("a"));
+        assert_eq!(cache.get("select 1", &[], 0).unwrap().1, &result("1"));
+        assert!(cache.get("select 1", &[], 1).is_none());
+        assert!(cache.get("select  1", &[], 0).is_none());
+        assert!(
+            cache
+                .get("select $1", &[Some("b".to_string())], 0)
+                .is_none()
+        );
+
+        cache.forget("select 1");
+        assert!(cache.get("select 1", &[], 0).is_none());
+        assert!(
+            cache
+                .get("select $1", &[Some("a".to_string())], 0)
+                .is_some()
+        );
+
+        let mut expiring = QueryCache::new(Some(Duration::from_millis(1)));
+        expiring.insert("select 1", &[], 0, result("1"));
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(expiring.get("select 1", &[], 0).is_none());
+
+        let mut off = QueryCache::new(None);
+        off.insert("select 1", &[], 0, result("1"));
+        assert!(off.get("select 1", &[], 0).is_none());
+    }
+}
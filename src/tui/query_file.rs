@@ -0,0 +1,49 @@
+//! Loading SQL from a file into the query input, for queries kept as `.sql` scripts.
+//!
+//! The path is typed into a prompt rather than a shell, so a leading `~` is expanded
+//! here; a relative path is taken from the directory the TUI was started in.
+
+use anyhow::{Result, anyhow};
+use std::fs;
+use std::path::PathBuf;
+
+/// The SQL in the file at `path`, without the line break editors add at the end.
+pub fn read(path: &str) -> Result<String> {
+    let path = path.trim();
+    if path.is_empty() {
+        return Err(anyhow!("No file given"));
+    }
+    let sql =
+        fs::read_to_string(expand_home(path)).map_err(|e| anyhow!("Can't read {}: {}", path, e))?;
+    Ok(sql.strip_suffix('\n').unwrap_or(&sql).to_string())
+}
+
+fn expand_home(path: &str) -> PathBuf {
+    match (path.strip_prefix("~/"), dirs::home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("report.sql");
+        fs::write(&path, "SELECT 1;\nSELECT 2;\n").unwrap();
+        let path = path.to_str().unwrap();
+        assert_eq!(read(path).unwrap(), "SELECT 1;\nSELECT 2;");
+
+        let missing = dir.path().join("missing.sql");
+        let error = read(missing.to_str().unwrap()).unwrap_err().to_string();
+        assert!(error.starts_with("Can't read "), "{}", error);
+        assert!(read("  ").is_err());
+
+        if let Some(home) = dirs::home_dir() {
+            assert_eq!(expand_home("~/q.sql"), home.join("q.sql"));
+        }
+    }
+}
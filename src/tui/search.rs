@@ -0,0 +1,123 @@
+//! Searching the text of the field detail view.
+//!
+//! Matches ignore case, like the list filter, and are found char by char so that a
+//! match never starts or ends inside a multibyte char.
+
+use std::ops::Range;
+
+/// A `/` search within a text, and which of its matches is current.
+#[derive(Debug, Clone, Default)]
+pub struct TextSearch {
+    pub query: String,
+    pub typing: bool,               // Whether keys still go to the query
+    pub matches: Vec<Range<usize>>, // Byte ranges into the text
+    pub current: usize,
+    pub scroll_pending: bool, // Whether the view should scroll to the current match
+}
+
+impl TextSearch {
+    pub fn new() -> Self {
+        TextSearch {
+            typing: true,
+            ..Default::default()
+        }
+    }
+
+    /// Find the query's matches in `text` again after it changed, making the first match
+    /// the current one.
+    pub fn update(&mut self, text: &str) {
+        self.matches = find_matches(text, &self.query);
+        self.current = 0;
+        self.scroll_pending = !self.matches.is_empty();
+    }
+
+    /// Make the next match current, or the previous one, wrapping around at either end.
+    pub fn step(&mut self, forward: bool) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        self.current = if forward {
+            (self.current + 1) % len
+        } else {
+            (self.current + len - 1) % len
+        };
+        self.scroll_pending = true;
+    }
+
+    pub fn current_match(&self) -> Option<&Range<usize>> {
+        self.matches.get(self.current)
+    }
+
+    /// The search as shown in the view's title, e.g. `/json (2 of 5)`.
+    pub fn status(&self) -> String {
+        let cursor = if self.typing { "_" } else { "" };
+        let position = match self.matches.len() {
+            _ if self.query.is_empty() => String::new(),
+            0 => " (no matches)".to_string(),
+            len => format!(" ({} of {})", self.current + 1, len),
+        };
+        format!("/{}{}{}", self.query, cursor, position)
+    }
+}
+
+/// The byte ranges of the non-overlapping matches of `query` in `text`, ignoring case.
+pub fn find_matches(text: &str, query: &str) -> Vec<Range<usize>> {
+    let query: Vec<char> = query.chars().collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let mut matches = Vec::new();
+    let mut i = 0;
+    while i + query.len() <= chars.len() {
+        let found = chars[i..i + query.len()]
+            .iter()
+            .zip(&query)
+            .all(|(&(_, c), &q)| c == q || c.to_lowercase().eq(q.to_lowercase()));
+        if found {
+            let end = chars
+                .get(i + query.len())
+                .map_or(text.len(), |&(offset, _)| offset);
+            matches.push(chars[i].0..end);
+            i += query.len();
+        } else {
+            i += 1;
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_matches() {
+        assert_eq!(find_matches("{\"id\": 1, \"ID\": 2}", "id"), [2..4, 11..13]);
+        assert_eq!(find_matches("aaaa", "aa"), [0..2, 2..4]);
+        assert!(find_matches("users", "").is_empty());
+        assert!(find_matches("users", "orders").is_empty());
+        // Byte offsets, past the two-byte "é"
+        assert_eq!(find_matches("café CAFÉ", "é"), [3..5, 9..11]);
+    }
+
+    #[test]
+    fn test_step_wraps_around() {
+        let mut search = TextSearch::new();
+        search.query = "a".to_string();
+        search.update("a b a b a");
+        assert_eq!(search.status(), "/a_ (1 of 3)");
+        search.step(false);
+        assert_eq!(search.current_match(), Some(&(8..9)));
+        search.step(true);
+        search.typing = false;
+        assert_eq!(search.status(), "/a (1 of 3)");
+
+        search.query = "c".to_string();
+        search.update("a b a b a");
+        search.step(true);
+        assert_eq!(search.current_match(), None);
+        assert_eq!(search.status(), "/c (no matches)");
+    }
+}
@@ -0,0 +1,134 @@
+//! Colors and text styles used by the render functions.
+
+use crate::config::ThemeName;
+use ratatui::style::{Color, Modifier, Style};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub status: Style,          // Transient messages in the status bar
+    pub error: Style,           // Error messages and failed statements
+    pub list_selection: Style,  // The selected connection or table
+    pub row_selection: Style,   // The selected row in a results table
+    pub row_marked: Style,      // Rows of the table view marked for copying
+    pub field_selection: Style, // The selected field, and the selected completion
+    pub header: Style,          // Column names
+    pub header_types: Style,    // Column types under the names
+    pub help: Style,            // Key hints below a view
+    pub muted: Style,           // Statements of a batch that were not run
+    pub text: Style,            // Field values in the detail view
+    pub accent: Style,          // Connection status and plain query text
+    pub search_match: Style, // Matches of a search in the detail view, the current one being field_selection
+    // Query syntax highlighting
+    pub keyword: Style,
+    pub string: Style,
+    pub number: Style,
+    pub comment: Style,
+}
+
+impl Theme {
+    pub fn preset(name: ThemeName) -> Theme {
+        match name {
+            ThemeName::Default => Theme::default(),
+            ThemeName::HighContrast => Theme {
+                status: Style::default()
+                    .fg(Color::LightGreen)
+                    .add_modifier(Modifier::BOLD),
+                error: Style::default()
+                    .fg(Color::White)
+                    .bg(Color::Red)
+                    .add_modifier(Modifier::BOLD),
+                list_selection: Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+                row_selection: Style::default().fg(Color::Black).bg(Color::Gray),
+                row_marked: Style::default()
+                    .fg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD | Modifier::REVERSED),
+                field_selection: Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::LightYellow)
+                    .add_modifier(Modifier::BOLD),
+                header: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                header_types: Style::default().add_modifier(Modifier::ITALIC),
+                help: Style::default().fg(Color::White),
+                muted: Style::default().fg(Color::Gray),
+                text: Style::default().fg(Color::White),
+                accent: Style::default().fg(Color::LightYellow),
+                search_match: Style::default().fg(Color::Black).bg(Color::LightCyan),
+                keyword: Style::default()
+                    .fg(Color::LightCyan)
+                    .add_modifier(Modifier::BOLD),
+                string: Style::default().fg(Color::LightGreen),
+                number: Style::default().fg(Color::LightMagenta),
+                comment: Style::default()
+                    .fg(Color::Gray)
+                    .add_modifier(Modifier::ITALIC),
+            },
+            // Blue and yellow/orange stay distinguishable with the common forms of
+            // color blindness, so errors are yellow and selections blue
+            ThemeName::ColorblindSafe => Theme {
+                status: Style::default().fg(Color::LightBlue),
+                error: Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+                list_selection: Style::default()
+                    .fg(Color::White)
+                    .bg(Color::Blue)
+                    .add_modifier(Modifier::BOLD),
+                row_selection: Style::default().fg(Color::White).bg(Color::DarkGray),
+                row_marked: Style::default()
+                    .fg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                field_selection: Style::default().fg(Color::Black).bg(Color::Yellow),
+                header: Style::default().add_modifier(Modifier::BOLD),
+                header_types: Style::default().add_modifier(Modifier::ITALIC),
+                help: Style::default().add_modifier(Modifier::ITALIC),
+                muted: Style::default().fg(Color::DarkGray),
+                text: Style::default(),
+                accent: Style::default().fg(Color::LightCyan),
+                search_match: Style::default().fg(Color::White).bg(Color::Blue),
+                keyword: Style::default()
+                    .fg(Color::LightBlue)
+                    .add_modifier(Modifier::BOLD),
+                string: Style::default().fg(Color::Yellow),
+                number: Style::default().fg(Color::LightMagenta),
+                comment: Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::ITALIC),
+            },
+        }
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            status: Style::default().fg(Color::Green),
+            error: Style::default().fg(Color::Red),
+            list_selection: Style::default()
+                .bg(Color::LightGreen)
+                .add_modifier(Modifier::BOLD),
+            row_selection: Style::default().bg(Color::LightBlue),
+            row_marked: Style::default()
+                .fg(Color::Magenta)
+                .add_modifier(Modifier::BOLD),
+            field_selection: Style::default().bg(Color::Yellow).fg(Color::Black),
+            header: Style::default().add_modifier(Modifier::BOLD),
+            header_types: Style::default().add_modifier(Modifier::ITALIC),
+            help: Style::default().add_modifier(Modifier::ITALIC),
+            muted: Style::default().fg(Color::DarkGray),
+            text: Style::default().fg(Color::White),
+            accent: Style::default().fg(Color::Yellow),
+            search_match: Style::default().fg(Color::Black).bg(Color::Cyan),
+            keyword: Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+            string: Style::default().fg(Color::Green),
+            number: Style::default().fg(Color::Magenta),
+            comment: Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::ITALIC),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Friendlier display of timestamp columns in the table view.
+//!
+//! Values arrive as the server's text form of the timestamp. Anything that doesn't parse,
+//! such as `infinity` or a BC date, is shown unchanged.
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, FixedOffset, Local, NaiveDateTime, TimeZone, Utc};
+
+pub const DEFAULT_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TimestampDisplay {
+    #[default]
+    Raw,
+    Relative,  // "3 days ago"
+    Formatted, // With `settings.timestamp_format`, in local time
+}
+
+impl TimestampDisplay {
+    pub fn next(self) -> TimestampDisplay {
+        match self {
+            TimestampDisplay::Raw => TimestampDisplay::Relative,
+            TimestampDisplay::Relative => TimestampDisplay::Formatted,
+            TimestampDisplay::Formatted => TimestampDisplay::Raw,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TimestampDisplay::Raw => "raw",
+            TimestampDisplay::Relative => "relative",
+            TimestampDisplay::Formatted => "formatted",
+        }
+    }
+}
+
+/// Whether a column type as reported by `information_schema` is a timestamp.
+pub fn is_timestamp_type(data_type: &str) -> bool {
+    data_type.starts_with("timestamp")
+}
+
+/// `value` shown the way `display` asks for, or `None` to show it unchanged.
+pub fn display(value: &str, display: TimestampDisplay, format: &str) -> Option<String> {
+    let time = parse(value)?;
+    match display {
+        TimestampDisplay::Raw => None,
+        TimestampDisplay::Relative => Some(relative(time, Utc::now())),
+        TimestampDisplay::Formatted => {
+            // An invalid format would panic when displayed
+            let items: Vec<Item> = StrftimeItems::new(format).collect();
+            if items.contains(&Item::Error) {
+                return None;
+            }
+            Some(
+                time.with_timezone(&Local)
+                    .format_with_items(items.into_iter())
+                    .to_string(),
+            )
+        }
+    }
+}
+
+// Values with an offset are `timestamptz`; values without are taken as local time
+fn parse(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(time) = DateTime::<FixedOffset>::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f%#z") {
+        return Some(time.with_timezone(&Utc));
+    }
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f").ok()?;
+    Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+/// How long before or after `now` the time was, in its largest whole unit.
+pub fn relative(time: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let seconds = (now - time).num_seconds();
+    let (amount, unit) = match seconds.abs() {
+        s if s < 60 => return "just now".to_string(),
+        s if s < 3600 => (s / 60, "minute"),
+        s if s < 86_400 => (s / 3600, "hour"),
+        s if s < 30 * 86_400 => (s / 86_400, "day"),
+        s if s < 365 * 86_400 => (s / (30 * 86_400), "month"),
+        s => (s / (365 * 86_400), "year"),
+    };
+    let plural = if amount == 1 { "" } else { "s" };
+    if seconds > 0 {
+        format!("{} {}{} ago", amount, unit, plural)
+    } else {
+        format!("in {} {}{}", amount, unit, plural)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 15, 12, 0, 0).unwrap();
+        let ago = |seconds: i64| relative(now - chrono::Duration::seconds(seconds), now);
+        assert_eq!(ago(30), "just now");
+        assert_eq!(ago(60), "1 minute ago");
+        assert_eq!(ago(3 * 3600 + 59), "3 hours ago");
+        assert_eq!(ago(3 * 86_400), "3 days ago");
+        assert_eq!(ago(65 * 86_400), "2 months ago");
+        assert_eq!(ago(800 * 86_400), "2 years ago");
+        assert_eq!(ago(-2 * 86_400), "in 2 days");
+    }
+
+    #[test]
+    fn test_parse_server_text() {
+        let expected = Utc.with_ymd_and_hms(2024, 6, 15, 10, 30, 0).unwrap();
+        assert_eq!(parse("2024-06-15 12:30:00+02"), Some(expected));
+        assert_eq!(
+            parse("2024-06-15 10:30:00.123+00:00").map(|t| t.timestamp()),
+            Some(expected.timestamp())
+        );
+        assert!(parse("2024-06-15 10:30:00.5").is_some());
+        assert_eq!(parse("infinity"), None);
+        assert_eq!(parse("NULL"), None);
+    }
+
+    #[test]
+    fn test_display_leaves_raw_and_bad_formats_alone() {
+        let value = "2024-06-15 10:30:00+00";
+        assert_eq!(display(value, TimestampDisplay::Raw, DEFAULT_FORMAT), None);
+        assert_eq!(display(value, TimestampDisplay::Formatted, "%Q"), None);
+        assert!(display(value, TimestampDisplay::Formatted, "%Y").is_some());
+        assert_eq!(
+            display("NULL", TimestampDisplay::Relative, DEFAULT_FORMAT),
+            None
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! How long the last page of rows took to get from the server, shown in the results
+//! block's title.
+//!
+//! Fetching the page and counting the rows are timed apart, since the count often takes
+//! far longer than the page. A part that was answered from a cache isn't timed.
+
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct QueryTiming {
+    pub execute: Option<Duration>, // Running the query for the page, or the statement
+    pub count: Option<Duration>,   // Counting the rows, when that was a separate query
+}
+
+impl QueryTiming {
+    /// E.g. `Executed in 143ms, counted in 1.20s`, or None if nothing was timed.
+    pub fn label(&self) -> Option<String> {
+        let parts: Vec<String> = [("executed", self.execute), ("counted", self.count)]
+            .into_iter()
+            .filter_map(|(what, duration)| Some(format!("{} in {}", what, format(duration?))))
+            .collect();
+        if parts.is_empty() {
+            return None;
+        }
+        let label = parts.join(", ");
+        Some(label[..1].to_uppercase() + &label[1..])
+    }
+}
+
+// Milliseconds, with a decimal while they're few, then seconds past one
+fn format(duration: Duration) -> String {
+    let millis = duration.as_secs_f64() * 1000.0;
+    match millis {
+        m if m < 10.0 => format!("{:.1}ms", m),
+        m if m < 1000.0 => format!("{:.0}ms", m),
+        m => format!("{:.2}s", m / 1000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_timing_label() {
+        let timing = QueryTiming {
+            execute: Some(Duration::from_micros(2400)),
+            count: Some(Duration::from_millis(1204)),
+        };
+        assert_eq!(
+            timing.label().unwrap(),
+            "Executed in 2.4ms, counted in 1.20s"
+        );
+        let timing = QueryTiming {
+            execute: Some(Duration::from_millis(143)),
+            count: None,
+        };
+        assert_eq!(timing.label().unwrap(), "Executed in 143ms");
+        let timing = QueryTiming {
+            execute: None,
+            count: Some(Duration::from_millis(40)),
+        };
+        assert_eq!(timing.label().unwrap(), "Counted in 40ms");
+        assert_eq!(QueryTiming::default().label(), None);
+    }
+}